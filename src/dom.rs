@@ -35,26 +35,38 @@ fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
 */
 
 use std::any::Any;
-use std::cell::OnceCell;
+use std::borrow::Cow;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Formatter;
 
 use std::hash::{Hash, Hasher};
+use std::ops::Range;
 use std::path::Path;
+use std::sync::OnceLock;
 use regex::Regex;
 use crate::errors::*;
+use crate::{OutputFilter, OutputOptions};
 
 /**
 A Document represents a DOM plus additional (optional) metadata such as one or more Document Type Declarations (DTD). Use this struct to write a DOM to a string or file.
 */
+#[derive(Clone)]
 pub struct Document {
 	/// Optional XML declaration (ie `<?xml version="1.0" encoding="UTF-8"?>`)
 	declaration: Option<Declaration>,
 	/// Doctype defs, if any
 	dtds: Vec<DTD>,
 	/// Root element (multi-element XML docs not supported)
-	root_element: Element
+	root_element: Element,
+	/// Byte spans of the elements in the original source (only when requested at parse time)
+	source_spans: Option<SourceMapTable>,
+	/// Unrecognized markup outside the root element, kept verbatim (only created by the parser)
+	opaque_markup: Vec<OpaqueMarkup>,
+	/// Warnings about the source XML found while parsing (only created by the parser)
+	parse_warnings: Vec<crate::lint::LintWarning>,
+	/// If true, this document is a fragment, which is written without a trailing newline
+	fragment: bool
 }
 
 impl Document {
@@ -65,6 +77,24 @@ Constructs a new Document with the given root element and default declaration
 		Document::new_with_decl_dtd(root, Some(Declaration::default()), None)
 	}
 	/**
+Constructs a new fragment Document with the given root element, which has no XML declaration and is written without a trailing newline (see [is_fragment()](Document::is_fragment()))
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml::dom::*;
+		let doc = Document::new_fragment(Element::new_with_text("motd", "hello!")?);
+		assert!(doc.is_fragment());
+		assert_eq!(doc.to_string(), "<motd>hello!</motd>");
+		Ok(())
+	}
+	```
+	 */
+	pub fn new_fragment(root: Element) -> Self {
+		let mut doc = Document::new_with_decl_dtd(root, None, None);
+		doc.fragment = true;
+		doc
+	}
+	/**
 Full constructor with required root element and optional XML declaration and optional list of one or more document type definition (DTD) items.
 	 */
 	pub fn new_with_decl_dtd(root: Element, declaration: Option<Declaration>, dtd: Option<&[DTD]>) -> Self {
@@ -74,22 +104,60 @@ Full constructor with required root element and optional XML declaration and opt
 				None => Vec::with_capacity(1),
 				Some(dtds) => Vec::from(dtds)
 			},
-			root_element: root
+			root_element: root,
+			source_spans: None,
+			opaque_markup: Vec::new(),
+			parse_warnings: Vec::new(),
+			fragment: false
 		}
 	}
 	/**
 	Returns a list of any and all DTDs for this Document as an iterator
 	 */
-	pub fn doctype_defs(&self) -> impl Iterator<Item = &DTD> {
+	pub fn doctype_defs(&self) -> impl ExactSizeIterator<Item = &DTD> {
 		self.dtds.iter()
 	}
 	/**
 	Returns a list of any and all DTDs for this Document as an iterator
 	 */
-	pub fn doctype_defs_mut(&mut self) -> impl Iterator<Item = &mut DTD> {
+	pub fn doctype_defs_mut(&mut self) -> impl ExactSizeIterator<Item = &mut DTD> {
 		self.dtds.iter_mut()
 	}
 	/**
+	Returns the number of DTDs in this Document
+	 */
+	pub fn dtd_count(&self) -> usize {
+		self.dtds.len()
+	}
+	/**
+	Returns true if this Document has at least one DTD
+	 */
+	pub fn has_dtd(&self) -> bool {
+		!self.dtds.is_empty()
+	}
+	/**
+	Returns the DTD at the given index (in the order they appear in the document), or `None` if the index is out of range. Indices only change when the DTDs are replaced with [set_doctype_defs(...)](Document::set_doctype_defs()).
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		let doc = kiss_xml::parse_str("<!DOCTYPE note SYSTEM \"note.dtd\">\n<note/>")?;
+		assert_eq!(doc.dtd_count(), 1);
+		assert_eq!(doc.dtd(0).unwrap().name(), "note");
+		assert!(doc.dtd(1).is_none());
+		Ok(())
+	}
+	```
+	 */
+	pub fn dtd(&self, index: usize) -> Option<&DTD> {
+		self.dtds.get(index)
+	}
+	/**
+	Returns the DTD at the given index as a mutable reference, or `None` if the index is out of range
+	 */
+	pub fn dtd_mut(&mut self, index: usize) -> Option<&mut DTD> {
+		self.dtds.get_mut(index)
+	}
+	/**
 Sets the DTDs for this document (a `None` argument will remove all DTDs)
 	 */
 	pub fn set_doctype_defs(&mut self, dtds: Option<&[DTD]>) {
@@ -108,7 +176,114 @@ Gets the XML declaration for this document, if it has one (while the XML spec re
 Sets the XML declaration for this document (a `None` argument will remove any existing declaration). While the XML spec requires a declaration at the start of every XML file, it is commonly omitted, especially when the XML is embedded in a stream or file.
 	 */
 	pub fn set_declaration(&mut self, decl: Declaration) {
-		self.declaration = Some(decl)
+		self.declaration = Some(decl);
+		self.fragment = false;
+	}
+	/**
+	Returns true if this document is a fragment: a document without an XML declaration that is written without a trailing newline, such as an XML file that is included into another XML file as text. Documents constructed with [new_fragment(...)](Document::new_fragment()) are fragments, and the parser marks a document as a fragment when the XML has no declaration and does not end with a newline, so that loading, editing, and saving a fragment keeps its style.
+
+	Note that this means that parsing XML without a declaration or a trailing newline (eg `parse_str("<root/>")`) and writing it again gives XML without a trailing newline, which was not the case in kiss-xml 1.0. To always write a trailing newline, use the `trailing_newline` output option (see [OutputOptions](crate::OutputOptions)) or call [set_fragment(false)](Document::set_fragment()).
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		let mut include = kiss_xml::parse_str("<sound>\n  <volume>11</volume>\n</sound>")?;
+		assert!(include.is_fragment());
+		include.root_element_mut().first_element_by_name_mut("volume")?.set_text("7");
+		assert_eq!(include.to_string(), "<sound>\n  <volume>7</volume>\n</sound>");
+		let options = kiss_xml::OutputOptions::new().trailing_newline(Some(true));
+		assert_eq!(include.to_string_with_options(&options), "<sound>\n  <volume>7</volume>\n</sound>\n");
+		assert!(!kiss_xml::parse_str("<sound/>\n")?.is_fragment());
+		assert!(!kiss_xml::parse_str("<?xml version=\"1.0\"?><sound/>")?.is_fragment());
+		Ok(())
+	}
+	```
+	 */
+	pub fn is_fragment(&self) -> bool {
+		self.fragment
+	}
+	/**
+	Makes this document a fragment (see [is_fragment()](Document::is_fragment())), removing its XML declaration (if any), or makes it a normal document that is written with a trailing newline. Setting a declaration with [set_declaration(...)](Document::set_declaration()) also makes a fragment a normal document.
+	 */
+	pub fn set_fragment(&mut self, fragment: bool) {
+		if fragment {
+			self.declaration = None;
+		}
+		self.fragment = fragment;
+	}
+
+	/**
+	Replaces the content of every comment in this document's root element (recursively) that matches the given predicate with the given replacement text, returning the number of replaced comments. This is useful for golden-file testing of generated documents, for example to replace "generated on ..." timestamps with a fixed placeholder. If the replacement is not valid comment content, an error result is returned and the document is not modified.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		use kiss_xml::dom::*;
+		use chrono::Utc;
+		let generate = || -> Result<Document, kiss_xml::errors::KissXmlError> {
+			Ok(Document::new(Element::new_with_children("root", vec![
+				Comment::new(format!("generated on {}", Utc::now().to_rfc3339()))?.boxed(),
+				Element::new_with_text("motd", "hello!")?.boxed()
+			])?))
+		};
+		let mut doc = generate()?;
+		let count = doc.scrub_comments(|c| c.get_content().starts_with("generated on "), "generated on <timestamp>")?;
+		assert_eq!(count, 1);
+		assert_eq!(doc.root_element().to_string(), "<root>\n  <!--generated on <timestamp>-->\n  <motd>hello!</motd>\n</root>");
+		Ok(())
+	}
+	```
+	 */
+	pub fn scrub_comments<P>(&mut self, predicate: P, replacement: &str) -> Result<usize, InvalidContent> where P: Fn(&Comment) -> bool {
+		self.root_element.scrub_comments(predicate, replacement)
+	}
+
+	/**
+	Removes every attribute with an empty value (eg `id=""`) from all of the elements in this document, returning the number of removed attributes. Empty namespace declarations (eg `xmlns=""`) are kept (see [Element::remove_empty_attrs()](Element::remove_empty_attrs())).
+	 */
+	pub fn remove_empty_attrs(&mut self) -> usize {
+		self.root_element.remove_empty_attrs_recursive()
+	}
+
+	/**
+	Compares this document to another, the same as `==` but ignoring any comments in the root elements for which the given predicate returns true (use `|_| true` to ignore all comments). This is useful for golden-file testing of generated documents that contain comments that change every time, such as timestamps.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		let a = kiss_xml::parse_str("<root><!--generated on Monday--><a/><!--note--></root>")?;
+		let b = kiss_xml::parse_str("<root><a/><!--generated on Tuesday--><!--note--></root>")?;
+		assert!(a != b);
+		assert!(a.eq_ignore_comments(&b, |c| c.get_content().starts_with("generated on ")));
+		assert!(!a.eq_ignore_comments(&b, |c| c.get_content() == "note"));
+		Ok(())
+	}
+	```
+	 */
+	pub fn eq_ignore_comments<P>(&self, other: &Document, predicate: P) -> bool where P: Fn(&Comment) -> bool {
+		self.declaration == other.declaration
+		&& self.dtds == other.dtds
+		&& self.root_element.eq_ignore_comments(&other.root_element, predicate)
+		&& self.opaque_markup == other.opaque_markup
+	}
+
+	/**
+	Returns true if this document equals the other one (see the `PartialEq` contract for [Document]) and both documents are also written as exactly the same XML text by [to_string()](Document::to_string()). This is stricter than `==`, eg a DTD parsed from `<!DOCTYPE  note>` equals one parsed from `<!DOCTYPE note>`, but the two are written differently.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		let a = kiss_xml::parse_str("<!DOCTYPE  note>\n<note/>")?;
+		let b = kiss_xml::parse_str("<!DOCTYPE note>\n<note/>")?;
+		assert!(a == b);
+		assert!(!a.identical_to(&b));
+		assert!(a.identical_to(&a.clone()));
+		Ok(())
+	}
+	```
+	*/
+	pub fn identical_to(&self, other: &Document) -> bool {
+		self == other && self.to_string() == other.to_string()
 	}
 
 	/**
@@ -126,14 +301,56 @@ Produces the XML text representing this XML DOM using the default indent of two
 		default indent used instead)
 	 */
 	pub fn to_string_with_indent(&self, indent: impl Into<String>) -> String {
-		let mut indent = indent.into();
-		match crate::validate_indent(indent.as_str()){
-			Ok(_) => {},
-			Err(_) => {
-				eprintln!("WARNING: {:?} is not a valid indentation. Must be either 1 tab or any number of spaces. The default of 2 spaces will be used instead", indent);
-				indent = "  ".to_string();
-			}
+		self.to_string_with_options(&OutputOptions{indent: indent.into(), ..Default::default()})
+	}
+
+	/**
+	Produces the XML text representing this XML DOM using the provided output options (see [OutputOptions] for details).
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		let doc = kiss_xml::parse_str("<root><a/></root>")?;
+		let options = kiss_xml::OutputOptions{indent: "\t".to_string(), space_before_self_close: true, ..Default::default()};
+		assert_eq!(doc.to_string_with_options(&options), "<root>\n\t<a />\n</root>");
+		Ok(())
+	}
+	```
+	 */
+	pub fn to_string_with_options(&self, options: &OutputOptions) -> String {
+		self.write_to_string(options, None)
+	}
+
+	/**
+	Produces the XML text for a redacted projection of this XML DOM, skipping the elements and omitting the attributes denied by the given filter (see [OutputFilter] for details). The DOM itself is not modified.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		let doc = kiss_xml::parse_str(r#"<root><a secret="1"/><b/><b/></root>"#)?;
+		let filter = kiss_xml::OutputFilter{
+			deny_paths: vec!["/root/b[2]".to_string()],
+			deny_attributes: vec!["secret".to_string()],
+			..Default::default()
 		};
+		assert_eq!(doc.to_string_filtered(&filter), "<root>\n  <a/>\n  <b/>\n</root>");
+		Ok(())
+	}
+	```
+	 */
+	pub fn to_string_filtered(&self, filter: &OutputFilter) -> String {
+		let mut denied: HashSet<*const Element> = HashSet::new();
+		for path in &filter.deny_paths {
+			if let Ok(Some(e)) = self.element_at_path(path.as_str()) {
+				denied.insert(e as *const Element);
+			}
+		}
+		self.write_to_string(&filter.output_options, Some(&FilterContext{filter, denied}))
+	}
+
+	/// writes this document as XML, applying the filter (if any)
+	fn write_to_string(&self, options: &OutputOptions, filter: Option<&FilterContext>) -> String {
+		let options = checked_output_options(options);
 		let mut builder = String::new();
 		match &self.declaration{
 			None => {},
@@ -142,12 +359,19 @@ Produces the XML text representing this XML DOM using the default indent of two
 				builder.push_str("\n");
 			}
 		}
-		for dtd in &self.dtds {
+		for (i, dtd) in self.dtds.iter().enumerate() {
+			self.write_opaque_markup(&mut builder, |p| p == MarkupPlacement::Prologue(i));
 			builder.push_str(dtd.to_string().as_str());
 			builder.push_str("\n");
 		}
-		builder.push_str(&self.root_element.to_string_with_indent(indent.as_str()));
+		let dtd_count = self.dtds.len();
+		self.write_opaque_markup(&mut builder, |p| matches!(p, MarkupPlacement::Prologue(i) if i >= dtd_count));
+		builder.push_str(&self.root_element.to_string_with_prefix_and_options("", &options, false, filter, &HashMap::new()));
 		builder.push_str("\n");
+		self.write_opaque_markup(&mut builder, |p| p == MarkupPlacement::Epilogue);
+		if !options.trailing_newline.unwrap_or(!self.fragment) {
+			builder.pop();
+		}
 		return builder;
 	}
 
@@ -199,135 +423,767 @@ Produces the XML text representing this XML DOM using the default indent of two
 	pub fn root_element_mut(&mut self) -> &mut Element {
 		&mut self.root_element
 	}
-}
 
-impl std::fmt::Display for Document{
-	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-		write!(f, "{}", self.to_string_with_indent("  "))
+	/**
+	Gets the value of an attribute of the root element by name (same as `root_element().get_attr(...)`), eg `doc.root_attr("version")`
+	 */
+	pub fn root_attr(&self, attr_name: impl Into<String>) -> Option<&String> {
+		self.root_element.get_attr(attr_name)
 	}
-}
 
-impl std::fmt::Debug for Document{
-	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-		write!(f, "{}", self.to_string_with_indent("  "))
+	/**
+	Sets the value of an attribute of the root element (same as `root_element_mut().set_attr(...)`), eg `doc.set_root_attr("xmlns:x", "internal://x")?`. Setting an `xmlns:prefix` attribute declares that namespace prefix for any elements appended under the root afterwards.
+	 */
+	pub fn set_root_attr(&mut self, attr_name: impl Into<String>, value: impl Into<String>) -> Result<(), InvalidAttributeName> {
+		self.root_element.set_attr(attr_name, value)
 	}
-}
 
-impl PartialEq<Self> for Document {
-	fn eq(&self, other: &Self) -> bool {
-		self.declaration == other.declaration
-		&& self.dtds == other.dtds
-		&& self.root_element == other.root_element
+	/**
+	Returns the tag name of the root element (same as `root_element().name()`), eg `assert_eq!(doc.root_name(), "config")`
+	 */
+	pub fn root_name(&self) -> String {
+		self.root_element.name()
 	}
-}
-
-/** This enum lists the types of XML DOM nodes used in kiss_xml, useful for runtime reflection. */
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
-pub enum DomNodeType {
-	/// node type is CDATA
-	CDataNode,
-	/// node type is Comment
-	CommentNode,
-	/// node type is Element
-	ElementNode,
-	/// node type is Text
-	TextNode
-}
 
-impl From<Box<dyn Node>> for DomNodeType {
-	fn from(value: Box<dyn Node>) -> Self {
-		value.node_type()
+	/**
+	Returns the namespace of the root element, if any (same as `root_element().namespace()`), eg `doc.root_namespace().unwrap_or_default()`
+	 */
+	pub fn root_namespace(&self) -> Option<String> {
+		self.root_element.namespace()
 	}
-}
 
-impl std::fmt::Display for DomNodeType {
-	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-		match self {
-			DomNodeType::CDataNode => write!(f, "CDATA"),
-			DomNodeType::CommentNode => write!(f, "Comment"),
-			DomNodeType::ElementNode => write!(f, "Element"),
-			DomNodeType::TextNode => write!(f, "Text"),
+	/**
+	Finds the element at the given path, returning `None` if there is no such element, or an error result if the path is not valid. Paths are slash-separated element names (namespace prefixes are ignored), where each name may have a 1-based index in square brackets to select the Nth sibling element with that name (no index is the same as `[1]`). An absolute path (starting with `/`) begins with the root element, while a relative path is resolved from the root element.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml::dom::Node;
+		let doc = kiss_xml::parse_str("<root><a>1</a><a>2</a></root>")?;
+		assert_eq!(doc.element_at_path("/root/a[2]")?.unwrap().text(), "2");
+		assert_eq!(doc.element_at_path("a")?.unwrap().text(), "1");
+		assert!(doc.element_at_path("/root/b")?.is_none());
+		Ok(())
+	}
+	```
+	 */
+	pub fn element_at_path(&self, path: &str) -> Result<Option<&Element>, KissXmlError> {
+		let parsed = crate::paths::ElementPath::parse(path)?;
+		let segments = match self.relative_segments(&parsed) {
+			None => return Ok(None),
+			Some(segments) => segments
+		};
+		let mut elem = &self.root_element;
+		for seg in segments {
+			match elem.nth_child_element_index(seg.name.as_str(), seg.index) {
+				None => return Ok(None),
+				Some(i) => elem = elem.child_nodes[i].as_element().expect("logic error")
+			}
 		}
+		Ok(Some(elem))
 	}
-}
-
-/**
-A node in the DOM tree. Elements, Comments, and Text are all types of nodes, but only Elements can be branch nodes with children of their own.
- */
-pub trait Node: dyn_clone::DynClone + std::fmt::Debug + std::fmt::Display + ToString {
 
 	/**
-	Returns the text content of the node. For a Comment, CData, or Text node, this is just the comment or text string. For an Element, this will return *all* text nodes (including from child elements, recursive scan) as a single string, or an empty string if this element has no child text nodes
-	 */
-	fn text(&self) -> String;
+	Removes every element matching any of the given paths (see [element_at_path(...)](Document::element_at_path()) for the path syntax), along with all of its content, returning the number of elements removed. All of the paths are resolved in a single traversal of the document before anything is removed, so indices in the paths refer to the document as it was before this call. Paths that do not match any element are ignored (use [remove_paths_with_misses(...)](Document::remove_paths_with_misses()) to find out which ones). If a path points inside another removed element, it is removed along with its ancestor and not counted separately.
 
-	/**
-	Returns `true` if this Node trait object is an Element struct, otherwise `false`
+	Returns an error result if any path is invalid or if a path matches the root element (which cannot be removed), in which case the document is not modified.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		let mut doc = kiss_xml::parse_str("<root><a><b/></a><c/><d/></root>")?;
+		assert_eq!(doc.remove_paths(&["/root/a", "/root/a/b", "d", "/root/x"])?, 2);
+		assert_eq!(doc.root_element().to_string(), "<root>\n  <c/>\n</root>");
+		Ok(())
+	}
+	```
 	 */
-	fn is_element(&self) -> bool;
+	pub fn remove_paths(&mut self, paths: &[&str]) -> Result<usize, KissXmlError> {
+		self.remove_paths_with_misses(paths).map(|(count, _)| count)
+	}
 
 	/**
-	Returns `true` if this Node trait object is a Text struct, otherwise `false`
+	Same as [remove_paths(...)](Document::remove_paths()), but also returns the list of paths that did not match any element (in the order they were given).
 	 */
-	fn is_text(&self) -> bool;
+	pub fn remove_paths_with_misses(&mut self, paths: &[&str]) -> Result<(usize, Vec<String>), KissXmlError> {
+		let mut trie = crate::paths::PathTrie::default();
+		let mut matched = vec![false; paths.len()];
+		for (id, path) in paths.iter().enumerate() {
+			let parsed = crate::paths::ElementPath::parse(path)?;
+			match self.relative_segments(&parsed) {
+				None => {/* cannot match */},
+				Some([]) => return Err(InvalidContent::new(format!(
+					"cannot remove root element (path '{path}')"
+				)).into()),
+				Some(segments) => trie.insert(segments, id)
+			}
+		}
+		let count = self.root_element.remove_path_matches(&trie, &mut matched, false);
+		let misses = paths.iter().zip(matched)
+			.filter(|(_, m)| !m)
+			.map(|(p, _)| p.to_string())
+			.collect();
+		Ok((count, misses))
+	}
 
 	/**
-	Returns `true` if this Node trait object is a Comment struct, otherwise `false`
+	Copies the first element matching the given selector (either a path, see [element_at_path(...)](Document::element_at_path()), or an [ElementSelector::Predicate] which is tested against every element in document order) into a new standalone Document with a default XML declaration. Any namespace declarations from ancestor elements that are needed by the copied subtree are added to the new root element, while unused ones are left out. Returns a `DoesNotExistError` if no element matches.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		let doc = kiss_xml::parse_str(r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:x="internal://x"><g id="layer1"><path/></g></svg>"#)?;
+		let layer = doc.extract_subtree("/svg/g")?;
+		assert_eq!(layer.root_element().to_string(), r#"<g xmlns="http://www.w3.org/2000/svg" id="layer1">
+	  <path/>
+	</g>"#);
+		Ok(())
+	}
+	```
 	 */
-	fn is_comment(&self) -> bool;
+	pub fn extract_subtree<'a>(&self, selector: impl Into<ElementSelector<'a>>) -> Result<Document, KissXmlError> {
+		let location = self.locate(selector.into())?;
+		let mut elem = &self.root_element;
+		for i in location {
+			elem = elem.child_nodes[i].as_element().expect("logic error");
+		}
+		Ok(Document::new(elem.clone().into_standalone()))
+	}
 
 	/**
-	Returns `true` if this Node trait object is a CData struct, otherwise `false`
+	Same as [extract_subtree(...)](Document::extract_subtree()), except that the matched element is removed from this document instead of being copied. Returns an `InvalidContent` error if the selector matches the root element (which cannot be removed), in which case this document is not modified.
 	 */
-	fn is_cdata(&self) -> bool;
+	pub fn detach_subtree<'a>(&mut self, selector: impl Into<ElementSelector<'a>>) -> Result<Document, KissXmlError> {
+		let mut location = self.locate(selector.into())?;
+		let index = match location.pop() {
+			None => return Err(InvalidContent::new("cannot detach the root element").into()),
+			Some(i) => i
+		};
+		let mut parent = &mut self.root_element;
+		for i in location {
+			parent = parent.child_nodes[i].as_element_mut().expect("logic error");
+		}
+		let elem = parent.remove_element(index)?;
+		Ok(Document::new(elem.into_standalone()))
+	}
+
+	/// finds the first element matching the selector, returning the child node indices leading to it from the root
+	fn locate(&self, selector: ElementSelector) -> Result<Vec<usize>, KissXmlError> {
+		let found = match selector {
+			ElementSelector::Path(path) => {
+				let parsed = crate::paths::ElementPath::parse(path)?;
+				self.relative_segments(&parsed).and_then(|segments| {
+					let mut location = Vec::with_capacity(segments.len());
+					let mut elem = &self.root_element;
+					for seg in segments {
+						let i = elem.nth_child_element_index(seg.name.as_str(), seg.index)?;
+						location.push(i);
+						elem = elem.child_nodes[i].as_element().expect("logic error");
+					}
+					Some(location)
+				})
+			},
+			ElementSelector::Predicate(predicate) => {
+				let mut location = Vec::new();
+				match self.root_element.locate_first(predicate, &mut location) {
+					true => Some(location),
+					false => None
+				}
+			}
+		};
+		found.ok_or_else(|| DoesNotExistError::new("no element matches the given selector").into())
+	}
 
-	/**
-	Returns the type information for this node
-	*/
-	fn node_type(&self) -> DomNodeType {
-		if self.is_cdata() {
-			DomNodeType::CDataNode
-		} else if self.is_comment() {
-			DomNodeType::CommentNode
-		} else if self.is_element() {
-			DomNodeType::ElementNode
-		} else if self.is_text() {
-			DomNodeType::TextNode
-		} else {
-			panic!("Logic error! Box<dyn Node> value has no corresponding type in enum DomNodeType")
+	/// gets the path segments below the root element, or None if an absolute path does not start with the root
+	fn relative_segments<'p>(&self, path: &'p crate::paths::ElementPath) -> Option<&'p [crate::paths::PathSegment]> {
+		if !path.absolute {
+			return Some(path.segments.as_slice());
+		}
+		let first = &path.segments[0];
+		match first.name == self.root_element.name && first.index == 1 {
+			true => Some(&path.segments[1..]),
+			false => None
 		}
 	}
 
 	/**
-	Casts this Node to an Element struct (if the Node is not an Element struct, then `Err(TypeCastError)` error result is returned).
-	 */
-	fn as_element(&self) -> Result<&Element, TypeCastError>;
+	Returns the table of source byte spans for the elements of this document, if this document was parsed with the `source_spans` option enabled (see [ParseOptions](crate::ParseOptions)), otherwise `None`.
 
-	/**
-	Casts this Node to a Comment struct (if the Node is not an Comment struct, then `Err(TypeCastError)` error result is returned).
+	**Note:** the source map reflects the original XML source that was parsed. It is not updated when the DOM is modified.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		let xml = "<root><a/><a>text</a></root>";
+		let options = kiss_xml::ParseOptions{source_spans: true, ..Default::default()};
+		let doc = kiss_xml::parse_str_with_options(xml, &options)?;
+		let span = doc.source_spans().unwrap().get("/root/a[2]")?.unwrap();
+		assert_eq!(&xml[span.extent.clone()], "<a>text</a>");
+		Ok(())
+	}
+	```
 	 */
-	fn as_comment(&self) -> Result<&Comment, TypeCastError>;
+	pub fn source_spans(&self) -> Option<&SourceMapTable> {
+		self.source_spans.as_ref()
+	}
 
 	/**
-	Casts this Node to a Text struct (if the Node is not a Text struct, then `Err(TypeCastError)` error result is returned).
-	 */
-	fn as_text(&self) -> Result<&Text, TypeCastError>;
+	Walks this document in document order and returns the sequence of SAX-like parsing events that it corresponds to (declaration, DTDs, start and end of each element, text, comments, and CDATA). This allows event-based code to be used with an already-parsed DOM.
 
-	/**
-	Casts this Node to a CData struct (if the Node is not an CData struct, then `Err(TypeCastError)` error result is returned).
+	Note that the DOM does not store whitespace-only text between tags (see the crate documentation about whitespace handling), so no `Text` events are produced for it.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		use kiss_xml::dom::XmlEvent;
+		let doc = kiss_xml::parse_str("<root><a>text</a></root>")?;
+		for event in doc.events() {
+			match event {
+				XmlEvent::StartElement{name, ..} => println!("start <{}>", name),
+				XmlEvent::EndElement{name, ..} => println!("end <{}>", name),
+				XmlEvent::Text(text) => println!("text: {}", text),
+				_ => {}
+			}
+		}
+		Ok(())
+	}
+	```
 	 */
-	fn as_cdata(&self) -> Result<&CData, TypeCastError>;
+	pub fn events(&self) -> impl Iterator<Item = XmlEvent<'_>> {
+		self.declaration.iter().map(XmlEvent::Declaration)
+			.chain(self.dtds.iter().map(XmlEvent::Dtd))
+			.chain(self.root_element.events())
+	}
 
 	/**
-	Casts this Node to an Element struct (if the Node is not an Element struct, then `Err(TypeCastError)` error result is returned).
+	Returns a rough estimate of the number of bytes of memory used by this document, including the root element and all of its descendants, the declaration, DTDs, opaque markup, and the source span table (if any). See [memory_breakdown()](Document::memory_breakdown()) for details.
 	 */
-	fn as_element_mut(&mut self) -> Result<&mut Element, TypeCastError>;
+	pub fn estimated_memory_bytes(&self) -> usize {
+		self.memory_breakdown().estimated_bytes
+	}
 
 	/**
-	Casts this Node to a Comment struct (if the Node is not an Comment struct, then `Err(TypeCastError)` error result is returned).
-	 */
-	fn as_comment_mut(&mut self) -> Result<&mut Comment, TypeCastError>;
-
+	Counts the nodes and attributes in this document and estimates the memory it uses. The estimate adds up the sizes of the structs, the capacities of all owned strings and vectors, and a fixed per-slot overhead for hash maps. It does not include allocator overhead and is not exact, but it is deterministic for a given document and covers all of the allocations owned by the DOM.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		let doc = kiss_xml::parse_str(r#"<root a="1"><b>text</b><!--note--></root>"#)?;
+		let stats = doc.memory_breakdown();
+		assert_eq!((stats.elements, stats.text_nodes, stats.comments, stats.attributes), (2, 1, 1, 1));
+		assert!(stats.estimated_bytes > stats.string_bytes);
+		Ok(())
+	}
+	```
+	 */
+	pub fn memory_breakdown(&self) -> MemoryBreakdown {
+		let mut breakdown = self.root_element.memory_breakdown();
+		let mut strings: Vec<&String> = Vec::new();
+		if let Some(decl) = &self.declaration {
+			strings.push(&decl.decl_str);
+		}
+		for dtd in &self.dtds {
+			strings.extend(dtd.raw.iter());
+			strings.push(&dtd.name);
+			strings.extend(dtd.external_id.iter());
+			strings.extend(dtd.internal_subset.iter());
+		}
+		strings.extend(self.opaque_markup.iter().map(|m| &m.raw));
+		let mut extra = size_of::<Document>() - size_of::<Element>()
+			+ self.dtds.capacity() * size_of::<DTD>()
+			+ self.opaque_markup.capacity() * size_of::<OpaqueMarkup>();
+		if let Some(table) = &self.source_spans {
+			extra += table.spans.capacity() * size_of::<(String, SourceSpan)>()
+				+ hash_map_bytes(&table.lookup);
+			strings.extend(table.spans.iter().map(|(path, _)| path));
+			strings.extend(table.lookup.keys());
+		}
+		for s in strings {
+			breakdown.string_bytes += s.len();
+			extra += s.capacity();
+		}
+		breakdown.estimated_bytes += extra;
+		breakdown
+	}
+
+	/**
+	Checks this document for constructs that are legal XML but are likely to be mistakes (see [LintKind](crate::lint::LintKind) for the list of checks) and returns the warnings in document order. To choose which checks to run, use a [Linter](crate::lint::Linter) instead.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml::lint::LintKind;
+		let doc = kiss_xml::parse_str(r#"<list><item id="1"/><item id="1"/></list>"#)?;
+		let warnings = doc.lint();
+		assert_eq!(warnings.len(), 1);
+		assert_eq!(warnings[0].kind, LintKind::DuplicateChild);
+		assert_eq!(warnings[0].path, "/list[1]");
+		Ok(())
+	}
+	```
+	 */
+	pub fn lint(&self) -> Vec<crate::lint::LintWarning> {
+		crate::lint::Linter::new().lint(self)
+	}
+
+	/**
+	Returns the warnings about the source XML that were found when this document was parsed, such as attribute values containing raw tabs or newlines (see [LintKind::RawWhitespaceInAttributeValue](crate::lint::LintKind::RawWhitespaceInAttributeValue)), which do not survive other XML parsers. These are also included in the results of [lint()](Document::lint()). Documents that were not parsed have no parse warnings.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		use kiss_xml::lint::LintKind;
+		let doc = kiss_xml::parse_str("<root><a note=\"two\nlines\"/></root>")?;
+		let warnings = doc.parse_warnings();
+		assert_eq!(warnings.len(), 1);
+		assert_eq!(warnings[0].kind, LintKind::RawWhitespaceInAttributeValue);
+		assert_eq!(warnings[0].path, "/root[1]/a[1]");
+		let fixed = doc.to_string_with_options(&kiss_xml::OutputOptions::new().escape_attribute_whitespace(true));
+		assert!(kiss_xml::parse_str(fixed)?.parse_warnings().is_empty());
+		Ok(())
+	}
+	```
+	*/
+	pub fn parse_warnings(&self) -> &[crate::lint::LintWarning] {
+		&self.parse_warnings
+	}
+
+	/** Adds a parse warning (used by the parser) */
+	pub(crate) fn push_parse_warning(&mut self, warning: crate::lint::LintWarning) {
+		self.parse_warnings.push(warning);
+	}
+
+	/** Sets the source map table (used by the parser) */
+	pub(crate) fn set_source_spans(&mut self, spans: Option<SourceMapTable>) {
+		self.source_spans = spans;
+	}
+
+	/**
+	Returns the markup outside the root element that kiss_xml does not interpret (such as processing instructions or vendor-specific `<!...>` constructs), in the order it appeared in the source. These items are only created by the parser and are written back out verbatim, at their original position, when this document is serialized.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+	<?xml-stylesheet type="text/xsl" href="style.xsl"?>
+	<root/>
+	"#;
+		let doc = kiss_xml::parse_str(xml)?;
+		let markup = doc.opaque_markup().next().unwrap();
+		assert_eq!(markup.raw(), r#"<?xml-stylesheet type="text/xsl" href="style.xsl"?>"#);
+		assert_eq!(doc.to_string(), xml);
+		Ok(())
+	}
+	```
+	 */
+	pub fn opaque_markup(&self) -> impl Iterator<Item = &OpaqueMarkup> {
+		self.opaque_markup.iter()
+	}
+
+	/**
+	Removes all opaque markup (see [opaque_markup()](Document::opaque_markup())) from this document
+	 */
+	pub fn clear_opaque_markup(&mut self) {
+		self.opaque_markup.clear();
+	}
+
+	/** Adds an opaque markup item (used by the parser) */
+	pub(crate) fn push_opaque_markup(&mut self, markup: OpaqueMarkup) {
+		self.opaque_markup.push(markup);
+	}
+
+	/// writes the opaque markup items with matching placement, one per line
+	fn write_opaque_markup<F: Fn(MarkupPlacement) -> bool>(&self, builder: &mut String, placement_filter: F) {
+		for markup in self.opaque_markup.iter().filter(|m| placement_filter(m.placement)) {
+			builder.push_str(markup.raw.as_str());
+			builder.push('\n');
+		}
+	}
+}
+
+impl std::fmt::Display for Document{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.to_string_with_indent("  "))
+	}
+}
+
+impl std::fmt::Debug for Document{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.to_string_with_indent("  "))
+	}
+}
+
+/**
+Two documents are equal if they hold the same XML content:
+ - the root elements are equal (see [Element]'s `PartialEq`: element names, namespaces, and attributes must match, attribute order is ignored, and child nodes are compared in order)
+ - the declarations are either both absent or have identical text (declarations are not interpreted, so a document without a declaration does not equal one with the default declaration, but an explicit `<?xml version="1.0" encoding="UTF-8"?>` equals the default [Declaration])
+ - the DTDs are equal and in the same order (DTDs are compared by name, external ID, and internal subset, not by their source formatting; see [DTD])
+ - the opaque markup in the prologue and epilogue is identical and in the same place (source offsets are ignored)
+
+Source spans (see [source_spans()](Document::source_spans())), parse warnings (see [parse_warnings()](Document::parse_warnings())), and whether the documents are fragments (see [is_fragment()](Document::is_fragment())) are never compared. For a stricter comparison that also requires the written XML text to be byte-for-byte the same, use [identical_to(...)](Document::identical_to()).
+*/
+impl PartialEq<Self> for Document {
+	fn eq(&self, other: &Self) -> bool {
+		self.declaration == other.declaration
+		&& self.dtds == other.dtds
+		&& self.root_element == other.root_element
+		&& self.opaque_markup == other.opaque_markup
+	}
+}
+
+/** Selects an element in a Document, either by path (eg `/root/a[2]`, see [Document::element_at_path(...)](Document::element_at_path())) or by the first element (in document order) for which a predicate function returns true */
+#[derive(Clone, Copy)]
+pub enum ElementSelector<'a> {
+	/// Selects the element at the given path
+	Path(&'a str),
+	/// Selects the first element for which this function returns true
+	Predicate(&'a dyn Fn(&Element) -> bool)
+}
+
+impl<'a> From<&'a str> for ElementSelector<'a> {
+	fn from(path: &'a str) -> Self {
+		ElementSelector::Path(path)
+	}
+}
+
+impl<'a> From<&'a String> for ElementSelector<'a> {
+	fn from(path: &'a String) -> Self {
+		ElementSelector::Path(path.as_str())
+	}
+}
+
+impl<'a> From<&'a dyn Fn(&Element) -> bool> for ElementSelector<'a> {
+	fn from(predicate: &'a dyn Fn(&Element) -> bool) -> Self {
+		ElementSelector::Predicate(predicate)
+	}
+}
+
+/// document-order iterator over an element and/or its descendant elements
+struct ElementTreeIter<'a> {
+	/// element to return before iterating through the stack
+	pending: Option<&'a Element>,
+	/// iterators over the child nodes of the elements on the current path
+	stack: Vec<std::slice::Iter<'a, Box<dyn Node>>>,
+}
+
+impl<'a> Iterator for ElementTreeIter<'a> {
+	type Item = &'a Element;
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(e) = self.pending.take() {
+			self.stack.push(e.child_nodes.iter());
+			return Some(e);
+		}
+		loop {
+			match self.stack.last_mut()?.next() {
+				None => {self.stack.pop();},
+				Some(node) => {
+					if let Ok(e) = node.as_element() {
+						self.stack.push(e.child_nodes.iter());
+						return Some(e);
+					}
+				}
+			}
+		}
+	}
+}
+
+/** Node counts and estimated memory usage of a DOM, see [Document::memory_breakdown()] and [Element::memory_breakdown()] */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MemoryBreakdown {
+	/// number of elements
+	pub elements: usize,
+	/// number of text nodes
+	pub text_nodes: usize,
+	/// number of comments
+	pub comments: usize,
+	/// number of CDATA nodes
+	pub cdata: usize,
+	/// number of attributes (including `xmlns` declarations)
+	pub attributes: usize,
+	/// total length in bytes of all of the strings in the DOM (names, attributes, text, etc.)
+	pub string_bytes: usize,
+	/// estimated total memory usage in bytes
+	pub estimated_bytes: usize,
+}
+
+/// estimated per-slot overhead of a hash map, in addition to the size of the entries (1 control byte per slot, rounded up)
+const HASH_MAP_SLOT_OVERHEAD: usize = 2;
+
+/// estimated bytes allocated by a hash map for its entries (not including heap data owned by the entries)
+fn hash_map_bytes<K, V>(map: &HashMap<K, V>) -> usize {
+	map.capacity() * (size_of::<(K, V)>() + HASH_MAP_SLOT_OVERHEAD)
+}
+
+/// an [OutputFilter] with its element paths resolved against the document being written
+struct FilterContext<'a> {
+	/// the filter
+	filter: &'a OutputFilter,
+	/// elements matched by the filter's paths
+	denied: HashSet<*const Element>,
+}
+
+impl<'a> FilterContext<'a> {
+	/// returns true if the given (non-root) element should be skipped
+	fn skips(&self, e: &Element) -> bool {
+		self.denied.contains(&(e as *const Element))
+			|| self.filter.deny_elements.iter().any(|n| *n == e.name || *n == e.tag_name())
+	}
+	/// returns true if the given attribute should be written (namespace declarations are always kept)
+	fn keeps_attr(&self, name: &str) -> bool {
+		name == "xmlns" || name.starts_with("xmlns:") || !self.filter.deny_attributes.iter().any(|n| n == name)
+	}
+}
+
+/// returns the given output options, replacing an invalid indent with the default (with a warning)
+fn checked_output_options(options: &OutputOptions) -> Cow<'_, OutputOptions> {
+	match crate::validate_indent(options.indent.as_str()){
+		Ok(_) => Cow::Borrowed(options),
+		Err(_) => {
+			eprintln!("WARNING: {:?} is not a valid indentation. Must be either 1 tab or any number of spaces. The default of 2 spaces will be used instead", options.indent);
+			Cow::Owned(OutputOptions{indent: "  ".to_string(), ..options.clone()})
+		}
+	}
+}
+
+/** Where an [OpaqueMarkup] item is located in a Document, relative to the DTDs and the root element */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MarkupPlacement {
+	/// Before the root element and before the DTD with the given index (an index equal to or greater than the number of DTDs means after the last DTD)
+	Prologue(usize),
+	/// After the root element
+	Epilogue
+}
+
+/**
+A construct outside the root element which the parser recognized as well-delimited but does not model, such as a processing instruction (eg `<?xml-stylesheet href="style.xsl"?>`), an `<!ENTITY ...>` outside a DOCTYPE, or some other vendor-specific `<!...>` markup. The exact source text is kept and written back out unchanged when the document is serialized. Opaque markup is only created by the parser and never appears inside the root element.
+ */
+#[derive(Clone, Debug, Eq)]
+pub struct OpaqueMarkup {
+	/// exact source text
+	raw: String,
+	/// location relative to the rest of the document
+	placement: MarkupPlacement,
+	/// byte offset in the original source
+	source_offset: usize
+}
+
+impl OpaqueMarkup {
+	/** Creates a new opaque markup item (used by the parser) */
+	pub(crate) fn new(raw: impl Into<String>, placement: MarkupPlacement, source_offset: usize) -> Self {
+		OpaqueMarkup{raw: raw.into(), placement, source_offset}
+	}
+	/** Returns the exact source text of this markup */
+	pub fn raw(&self) -> &str {
+		self.raw.as_str()
+	}
+	/** Returns the location of this markup relative to the DTDs and root element of the document */
+	pub fn placement(&self) -> MarkupPlacement {
+		self.placement
+	}
+	/** Returns the byte offset of this markup in the source it was parsed from */
+	pub fn source_offset(&self) -> usize {
+		self.source_offset
+	}
+}
+
+impl PartialEq for OpaqueMarkup {
+	/// the source offset is not part of the comparison, only the markup itself and its placement
+	fn eq(&self, other: &Self) -> bool {
+		self.raw == other.raw && self.placement == other.placement
+	}
+}
+
+impl std::fmt::Display for OpaqueMarkup {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.raw)
+	}
+}
+
+/** The location of an element in the original XML source, as byte offsets into the parsed string */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SourceSpan {
+	/// Byte range of the start tag (eg `<a href="x">`), or of the whole tag if the element is self-closing
+	pub start_tag: Range<usize>,
+	/// Byte range of the end tag (eg `</a>`), or `None` if the element is self-closing
+	pub end_tag: Option<Range<usize>>,
+	/// Byte range of the whole element, from the start of the start tag to the end of the end tag
+	pub extent: Range<usize>,
+}
+
+/**
+A side table that maps element paths (eg `/root/mydata/properties` or `/root/a[2]`) to the byte spans of the elements in the source that a Document was parsed from. This is only built when requested via [ParseOptions](crate::ParseOptions) and reflects the original source, not any later modifications of the DOM.
+
+Paths are slash-separated element names (namespace prefixes are ignored) starting at the root element, where each name may have a 1-based index in square brackets to select the Nth sibling element with that name (no index is the same as `[1]`).
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceMapTable {
+	/// canonical path and span of every element, in document order
+	spans: Vec<(String, SourceSpan)>,
+	/// canonical path -> index in `spans`
+	lookup: HashMap<String, usize>
+}
+
+impl SourceMapTable {
+	/** Looks up the span of the element at the given path, returning `Ok(None)` if there is no such element or an error result if the path is not valid path syntax */
+	pub fn get(&self, path: &str) -> Result<Option<&SourceSpan>, KissXmlError> {
+		let mut path = crate::paths::ElementPath::parse(path)?;
+		path.absolute = true;
+		Ok(self.lookup.get(&path.to_string()).map(|i| &self.spans[*i].1))
+	}
+	/** Returns all (path, span) entries in document order. The paths are in canonical form, with an explicit index on every path segment (eg `/root[1]/a[2]`) */
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &SourceSpan)> {
+		self.spans.iter().map(|(p, s)| (p.as_str(), s))
+	}
+	/** Returns the number of elements in this table */
+	pub fn len(&self) -> usize {
+		self.spans.len()
+	}
+	/** Returns true if this table has no entries */
+	pub fn is_empty(&self) -> bool {
+		self.spans.is_empty()
+	}
+	/** adds a new entry (used by the parser) */
+	pub(crate) fn insert(&mut self, canonical_path: String, span: SourceSpan) {
+		self.lookup.insert(canonical_path.clone(), self.spans.len());
+		self.spans.push((canonical_path, span));
+	}
+	/** gets a mutable reference to an entry (used by the parser) */
+	pub(crate) fn get_mut(&mut self, index: usize) -> Option<&mut SourceSpan> {
+		self.spans.get_mut(index).map(|(_, s)| s)
+	}
+}
+
+/** This enum lists the types of XML DOM nodes used in kiss_xml, useful for runtime reflection. */
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum DomNodeType {
+	/// node type is CDATA
+	CDataNode,
+	/// node type is Comment
+	CommentNode,
+	/// node type is Element
+	ElementNode,
+	/// node type is Text
+	TextNode
+}
+
+impl From<Box<dyn Node>> for DomNodeType {
+	fn from(value: Box<dyn Node>) -> Self {
+		value.node_type()
+	}
+}
+
+impl std::fmt::Display for DomNodeType {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			DomNodeType::CDataNode => write!(f, "CDATA"),
+			DomNodeType::CommentNode => write!(f, "Comment"),
+			DomNodeType::ElementNode => write!(f, "Element"),
+			DomNodeType::TextNode => write!(f, "Text"),
+		}
+	}
+}
+
+/// Private supertrait that prevents types outside this crate from implementing [Node]
+mod sealed {
+	pub trait Sealed {}
+	impl Sealed for super::Element {}
+	impl Sealed for super::Text {}
+	impl Sealed for super::Comment {}
+	impl Sealed for super::CData {}
+}
+
+/**
+A node in the DOM tree. Elements, Comments, and Text are all types of nodes, but only Elements can be branch nodes with children of their own.
+
+This trait is sealed: only the node types defined in this module implement it, so a [DomNodeType] match on
+any `Box<dyn Node>` is always exhaustive. Implementing it for your own types will not compile:
+```compile_fail
+use kiss_xml::dom::*;
+#[derive(Clone, Debug)]
+struct Timestamp(u64);
+impl std::fmt::Display for Timestamp {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {write!(f, "{}", self.0)}
+}
+impl Node for Timestamp {
+	fn text(&self) -> String {self.to_string()}
+	fn is_element(&self) -> bool {false}
+	fn is_text(&self) -> bool {true}
+	fn is_comment(&self) -> bool {false}
+	fn is_cdata(&self) -> bool {false}
+	fn node_type(&self) -> DomNodeType {DomNodeType::TextNode}
+	fn as_element(&self) -> Result<&Element, kiss_xml::errors::TypeCastError> {unimplemented!()}
+	fn as_comment(&self) -> Result<&Comment, kiss_xml::errors::TypeCastError> {unimplemented!()}
+	fn as_text(&self) -> Result<&Text, kiss_xml::errors::TypeCastError> {unimplemented!()}
+	fn as_cdata(&self) -> Result<&CData, kiss_xml::errors::TypeCastError> {unimplemented!()}
+	fn as_element_mut(&mut self) -> Result<&mut Element, kiss_xml::errors::TypeCastError> {unimplemented!()}
+	fn as_comment_mut(&mut self) -> Result<&mut Comment, kiss_xml::errors::TypeCastError> {unimplemented!()}
+	fn as_text_mut(&mut self) -> Result<&mut Text, kiss_xml::errors::TypeCastError> {unimplemented!()}
+	fn as_cdata_mut(&mut self) -> Result<&mut CData, kiss_xml::errors::TypeCastError> {unimplemented!()}
+	fn as_node(&self) -> &dyn Node {self}
+	fn as_node_mut(&mut self) -> &mut dyn Node {self}
+	fn as_any(&self) -> &dyn std::any::Any {self}
+	fn as_any_mut(&mut self) -> &mut dyn std::any::Any {self}
+	fn to_string_with_indent(&self, _indent: &str) -> String {self.to_string()}
+	fn boxed(self) -> Box<dyn Node> {Box::new(self)}
+}
+```
+ */
+pub trait Node: sealed::Sealed + dyn_clone::DynClone + std::fmt::Debug + std::fmt::Display + ToString {
+
+	/**
+	Returns the text content of the node. For a Comment, CData, or Text node, this is just the comment or text string. For an Element, this will return *all* text nodes (including from child elements, recursive scan) as a single string, or an empty string if this element has no child text nodes
+	 */
+	fn text(&self) -> String;
+
+	/**
+	Returns `true` if this Node trait object is an Element struct, otherwise `false`
+	 */
+	fn is_element(&self) -> bool;
+
+	/**
+	Returns `true` if this Node trait object is a Text struct, otherwise `false`
+	 */
+	fn is_text(&self) -> bool;
+
+	/**
+	Returns `true` if this Node trait object is a Comment struct, otherwise `false`
+	 */
+	fn is_comment(&self) -> bool;
+
+	/**
+	Returns `true` if this Node trait object is a CData struct, otherwise `false`
+	 */
+	fn is_cdata(&self) -> bool;
+
+	/**
+	Returns the type information for this node
+	*/
+	fn node_type(&self) -> DomNodeType;
+
+	/**
+	Casts this Node to an Element struct (if the Node is not an Element struct, then `Err(TypeCastError)` error result is returned).
+	 */
+	fn as_element(&self) -> Result<&Element, TypeCastError>;
+
+	/**
+	Casts this Node to a Comment struct (if the Node is not an Comment struct, then `Err(TypeCastError)` error result is returned).
+	 */
+	fn as_comment(&self) -> Result<&Comment, TypeCastError>;
+
+	/**
+	Casts this Node to a Text struct (if the Node is not a Text struct, then `Err(TypeCastError)` error result is returned).
+	 */
+	fn as_text(&self) -> Result<&Text, TypeCastError>;
+
+	/**
+	Casts this Node to a CData struct (if the Node is not an CData struct, then `Err(TypeCastError)` error result is returned).
+	 */
+	fn as_cdata(&self) -> Result<&CData, TypeCastError>;
+
+	/**
+	Casts this Node to an Element struct (if the Node is not an Element struct, then `Err(TypeCastError)` error result is returned).
+	 */
+	fn as_element_mut(&mut self) -> Result<&mut Element, TypeCastError>;
+
+	/**
+	Casts this Node to a Comment struct (if the Node is not an Comment struct, then `Err(TypeCastError)` error result is returned).
+	 */
+	fn as_comment_mut(&mut self) -> Result<&mut Comment, TypeCastError>;
+
 	/**
 	Casts this Node to a Text struct (if the Node is not a Text struct, then `Err(TypeCastError)` error result is returned).
 	 */
@@ -373,16 +1229,18 @@ pub trait Node: dyn_clone::DynClone + std::fmt::Debug + std::fmt::Display + ToSt
 
 /// clones a given boxed node
 pub fn clone_node(node: &Box<dyn Node>) -> Box<dyn Node> {
-	if node.is_element() {
-		Box::new(node.as_element().expect("logic error").clone())
-	} else if node.is_text() {
-		Box::new(node.as_text().expect("logic error").clone())
-	} else if node.is_comment() {
-		Box::new(node.as_comment().expect("logic error").clone())
-	} else if node.is_cdata() {
-		Box::new(node.as_cdata().expect("logic error").clone())
-	} else {
-		panic!("logic error: Node is neither of Element, Text, Comment, nor CData");
+	dyn_clone::clone_box(&**node)
+}
+
+/// hashes a boxed node consistently with `node_eq()`
+fn hash_node<H: Hasher>(node: &dyn Node, state: &mut H) {
+	let t = node.node_type();
+	t.hash(state);
+	match t {
+		DomNodeType::CDataNode => node.as_cdata().ok().hash(state),
+		DomNodeType::CommentNode => node.as_comment().ok().hash(state),
+		DomNodeType::ElementNode => node.as_element().ok().hash(state),
+		DomNodeType::TextNode => node.as_text().ok().hash(state)
 	}
 }
 
@@ -395,16 +1253,86 @@ pub fn node_eq(n1: &Box<dyn Node>, n2: &Box<dyn Node>) -> bool {
 	}
 	return match t1 {
 		DomNodeType::CDataNode =>
-			n1.as_cdata().unwrap() == n2.as_cdata().unwrap(),
+			n1.as_cdata().ok() == n2.as_cdata().ok(),
 		DomNodeType::CommentNode =>
-			n1.as_comment().unwrap() == n2.as_comment().unwrap(),
+			n1.as_comment().ok() == n2.as_comment().ok(),
 		DomNodeType::ElementNode =>
-			n1.as_element().unwrap() == n2.as_element().unwrap(),
+			n1.as_element().ok() == n2.as_element().ok(),
 		DomNodeType::TextNode =>
-			n1.as_text().unwrap() == n2.as_text().unwrap()
+			n1.as_text().ok() == n2.as_text().ok()
 	}
 }
 
+/** The attribute operations that turn one element's attributes into another's, as returned by [Element::attr_diff(...)](Element::attr_diff()). Each list is sorted by attribute name. */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AttrDiff {
+	/// attributes that are only in the other element, as (name, value)
+	pub added: Vec<(String, String)>,
+	/// names of the attributes that are only in this element
+	pub removed: Vec<String>,
+	/// attributes with different values, as (name, old value, new value)
+	pub changed: Vec<(String, String, String)>
+}
+
+/** A single attribute operation in an [AttrDiff] */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AttrChange<'a> {
+	/// An attribute was added, with (name, value)
+	Added(&'a str, &'a str),
+	/// An attribute was removed, with name
+	Removed(&'a str),
+	/// An attribute value was changed, with (name, old value, new value)
+	Changed(&'a str, &'a str, &'a str)
+}
+
+impl AttrDiff {
+	/** Returns true if there are no attribute changes */
+	pub fn is_empty(&self) -> bool {
+		self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+	}
+	/** Returns the total number of attribute changes */
+	pub fn len(&self) -> usize {
+		self.added.len() + self.removed.len() + self.changed.len()
+	}
+	/** Iterates over all attribute changes in order of attribute name (each name appears at most once) */
+	pub fn changes(&self) -> impl Iterator<Item = AttrChange<'_>> {
+		let mut changes: Vec<AttrChange> = self.added.iter().map(|(k, v)| AttrChange::Added(k, v))
+			.chain(self.removed.iter().map(|k| AttrChange::Removed(k)))
+			.chain(self.changed.iter().map(|(k, o, n)| AttrChange::Changed(k, o, n)))
+			.collect();
+		changes.sort_by_key(|c| match c {
+			AttrChange::Added(k, _) | AttrChange::Removed(k) | AttrChange::Changed(k, _, _) => *k
+		});
+		changes.into_iter()
+	}
+}
+
+/** What [Element::insert_path_values(...)](Element::insert_path_values()) does when an element already exists at a path */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DuplicatePathPolicy {
+	/// Replace the text of the existing element (the default)
+	#[default]
+	Overwrite,
+	/// Append a new sibling element with the same name and the new text
+	AppendSibling
+}
+
+/** One piece of mixed content for [Element::new_mixed(...)](Element::new_mixed()) */
+#[derive(Debug)]
+pub enum MixedPart {
+	/// A run of text
+	Text(String),
+	/// Any child node (usually an [Element])
+	Child(Box<dyn Node>),
+	/// A comment with the given text
+	Comment(String),
+	/// A CDATA section with the given content
+	CData(String)
+}
+
+/// The XML Schema instance namespace (used for `xsi:nil`)
+const XSI_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema-instance";
+
 /// Represents an XML element with a name, text content, attributes, xmlns namespace (with optional prefix), and children.
 pub struct Element {
 	/// Name of this element
@@ -418,7 +1346,11 @@ pub struct Element {
 	/// optional xmlns (if xmlns_prefix is None then the xmlns is default namespace)
 	xmlns_prefix: Option<String>,
 	/// xmlns definitions for this element, if any
-	xmlns_context: HashMap<String, String>
+	xmlns_context: HashMap<String, String>,
+	/// default namespace inherited from the parent element (needed for the un-prefixed children of a prefixed element)
+	inherited_xmlns: Option<String>,
+	/// xmlns prefixes inherited from the parent element (needed when this element's own declaration of a prefix is removed)
+	inherited_xmlns_context: HashMap<String, String>
 }
 
 impl Element {
@@ -431,6 +1363,8 @@ impl Element {
 	* *xmlns*: optional namespace for this element. Note that this will override any xmlns definitions in the attributes
 	* *xmlns_prefix*: optional namespace prefix. If `xmlns` is not `None` but `xmlns_prefix` is `None`, then this element will set it's xmlns as the default xlmns for it and its children. Note that this will override any xmlns definitions in the attributes
 	* *children*: optional list of child nodes to add to this element
+
+	The text (if any) is always appended before the children, so `Some("a")` with children `[<b/>]` produces `<x>a<b/></x>`. As with [append(...)](Element::append()), adjacent text nodes are merged into one and whitespace-only text nodes are dropped, so a leading text child in `children` is merged into `text`. To interleave text and child elements (eg `text<b/>more text`), use [new_mixed(...)](Element::new_mixed()) instead.
 	 */
 	pub fn new<TEXT1: Into<String>+Clone, TEXT2: Into<String>+Clone>(
 		name: impl Into<String>, text: Option<String>,
@@ -481,7 +1415,9 @@ impl Element {
 			xmlns_context: Element::xmlns_context_from_attributes(&attrs),
 			attributes: attrs,
 			xmlns: xmlns.map(|s| s.to_string()),
-			xmlns_prefix: xmlns_prefix.map(|s| s.to_string())
+			xmlns_prefix: xmlns_prefix.map(|s| s.to_string()),
+			inherited_xmlns: None,
+			inherited_xmlns_context: HashMap::new()
 		};
 		// finally, add children
 		// (using the append*(...) functions in case of default namespace inheritance)
@@ -495,6 +1431,34 @@ impl Element {
 		};
 		return Ok(elem);
 	}
+	/**
+	Creates a new Element with the specified name and mixed content (text, elements, comments, and CDATA), appended in exactly the given order. Adjacent `Text` parts are merged into a single text node and whitespace-only text is dropped, the same as when parsing or when using [append(...)](Element::append()); text parts separated by any other part are kept separate.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml::dom::*;
+		let p = Element::new_mixed("paragraph", vec![
+			MixedPart::Text("Don't forget ".into()),
+			MixedPart::Child(Element::new_with_text("b", "me")?.boxed()),
+			MixedPart::Text(" this weekend!".into()),
+		])?;
+		assert_eq!(p.to_string(), "<paragraph>Don't forget <b>me</b> this weekend!</paragraph>");
+		Ok(())
+	}
+	```
+	*/
+	pub fn new_mixed(name: &str, parts: Vec<MixedPart>) -> Result<Self, KissXmlError> {
+		let mut elem = Element::new_from_name(name)?;
+		for part in parts {
+			match part {
+				MixedPart::Text(t) => elem.append_text(t),
+				MixedPart::Child(node) => elem.append_boxed(node),
+				MixedPart::Comment(c) => elem.append_comment(c)?,
+				MixedPart::CData(c) => elem.append_cdata(c)?
+			}
+		}
+		Ok(elem)
+	}
 	/// Creates a new Element with the specified name and not attributes or content.
 	pub fn new_from_name(name: &str) -> Result<Self, KissXmlError> {
 		// sanity check
@@ -637,6 +1601,41 @@ impl Element {
 		}
 	}
 	/**
+	Returns the default namespace that an un-prefixed child element appended to this element now would receive, or `None` if there is none.
+
+	The default namespace rules are:
+	* An `xmlns="..."` attribute sets the default namespace of the element that declares it and of all of its un-prefixed descendants, until another `xmlns="..."` overrides it
+	* An un-prefixed element without its own `xmlns` attribute is in its parent's effective default namespace (so for un-prefixed elements, this is the same as [default_namespace()](Element::default_namespace()))
+	* A prefixed element (eg `<img:root>`) is in the namespace bound to its prefix and [default_namespace()](Element::default_namespace()) returns `None`, but the default namespace inherited from its parent (or declared by its own `xmlns` attribute) still applies to its un-prefixed children
+	* Prefixed namespaces (`xmlns:prefix="..."`) are never inherited as a default namespace, an un-prefixed child of `<img:root>` is *not* in the `img` namespace
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		// default namespace declared on the root is inherited by its children
+		let doc = kiss_xml::parse_str(r#"<root xmlns="internal://ns/a"><width>200</width></root>"#)?;
+		let width = doc.root_element().first_element_by_name("width")?;
+		assert_eq!(width.effective_default_namespace(), Some("internal://ns/a".to_string()));
+		// a prefixed namespace is not a default namespace
+		let doc = kiss_xml::parse_str(r#"<img:root xmlns:img="internal://ns/a"><width>200</width></img:root>"#)?;
+		assert_eq!(doc.root_element().namespace(), Some("internal://ns/a".to_string()));
+		assert_eq!(doc.root_element().effective_default_namespace(), None);
+		// but an inherited default namespace passes through a prefixed element
+		let doc = kiss_xml::parse_str(r#"<root xmlns="internal://ns/a" xmlns:b="internal://ns/b"><b:group><item/></b:group></root>"#)?;
+		let group = doc.root_element().first_element_by_name("group")?;
+		assert_eq!(group.default_namespace(), None);
+		assert_eq!(group.effective_default_namespace(), Some("internal://ns/a".to_string()));
+		assert_eq!(group.first_element_by_name("item")?.namespace(), Some("internal://ns/a".to_string()));
+		Ok(())
+	}
+	```
+	 */
+	pub fn effective_default_namespace(&self) -> Option<String> {
+		match self.xmlns_prefix {
+			None => self.xmlns.clone(),
+			Some(_) => self.attributes.get("xmlns").cloned().or_else(|| self.inherited_xmlns.clone())
+		}
+	}
+	/**
 	This is the tag name as it will appear in serialized XML. If this element has an xmlns prefix, then this returns prefix:name, otherwise it just returns the name
 	*/
 	pub fn tag_name(&self) -> String {
@@ -791,7 +1790,90 @@ impl Element {
 		let pfx = prefix.map(|p| p.to_string());
 		self.child_elements_mut().filter(move |c| c.xmlns_prefix == pfx)
 	}
-	/** Gets any and all xmlns prefixes defined in this element (does not include prefix-less default namespace, nor prefixes inherited from a parent element) */
+	/**
+	Returns all of the namespaces in scope for this element as (prefix, URI) pairs sorted by prefix, including both those declared by this element and those inherited from its ancestors. The default namespace (if any) has the prefix `""`.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		let doc = kiss_xml::parse_str(r#"<root xmlns="internal://a" xmlns:b="internal://b"><child/></root>"#)?;
+		let child = doc.root_element().first_element_by_name("child")?;
+		let in_scope: Vec<(&str, &str)> = child.namespaces_in_scope().collect();
+		assert_eq!(in_scope, vec![("", "internal://a"), ("b", "internal://b")]);
+		Ok(())
+	}
+	```
+	 */
+	pub fn namespaces_in_scope(&self) -> impl Iterator<Item = (&str, &str)> {
+		// same as effective_default_namespace(), but borrowed
+		let default_ns = match self.xmlns_prefix {
+			None => self.xmlns.as_ref(),
+			Some(_) => self.attributes.get("xmlns").or(self.inherited_xmlns.as_ref())
+		}.map(|ns| ("", ns.as_str()));
+		let mut namespaces: Vec<(&str, &str)> = self.xmlns_context.iter()
+			.map(|(prefix, ns)| (prefix.as_str(), ns.as_str()))
+			.collect();
+		namespaces.sort();
+		default_ns.into_iter().chain(namespaces)
+	}
+	/**
+	Returns the set of namespace URIs actually used by this element and its descendants, ie the namespaces of the elements and of any prefixed attributes (namespace declarations which are not used by any element or attribute are not included).
+	 */
+	pub fn namespaces_used_in_subtree(&self) -> HashSet<String> {
+		let mut used: HashSet<String> = HashSet::new();
+		for elem in self.self_and_descendants() {
+			if let Some(ns) = &elem.xmlns {
+				used.insert(ns.clone());
+			}
+			let attr_prefixes = elem.attributes.keys()
+				.filter(|k| !Element::is_xmlns_attr(k))
+				.filter_map(|k| k.split_once(':').map(|(prefix, _)| prefix));
+			for prefix in attr_prefixes {
+				if let Some(ns) = elem.xmlns_context.get(prefix) {
+					used.insert(ns.clone());
+				}
+			}
+		}
+		used
+	}
+	/**
+	Recursively replaces the namespace URI `old` with `new` in this element and all of its descendants, both in the elements' namespaces and in the values of `xmlns` and `xmlns:prefix` declarations. The namespaces that the descendants inherit (see [effective_default_namespace()](Element::effective_default_namespace())) follow the rewritten declarations, but are not counted. Returns the number of replacements (elements whose namespace changed plus declarations whose value changed).
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		let mut doc = kiss_xml::parse_str(r#"<root xmlns="internal://draft"><child/></root>"#)?;
+		assert_eq!(doc.root_element_mut().rewrite_namespace_uri("internal://draft", "internal://final"), 3);
+		assert_eq!(doc.root_element().to_string(), "<root xmlns=\"internal://final\">\n  <child/>\n</root>");
+		Ok(())
+	}
+	```
+	 */
+	pub fn rewrite_namespace_uri(&mut self, old: &str, new: &str) -> usize {
+		let mut count = 0;
+		if self.xmlns.as_deref() == Some(old) {
+			self.xmlns = Some(new.to_string());
+			count += 1;
+		}
+		for ns in self.xmlns_context.values_mut() {
+			if ns == old {
+				*ns = new.to_string();
+			}
+		}
+		for (k, v) in self.attributes.iter_mut() {
+			if Element::is_xmlns_attr(k) && v == old {
+				*v = new.to_string();
+				count += 1;
+			}
+		}
+		let default_ns = self.effective_default_namespace();
+		let prefixes = self.xmlns_context.clone();
+		for child in self.child_elements_mut() {
+			count += child.rewrite_namespace_uri(old, new);
+			child.inherited_xmlns = default_ns.clone();
+			child.inherited_xmlns_context = prefixes.clone();
+		}
+		count
+	}
+	/** Gets any and all xmlns prefixes defined in this element (does not include prefix-less default namespace, nor prefixes inherited from a parent element). The iteration order of the returned `HashMap` is arbitrary, so sort the prefixes if the order matters (eg when writing them out), or use [namespaces_in_scope()](Element::namespaces_in_scope()), which is sorted. */
 	pub fn namespace_prefixes(&self) -> Option<HashMap<String, String>> {
 		let prefixes = Self::xmlns_context_from_attributes(&self.attributes);
 		if prefixes.is_empty() {
@@ -804,6 +1886,8 @@ impl Element {
 	pub(crate) fn get_namespace_context(&self) -> HashMap<String, String> {self.xmlns_context.clone()}
 	/** Sets any and all xmlns prefixes this element should inherit. This must include both those that are defined by this element as well as those defined by parent elements up the DOM tree. */
 	pub(crate) fn set_namespace_context(&mut self, parent_default_namespace: Option<String>, parent_prefixes: Option<HashMap<String, String>>) {
+		self.inherited_xmlns = parent_default_namespace.clone();
+		self.inherited_xmlns_context = parent_prefixes.clone().unwrap_or_default();
 		// inherit default namespace unless this element also defines one
 		match self.xmlns_prefix {
 			None => {
@@ -852,9 +1936,37 @@ impl Element {
 			.filter(|n| n.is_element())
 			.map(|n| n.as_element_mut().expect("logic error"))
 	}
-	/** Returns a list of al child nodes (elements, comments, and text components) as an iterator (non-recursive). For a recursive iterator of all children and children-of-children, use [all_children()](all_children())*/
-	pub fn children(&self) -> impl Iterator<Item = &Box<dyn Node>>{
-		self.child_nodes.iter()
+	/** Returns a list of al child nodes (elements, comments, and text components) as an iterator (non-recursive). For a recursive iterator of all children and children-of-children, use [all_children()](all_children())*/
+	pub fn children(&self) -> impl Iterator<Item = &Box<dyn Node>>{
+		self.child_nodes.iter()
+	}
+	/** Returns the first child node of this element (element, text, comment, or CDATA), or `None` if this element has no children. This is the same as `children().next()`. */
+	pub fn first_child_node(&self) -> Option<&Box<dyn Node>> {
+		self.child_nodes.first()
+	}
+	/**
+	Returns the text directly inside this element (the text child nodes joined together, without any text from child elements), or `None` if this element has no text children. In contrast, [text()](Node::text()) also includes the text of all descendant elements and returns an empty string if there is no text.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml::dom::*;
+		let doc = kiss_xml::parse_str("<p>Don't forget <b>me</b> this weekend!<br/></p>")?;
+		let p = doc.root_element();
+		assert_eq!(p.direct_text().unwrap(), "Don't forget  this weekend!");
+		assert_eq!(p.text(), "Don't forget me this weekend!");
+		assert_eq!(p.first_element_by_name("br")?.direct_text(), None);
+		Ok(())
+	}
+	```
+	*/
+	pub fn direct_text(&self) -> Option<String> {
+		let mut texts = self.child_nodes.iter().filter(|c| c.is_text()).peekable();
+		texts.peek()?;
+		Some(texts.map(|c| c.text()).collect())
+	}
+	/** Walks this element and all of its descendants in document order, returning the corresponding SAX-like parsing events (see [Document::events()](Document::events())), starting with the start of this element and ending with the end of this element. */
+	pub fn events(&self) -> ElementEvents<'_> {
+		ElementEvents{start: Some(self), stack: Vec::new()}
 	}
 	/** Returns a recusive iterator to all child nodes (elements, comments, and text components) */
 	pub fn all_children(&self) -> impl Iterator<Item = &Box<dyn Node>>{
@@ -864,6 +1976,200 @@ impl Element {
 	pub fn children_mut(&mut self) -> impl Iterator<Item = &mut Box<dyn Node>>{
 		self.child_nodes.iter_mut()
 	}
+	/**
+	Returns the child nodes that come strictly after the first child matching `start_predicate` and before the next child (after that start) matching `end_predicate`. If no child after the start matches `end_predicate`, then all remaining children are returned. If no child matches `start_predicate`, the returned iterator is empty.
+
+	This is useful for document-style XML, where a header element is followed by the sibling nodes that belong to it. This search is non-recursive.
+	# Example
+	```rust
+	fn main() -> Result<(), Box<dyn std::error::Error>> {
+		use kiss_xml;
+		use kiss_xml::dom::*;
+		let doc = kiss_xml::parse_str(r#"<body>
+		<h1>Intro</h1>
+		<p>Hello</p>
+		<p>World</p>
+		<h1>Chapter 1</h1>
+		<p>Once upon a time...</p>
+	</body>"#)?;
+		let is_h1 = |n: &Box<dyn Node>| n.is_element() && n.as_element().unwrap().name() == "h1";
+		for n in doc.root_element().children_between(is_h1, is_h1) {
+			println!("{}", n.text());
+		}
+		/* prints:
+			Hello
+			World
+		*/
+		Ok(())
+	}
+	```
+	 */
+	pub fn children_between<S, E>(&self, start_predicate: S, end_predicate: E) -> impl Iterator<Item = &Box<dyn Node>>
+		where S: FnMut(&Box<dyn Node>) -> bool, E: FnMut(&Box<dyn Node>) -> bool {
+		let range = self.child_range_between(start_predicate, end_predicate);
+		self.child_nodes[range].iter()
+	}
+	/**
+	Same as [children_between(...)](Element::children_between()), but only considers child elements: returns the child elements that come strictly after the first child element matching `start_predicate` and before the next child element matching `end_predicate` (or to the end if there is no such element). Non-element nodes are skipped. This search is non-recursive.
+	 */
+	pub fn elements_between<S, E>(&self, mut start_predicate: S, mut end_predicate: E) -> impl Iterator<Item = &Element>
+		where S: FnMut(&Element) -> bool, E: FnMut(&Element) -> bool {
+		let range = self.child_range_between(
+			|n| n.is_element() && start_predicate(n.as_element().expect("logic error")),
+			|n| n.is_element() && end_predicate(n.as_element().expect("logic error"))
+		);
+		self.child_nodes[range].iter()
+			.filter(|n| n.is_element())
+			.map(|n| n.as_element().expect("logic error"))
+	}
+	/**
+	Returns the child nodes with the given indices, as an iterator (non-recursive). The indices are in the same index space as [children()](Element::children()), [insert(...)](Element::insert()), and [remove(...)](Element::remove()), so text, comment, and CDATA nodes count as well as elements (see [child_elements_range(...)](Element::child_elements_range()) to count only elements).
+
+	This is meant for paging through very wide elements without iterating from the first child every time. The end of the range is clamped to the number of children, so the last window of a page may be shorter than requested (and a range that ends before it starts is empty), but an `IndexOutOfBounds` error is returned if the range starts after the last child.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml::dom::*;
+		let doc = kiss_xml::parse_str("<list><i>0</i><i>1</i><!-- comment --><i>2</i><i>3</i></list>")?;
+		let list = doc.root_element();
+		let page: Vec<String> = list.children_range(1..3)?.map(|n| n.to_string()).collect();
+		assert_eq!(page, vec!["<i>1</i>", "<!-- comment -->"]);
+		assert_eq!(list.children_range(4..10)?.count(), 1);
+		assert!(list.children_range(6..10).is_err());
+		Ok(())
+	}
+	```
+	 */
+	pub fn children_range(&self, range: Range<usize>) -> Result<impl Iterator<Item = &Box<dyn Node>>, IndexOutOfBounds> {
+		let len = self.child_nodes.len();
+		if range.start > len {
+			return Err(IndexOutOfBounds::new(range.start as isize, Some((0, len as isize))));
+		}
+		let end = range.end.clamp(range.start, len);
+		Ok(self.child_nodes[range.start..end].iter())
+	}
+	/**
+	Same as [children_range(...)](Element::children_range()), but the indices are in the index space of [child_elements()](Element::child_elements()) and [remove_element(...)](Element::remove_element()), which only counts the child elements (eg `child_elements_range(0..10)` returns the first 10 child elements, no matter how many text or comment nodes are between them). The end of the range is clamped to the number of child elements, and an `IndexOutOfBounds` error is returned if the range starts after the last child element.
+
+	Unlike `children_range(...)`, this has to count the child elements, so it takes time proportional to the number of children.
+	 */
+	pub fn child_elements_range(&self, range: Range<usize>) -> Result<impl Iterator<Item = &Element>, IndexOutOfBounds> {
+		let count = self.child_elements().count();
+		if range.start > count {
+			return Err(IndexOutOfBounds::new(range.start as isize, Some((0, count as isize))));
+		}
+		let end = range.end.clamp(range.start, count);
+		Ok(self.child_elements().skip(range.start).take(end - range.start))
+	}
+	/**
+	Returns the child nodes of this element in slices of `chunk_size` nodes (non-recursive), in the same index space as [children_range(...)](Element::children_range()). All chunks have exactly `chunk_size` nodes, except for the last chunk, which has the remaining nodes. If this element has no children, then there are no chunks.
+
+	# Panics
+	Panics if `chunk_size` is 0 (the same as [slice::chunks()]).
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml::dom::*;
+		let doc = kiss_xml::parse_str("<list><i>0</i><i>1</i><i>2</i><i>3</i><i>4</i></list>")?;
+		let sizes: Vec<usize> = doc.root_element().children_chunks(2).map(|c| c.len()).collect();
+		assert_eq!(sizes, vec![2, 2, 1]);
+		Ok(())
+	}
+	```
+	 */
+	pub fn children_chunks(&self, chunk_size: usize) -> impl Iterator<Item = &[Box<dyn Node>]> {
+		self.child_nodes.chunks(chunk_size)
+	}
+	/** index range of child nodes for `children_between(...)` */
+	fn child_range_between<S, E>(&self, mut start_predicate: S, mut end_predicate: E) -> std::ops::Range<usize>
+		where S: FnMut(&Box<dyn Node>) -> bool, E: FnMut(&Box<dyn Node>) -> bool {
+		let start = match self.child_nodes.iter().position(&mut start_predicate) {
+			None => self.child_nodes.len(),
+			Some(i) => i + 1
+		};
+		let end = match self.child_nodes[start..].iter().position(&mut end_predicate) {
+			None => self.child_nodes.len(),
+			Some(i) => start + i
+		};
+		start..end
+	}
+	/**
+	Iterates through this element and all of its descendant elements (children, children of children, etc.) in document order, ie the order in which their start tags appear in the XML.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		let doc = kiss_xml::parse_str("<a><b><c/></b><d/></a>")?;
+		let names: Vec<String> = doc.root_element().self_and_descendants().map(|e| e.name()).collect();
+		assert_eq!(names, vec!["a", "b", "c", "d"]);
+		Ok(())
+	}
+	```
+	 */
+	pub fn self_and_descendants(&self) -> impl Iterator<Item = &Element> {
+		ElementTreeIter{pending: Some(self), stack: Vec::new()}
+	}
+	/**
+	Iterates through all of the descendant elements of this element (children, children of children, etc.) in document order. This is the same as [self_and_descendants()](Element::self_and_descendants()) but without this element itself.
+	 */
+	pub fn descendant_elements(&self) -> impl Iterator<Item = &Element> {
+		ElementTreeIter{pending: None, stack: vec![self.child_nodes.iter()]}
+	}
+	/**
+	Returns `true` if this element or any of its descendants structurally contains the given fragment (see [find_fragment(...)](Element::find_fragment())), otherwise `false`.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		let doc = kiss_xml::parse_str(r#"<config>
+			<sound>
+				<property name="volume" value="11" />
+				<property name="mixer" value="standard" />
+			</sound>
+		</config>"#)?;
+		let fragment = kiss_xml::parse_str(r#"<sound><property name="mixer"/></sound>"#)?;
+		assert!(doc.root_element().contains_fragment(fragment.root_element()));
+		Ok(())
+	}
+	```
+	 */
+	pub fn contains_fragment(&self, fragment: &Element) -> bool {
+		self.find_fragment(fragment).is_some()
+	}
+	/**
+	Finds the first element (in document order, starting with this element itself) that structurally matches the given fragment, or `None` if there is no such element. An element matches the fragment if:
+	* it has the same name as the fragment (and the same namespace, if the fragment has one)
+	* it has every attribute of the fragment with the same value (extra attributes are allowed)
+	* if the fragment has text, its own text (excluding the text of its children) matches the fragment's text, ignoring leading and trailing whitespace
+	* each child element of the fragment matches a child element of this element, in the same order (other child nodes may be interleaved)
+
+	Comments in the fragment are ignored.
+	 */
+	pub fn find_fragment(&self, fragment: &Element) -> Option<&Element> {
+		self.self_and_descendants().find(|e| e.matches_fragment(fragment))
+	}
+	/// returns true if this element (not its descendants) matches the given fragment, as per `find_fragment()`
+	fn matches_fragment(&self, fragment: &Element) -> bool {
+		if self.name != fragment.name {
+			return false;
+		}
+		if fragment.xmlns.is_some() && self.xmlns != fragment.xmlns {
+			return false;
+		}
+		if !fragment.attributes.iter().all(|(k, v)| self.attributes.get(k) == Some(v)) {
+			return false;
+		}
+		let own_text = |e: &Element| e.child_nodes.iter()
+			.filter(|n| n.is_text())
+			.map(|n| n.text())
+			.collect::<String>();
+		let fragment_text = own_text(fragment);
+		if !fragment_text.trim().is_empty() && own_text(self).trim() != fragment_text.trim() {
+			return false;
+		}
+		// ordered subsequence match of the child elements
+		let mut candidates = self.child_elements();
+		fragment.child_elements().all(|fc| candidates.any(|c| c.matches_fragment(fc)))
+	}
 	/** Recursively iterates through all child nodes, as well as children of children. Iteration order is arbitrary and not sequential through the DOM. */
 	pub fn children_recursive(&self) -> Box<dyn Iterator<Item = &Box<dyn Node>> + '_> {
 		Box::new(
@@ -905,13 +2211,24 @@ impl Element {
 	```
 	 */
 	pub fn first_element_by_name(&self, name: impl Into<String>) -> Result<&Element, DoesNotExistError> {
+		self.find_element_by_name(name).ok_or_else(DoesNotExistError::default)
+	}
+	/**
+	Gets the first child element with the given element name, or `None` if there is no such element. This is the same as [first_element_by_name(...)](Element::first_element_by_name()), but returns an `Option` instead of a `Result`.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		let doc = kiss_xml::parse_str(r#"<body><p>Hello there!</p></body>"#)?;
+		assert!(doc.root_element().find_element_by_name("p").is_some());
+		assert!(doc.root_element().find_element_by_name("div").is_none());
+		Ok(())
+	}
+	```
+	 */
+	pub fn find_element_by_name(&self, name: impl Into<String>) -> Option<&Element> {
 		let n: String = name.into();
-		for e in self.child_elements() {
-			if e.name() == n {
-				return Ok(e);
-			}
-		}
-		Err(DoesNotExistError::default())
+		self.child_elements().find(|e| e.name() == n)
 	}
 	/**
 	Gets the first child element with the given element name as a mutable reference. If no such element exists, an error result is returned.
@@ -931,87 +2248,643 @@ impl Element {
 		Ok(())
 	}
 	```
-	 */
-	pub fn first_element_by_name_mut(&mut self, name: impl Into<String>) -> Result<&mut Element, DoesNotExistError> {
-		let n: String = name.into();
-		for e in self.child_elements_mut() {
-			if e.name() == n {
-				return Ok(e);
-			}
+	 */
+	pub fn first_element_by_name_mut(&mut self, name: impl Into<String>) -> Result<&mut Element, DoesNotExistError> {
+		self.find_element_by_name_mut(name).ok_or_else(DoesNotExistError::default)
+	}
+	/**
+	Gets the first child element with the given element name as a mutable reference, or `None` if there is no such element. This is the same as [first_element_by_name_mut(...)](Element::first_element_by_name_mut()), but returns an `Option` instead of a `Result`.
+	 */
+	pub fn find_element_by_name_mut(&mut self, name: impl Into<String>) -> Option<&mut Element> {
+		let n: String = name.into();
+		self.child_elements_mut().find(|e| e.name() == n)
+	}
+	/**
+	Returns the first child element with the given name, appending a new empty child element with that name if there is none. The new element inherits this element's namespace context like any other appended element. If the name is not a valid element name, an error result is returned.
+	 */
+	pub fn ensure_child(&mut self, name: &str) -> Result<&mut Element, KissXmlError> {
+		self.ensure_nth_child(name, 1)
+	}
+	/**
+	Applies [ensure_child(...)](Element::ensure_child()) along a slash-separated path of element names (eg `"sound/effects/reverb"`), creating any missing elements along the way, and returns the last element of the path. The path is relative to this element and uses the same syntax as the other element path functions: a path segment may have a 1-based index in square brackets (eg `"property[2]"`), in which case new elements are appended until there are that many elements with that name. Namespace prefixes in the path are ignored.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml::dom::*;
+		let mut config = Element::new_from_name("config")?;
+		config.ensure_path("sound/property")?.set_attr("volume", "11")?;
+		config.ensure_path("sound/property")?.set_attr("mixer", "standard")?;
+		println!("{}", config);
+		/* prints:
+			<config>
+			  <sound>
+			    <property mixer="standard" volume="11"/>
+			  </sound>
+			</config>
+		*/
+		Ok(())
+	}
+	```
+	 */
+	pub fn ensure_path(&mut self, path: &str) -> Result<&mut Element, KissXmlError> {
+		let parsed = crate::paths::ElementPath::parse(path)?;
+		if parsed.absolute {
+			return Err(ParsingError::new(format!("'{path}' is not a relative path")).into());
+		}
+		let mut elem = self;
+		for seg in parsed.segments {
+			elem = elem.ensure_nth_child(seg.name.as_str(), seg.index)?;
+		}
+		Ok(elem)
+	}
+	/**
+	Builds nested elements from flattened (path, value) pairs, such as `("sound/volume", "11")` for `<sound><volume>11</volume></sound>`. Each path is relative to this element and uses the same syntax as [ensure_path(...)](Element::ensure_path()), which is used to create any missing elements along the way. The value becomes the text of the last element of the path (replacing any existing text, but keeping any child elements). If an element already exists at a path, the `on_duplicate` policy decides whether its text is overwritten or a new sibling element is appended. Returns the number of leaf elements written, or an error naming the offending path if a path is not valid (entries before it will already have been written).
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml::dom::*;
+		let mut config = Element::new_from_name("config")?;
+		let count = config.insert_path_values([
+			("sound/volume".to_string(), "11".to_string()),
+			("sound/mixer".to_string(), "standard".to_string()),
+			("name".to_string(), "My Settings".to_string()),
+		], DuplicatePathPolicy::Overwrite)?;
+		assert_eq!(count, 3);
+		assert_eq!(config.to_string(), "<config>\n  <sound>\n    <volume>11</volume>\n    <mixer>standard</mixer>\n  </sound>\n  <name>My Settings</name>\n</config>");
+		Ok(())
+	}
+	```
+	*/
+	pub fn insert_path_values(&mut self, entries: impl IntoIterator<Item = (String, String)>, on_duplicate: DuplicatePathPolicy) -> Result<usize, KissXmlError> {
+		let mut count = 0;
+		for (path, value) in entries {
+			crate::paths::validate_path(path.as_str())?;
+			let parsed = crate::paths::ElementPath::parse(path.as_str())?;
+			if parsed.absolute {
+				return Err(ParsingError::new(format!("'{path}' is not a relative path")).into());
+			}
+			let (leaf, ancestors) = parsed.segments.split_last()
+				.ok_or_else(|| ParsingError::new(format!("invalid path '{path}': no element names")))?;
+			let mut parent = &mut *self;
+			for seg in ancestors {
+				parent = parent.ensure_nth_child(seg.name.as_str(), seg.index)?;
+			}
+			let exists = parent.nth_child_element_index(leaf.name.as_str(), leaf.index).is_some();
+			if exists && on_duplicate == DuplicatePathPolicy::AppendSibling {
+				parent.append(Element::new_with_text(leaf.name.as_str(), value)?);
+			} else {
+				let elem = parent.ensure_nth_child(leaf.name.as_str(), leaf.index)?;
+				elem.child_nodes.retain(|c| !c.is_text());
+				elem.append_text(value);
+			}
+			count += 1;
+		}
+		Ok(count)
+	}
+	/** returns the index (in `child_nodes`) of the Nth (1-based) child element with the given name */
+	fn nth_child_element_index(&self, name: &str, n: usize) -> Option<usize> {
+		self.child_nodes.iter().enumerate()
+			.filter(|(_, c)| c.is_element() && c.as_element().expect("logic error").name == name)
+			.nth(n.checked_sub(1)?)
+			.map(|(i, _)| i)
+	}
+	/** marks the paths in the trie that match descendants of this element and removes the matches (unless inside an element that is already being removed), returning the number of removed elements */
+	fn remove_path_matches(&mut self, trie: &crate::paths::PathTrie, matched: &mut [bool], inside_removed: bool) -> usize {
+		let mut count = 0;
+		let mut to_remove: Vec<usize> = Vec::new();
+		for (seg, sub_trie) in &trie.children {
+			let i = match self.nth_child_element_index(seg.name.as_str(), seg.index) {
+				None => continue,
+				Some(i) => i
+			};
+			for id in &sub_trie.terminal {
+				matched[*id] = true;
+			}
+			let remove_child = !inside_removed && !sub_trie.terminal.is_empty();
+			if remove_child {
+				to_remove.push(i);
+			}
+			let child = self.child_nodes[i].as_element_mut().expect("logic error");
+			count += child.remove_path_matches(sub_trie, matched, inside_removed || remove_child);
+		}
+		// remove back-to-front so that the indices stay valid
+		to_remove.sort_unstable();
+		for i in to_remove.into_iter().rev() {
+			self.child_nodes.remove(i);
+			count += 1;
+		}
+		count
+	}
+	/**
+	Returns a rough estimate of the number of bytes of memory used by this element and all of its descendants. See [memory_breakdown()](Element::memory_breakdown()) for details.
+	 */
+	pub fn estimated_memory_bytes(&self) -> usize {
+		self.memory_breakdown().estimated_bytes
+	}
+	/**
+	Counts the nodes and attributes in this element (including this element itself) and estimates the memory they use, the same way as [Document::memory_breakdown()].
+	 */
+	pub fn memory_breakdown(&self) -> MemoryBreakdown {
+		let mut breakdown = MemoryBreakdown{estimated_bytes: size_of::<Element>(), ..Default::default()};
+		for elem in self.self_and_descendants() {
+			breakdown.elements += 1;
+			breakdown.attributes += elem.attributes.len();
+			let mut strings: Vec<&String> = vec![&elem.name];
+			strings.extend(elem.xmlns.iter());
+			strings.extend(elem.xmlns_prefix.iter());
+			strings.extend(elem.inherited_xmlns.iter());
+			for (k, v) in elem.attributes.iter().chain(elem.xmlns_context.iter()).chain(elem.inherited_xmlns_context.iter()) {
+				strings.push(k);
+				strings.push(v);
+			}
+			breakdown.estimated_bytes += elem.child_nodes.capacity() * size_of::<Box<dyn Node>>()
+				+ hash_map_bytes(&elem.attributes)
+				+ hash_map_bytes(&elem.xmlns_context)
+				+ hash_map_bytes(&elem.inherited_xmlns_context);
+			for child in &elem.child_nodes {
+				if child.is_element() {
+					// boxed element (its content is counted when the iterator reaches it)
+					breakdown.estimated_bytes += size_of::<Element>();
+				} else if let Ok(t) = child.as_text() {
+					breakdown.text_nodes += 1;
+					breakdown.estimated_bytes += size_of::<Text>();
+					strings.push(&t.content);
+				} else if let Ok(c) = child.as_comment() {
+					breakdown.comments += 1;
+					breakdown.estimated_bytes += size_of::<Comment>();
+					strings.push(&c.comment);
+				} else if let Ok(c) = child.as_cdata() {
+					breakdown.cdata += 1;
+					breakdown.estimated_bytes += size_of::<CData>();
+					strings.push(&c.cdata);
+				}
+			}
+			for s in strings {
+				breakdown.string_bytes += s.len();
+				breakdown.estimated_bytes += s.capacity();
+			}
+		}
+		breakdown
+	}
+	/** depth-first search for the first element (including this one) matching the predicate, recording the child node indices leading to it */
+	fn locate_first(&self, predicate: &dyn Fn(&Element) -> bool, location: &mut Vec<usize>) -> bool {
+		if predicate(self) {
+			return true;
+		}
+		for (i, child) in self.child_nodes.iter().enumerate() {
+			if let Ok(e) = child.as_element() {
+				location.push(i);
+				if e.locate_first(predicate, location) {
+					return true;
+				}
+				location.pop();
+			}
+		}
+		false
+	}
+	/** adds the xmlns declarations which this element and its descendants inherit from ancestors, so that it can be used as a root element */
+	fn into_standalone(mut self) -> Element {
+		if let Some(ns) = self.effective_default_namespace() {
+			if !self.attributes.contains_key("xmlns") {
+				self.attributes.insert("xmlns".to_string(), ns);
+			}
+		}
+		let mut used: Vec<String> = Vec::new();
+		self.collect_undeclared_prefixes(&mut Vec::new(), &mut used);
+		used.sort();
+		for prefix in used {
+			if let Some(ns) = self.xmlns_context.get(&prefix).cloned() {
+				self.attributes.insert(format!("xmlns:{prefix}"), ns);
+			}
+		}
+		self
+	}
+	/** collects the namespace prefixes used in this subtree (by elements or attributes) without being declared within the subtree */
+	fn collect_undeclared_prefixes(&self, declared: &mut Vec<String>, used: &mut Vec<String>) {
+		let declared_len = declared.len();
+		declared.extend(self.attributes.keys().filter_map(|k| k.strip_prefix("xmlns:")).map(|p| p.to_string()));
+		let attr_prefixes = self.attributes.keys()
+			.filter(|k| !Element::is_xmlns_attr(k))
+			.filter_map(|k| k.split_once(':').map(|(p, _)| p));
+		for prefix in self.xmlns_prefix.as_deref().into_iter().chain(attr_prefixes) {
+			if prefix != "xml" && !declared.iter().any(|d| d == prefix) && !used.iter().any(|u| u == prefix) {
+				used.push(prefix.to_string());
+			}
+		}
+		for child in self.child_elements() {
+			child.collect_undeclared_prefixes(declared, used);
+		}
+		declared.truncate(declared_len);
+	}
+	/** gets the Nth (1-based) child element with the given name, appending new elements until it exists */
+	fn ensure_nth_child(&mut self, name: &str, n: usize) -> Result<&mut Element, KissXmlError> {
+		loop {
+			match self.nth_child_element_index(name, n) {
+				Some(i) => return Ok(self.child_nodes[i].as_element_mut().expect("logic error")),
+				None => self.append(Element::new_from_name(name)?)
+			}
+		}
+	}
+	/** Returns a list of all child elements with the given name as an iterator.
+
+	This search is non-recursive, meaning that it only returns children of this element, not children-of-children. For a recursive search, use [search_elements_by_name(...)](search_elements_by_name()) instead.
+	 */
+	pub fn elements_by_name(&self, name: impl Into<String>) ->  impl Iterator<Item = &Element>{
+		let n: String = name.into();
+		self.child_elements().filter(move |c| c.name == n)
+	}
+	/** Returns a list of all child elements with the given name as an iterator.
+
+	This search is non-recursive, meaning that it only returns children of this element, not children-of-children. For a recursive search, use [search_elements_by_name(...)](search_elements_by_name()) instead.
+	 */
+	pub fn elements_by_name_mut(&mut self, name: impl Into<String>) ->  impl Iterator<Item = &mut Element>{
+		let n: String = name.into();
+		self.child_elements_mut().filter(move |c| c.name == n)
+	}
+	/** Gets the attributes for this element as a `HashMap`. Note that the iteration order of a `HashMap` is arbitrary and may change every time the program runs; use [sorted_attributes()](Element::sorted_attributes()) for a deterministic order. */
+	pub fn attributes(&self) -> &HashMap<String, String> {
+		&self.attributes
+	}
+	/**
+	Returns the attributes of this element (including namespace declarations) in the order in which they are written as XML: `xmlns` first, then `xmlns:prefix` declarations, then all other attributes, each sorted by name (see [attribute_order(...)](crate::attribute_order())). Unlike iterating over [attributes()](Element::attributes()), this order never changes between runs.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		let doc = kiss_xml::parse_str(r#"<a z="1" xmlns:p="internal://p" b="2" xmlns="internal://a"/>"#)?;
+		let names: Vec<&str> = doc.root_element().sorted_attributes().into_iter().map(|(k, _)| k.as_str()).collect();
+		assert_eq!(names, vec!["xmlns", "xmlns:p", "b", "z"]);
+		Ok(())
+	}
+	```
+	 */
+	pub fn sorted_attributes(&self) -> Vec<(&String, &String)> {
+		let mut attrs: Vec<(&String, &String)> = self.attributes.iter().collect();
+		attrs.sort_by(crate::attribute_order);
+		attrs
+	}
+	/** Gets the value of an attribute for this Element by name. If there is no such attribute, `None` is returned. Values are stored unescaped: when parsing, character references and entities in the attribute value are decoded (eg `note="x &amp; y"` is read as `x & y`), and they are escaped again when written as XML. */
+	pub fn get_attr(&self, attr_name: impl Into<String>) -> Option<&String> {
+		let n: String = attr_name.into();
+		self.attributes.get(&n)
+	}
+	/**
+	Gets the value of an attribute for this Element by name. This is the same as [get_attr(...)](Element::get_attr()), but returns a `DoesNotExistError` error result if there is no such attribute, for use with the `?` operator.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		let doc = kiss_xml::parse_str(r#"<property name="volume" value="11"/>"#)?;
+		println!("volume = {}", doc.root_element().require_attr("value")?);
+		assert!(doc.root_element().require_attr("units").is_err());
+		Ok(())
+	}
+	```
+	 */
+	pub fn require_attr(&self, attr_name: impl Into<String>) -> Result<&String, DoesNotExistError> {
+		let n: String = attr_name.into();
+		self.attributes.get(&n)
+			.ok_or_else(|| DoesNotExistError::new(format!("element <{}> has no attribute '{}'", self.name, n)))
+	}
+	/** Sets the value of an attribute for this Element by name. Setting an `xmlns` or `xmlns:prefix` attribute declares that namespace for this element and for its descendants (except those that declare it themselves), including any elements appended to it afterwards. */
+	pub fn set_attr(&mut self, attr_name: impl Into<String>, value: impl Into<String>) -> Result<(), InvalidAttributeName> {
+		let n: String = attr_name.into();
+		Element::check_attr_name(n.as_str())?;
+		let v: String = value.into();
+		self.attributes.insert(n.clone(), v);
+		self.sync_xmlns_attr(n.as_str());
+		Ok(())
+	}
+	/**
+	Sets the value of an attribute from a [format_args!(...)](std::format_args) template. Because attribute values are stored unescaped and only escaped when the element is serialized, both the literal parts of the template and the interpolated values are escaped exactly once, so untrusted values can be interpolated without any manual escaping (and without double-escaping the template).
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml::dom::*;
+		let user_input = "Tom & \"Jerry\"";
+		let mut e = Element::new_from_name("greeting")?;
+		e.set_attr_fmt("title", format_args!("Hello, {}!", user_input))?;
+		assert_eq!(e.get_attr("title").unwrap(), "Hello, Tom & \"Jerry\"!");
+		assert_eq!(e.to_string(), "<greeting title=\"Hello, Tom &amp; &quot;Jerry&quot;!\"/>");
+		Ok(())
+	}
+	```
+	*/
+	pub fn set_attr_fmt(&mut self, attr_name: impl Into<String>, args: std::fmt::Arguments) -> Result<(), InvalidAttributeName> {
+		self.set_attr(attr_name, std::fmt::format(args))
+	}
+	/**
+	Visits every attribute of this element with a mutable reference to the attribute's value (attribute names cannot be changed). Changes to `xmlns` and `xmlns:prefix` attributes are applied to this element's namespace context, the same as with [set_attr(...)](Element::set_attr()).
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		let mut doc = kiss_xml::parse_str(r#"<root a=" 1 " b="2  "/>"#)?;
+		doc.root_element_mut().attributes_entries_mut(|_k, v| *v = v.trim().to_string());
+		assert_eq!(doc.root_element().get_attr("a").unwrap(), "1");
+		Ok(())
+	}
+	```
+	 */
+	pub fn attributes_entries_mut<F>(&mut self, mut f: F) where F: FnMut(&str, &mut String) {
+		for (k, v) in self.attributes.iter_mut() {
+			f(k.as_str(), v);
+		}
+		let xmlns_keys: Vec<String> = self.attributes.keys()
+			.filter(|k| Self::is_xmlns_attr(k))
+			.cloned()
+			.collect();
+		for k in xmlns_keys {
+			self.sync_xmlns_attr(k.as_str());
+		}
+	}
+	/**
+	Removes all attributes for which the given predicate function returns `false` (the predicate is given the name and value of each attribute), returning the number of removed attributes. Removing an `xmlns` or `xmlns:prefix` attribute restores the inherited namespace, the same as with [remove_attr(...)](Element::remove_attr()).
+	 */
+	pub fn retain_attrs<P>(&mut self, mut predicate: P) -> usize where P: FnMut(&str, &str) -> bool {
+		let removed: Vec<String> = self.attributes.iter()
+			.filter(|(k, v)| !predicate(k.as_str(), v.as_str()))
+			.map(|(k, _)| k.clone())
+			.collect();
+		for k in &removed {
+			self.attributes.remove(k);
+			self.sync_xmlns_attr(k.as_str());
+		}
+		removed.len()
+	}
+	/**
+	Returns the names of the attributes of this element that have an empty value (eg `id=""`), sorted by name. Namespace declarations with empty values (`xmlns=""` and `xmlns:prefix=""`) are not included, because an empty namespace declaration undeclares a namespace rather than being a mistake.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		let mut doc = kiss_xml::parse_str(r#"<item id="" name="x" title="" xmlns=""/>"#)?;
+		let empty: Vec<&str> = doc.root_element().attrs_with_empty_values().collect();
+		assert_eq!(empty, vec!["id", "title"]);
+		assert_eq!(doc.root_element_mut().remove_empty_attrs(), 2);
+		assert_eq!(doc.to_string(), "<item xmlns=\"\" name=\"x\"/>");
+		Ok(())
+	}
+	```
+	*/
+	pub fn attrs_with_empty_values(&self) -> impl Iterator<Item = &str> {
+		let mut names: Vec<&str> = self.attributes.iter()
+			.filter(|(k, v)| v.is_empty() && !Element::is_xmlns_attr(k))
+			.map(|(k, _)| k.as_str())
+			.collect();
+		names.sort();
+		names.into_iter()
+	}
+	/**
+	Removes the attributes of this element that have an empty value (see [attrs_with_empty_values()](Element::attrs_with_empty_values())), returning the number of removed attributes (non-recursive, see [Document::remove_empty_attrs()](Document::remove_empty_attrs()) for the whole document). Empty namespace declarations are kept; to remove those as well, use `retain_attrs(|_, v| !v.is_empty())`, which also updates this element's namespace context.
+	*/
+	pub fn remove_empty_attrs(&mut self) -> usize {
+		self.retain_attrs(|k, v| !v.is_empty() || Element::is_xmlns_attr(k))
+	}
+	/// recursive implementation of `Document::remove_empty_attrs()`
+	fn remove_empty_attrs_recursive(&mut self) -> usize {
+		let mut count = self.remove_empty_attrs();
+		for child in self.child_elements_mut() {
+			count += child.remove_empty_attrs_recursive();
 		}
-		Err(DoesNotExistError::default())
+		count
 	}
-	/** Returns a list of all child elements with the given name as an iterator.
-
-	This search is non-recursive, meaning that it only returns children of this element, not children-of-children. For a recursive search, use [search_elements_by_name(...)](search_elements_by_name()) instead.
-	 */
-	pub fn elements_by_name(&self, name: impl Into<String>) ->  impl Iterator<Item = &Element>{
-		let n: String = name.into();
-		self.child_elements().filter(move |c| c.name == n)
+	/** returns true if the given attribute name is a namespace declaration (`xmlns` or `xmlns:prefix`) */
+	fn is_xmlns_attr(key: &str) -> bool {
+		key == "xmlns" || key.starts_with("xmlns:")
 	}
-	/** Returns a list of all child elements with the given name as an iterator.
+	/**
+	Updates the namespace context of this element and its descendants after the given attribute was added, changed, or removed. Only `xmlns` and `xmlns:prefix` attributes affect the context; other attributes are ignored.
 
-	This search is non-recursive, meaning that it only returns children of this element, not children-of-children. For a recursive search, use [search_elements_by_name(...)](search_elements_by_name()) instead.
+	When a declaration is removed, the binding inherited from the parent element applies again. The new binding is passed down to the descendants that do not declare it themselves, the same way as when the XML is parsed.
 	 */
-	pub fn elements_by_name_mut(&mut self, name: impl Into<String>) ->  impl Iterator<Item = &mut Element>{
-		let n: String = name.into();
-		self.child_elements_mut().filter(move |c| c.name == n)
-	}
-	/** Gets the attributes for this element as a `HashMap` */
-	pub fn attributes(&self) -> &HashMap<String, String> {
-		&self.attributes
+	fn sync_xmlns_attr(&mut self, key: &str) {
+		if key == "xmlns" {
+			if self.xmlns_prefix.is_none() {
+				self.xmlns = self.attributes.get(key).cloned().or_else(|| self.inherited_xmlns.clone());
+			}
+			let default_ns = self.effective_default_namespace();
+			for child in self.child_elements_mut() {
+				child.inherit_default_namespace(default_ns.clone());
+			}
+		} else if let Some(prefix) = key.strip_prefix("xmlns:") {
+			let ns = self.attributes.get(key).or_else(|| self.inherited_xmlns_context.get(prefix)).cloned();
+			match &ns {
+				Some(ns) => {self.xmlns_context.insert(prefix.to_string(), ns.clone());},
+				None => {self.xmlns_context.remove(prefix);}
+			}
+			if self.xmlns_prefix.as_deref() == Some(prefix) {
+				self.xmlns = ns.clone();
+			}
+			for child in self.child_elements_mut() {
+				child.inherit_prefix(prefix, ns.clone());
+			}
+		}
 	}
-	/** Gets the value of an attribute for this Element by name. If there is no such attribute, `None` is returned */
-	pub fn get_attr(&self, attr_name: impl Into<String>) -> Option<&String> {
-		let n: String = attr_name.into();
-		self.attributes.get(&n)
+	/** Updates the default namespace inherited from the parent element, and passes the change on to the descendants (unless this element declares its own default namespace) */
+	fn inherit_default_namespace(&mut self, ns: Option<String>) {
+		let old_default_ns = self.effective_default_namespace();
+		if self.xmlns_prefix.is_none() && !self.attributes.contains_key("xmlns") && self.xmlns == self.inherited_xmlns {
+			self.xmlns = ns.clone();
+		}
+		self.inherited_xmlns = ns;
+		let default_ns = self.effective_default_namespace();
+		if default_ns != old_default_ns {
+			for child in self.child_elements_mut() {
+				child.inherit_default_namespace(default_ns.clone());
+			}
+		}
 	}
-	/** Sets the value of an attribute for this Element by name. */
-	pub fn set_attr(&mut self, attr_name: impl Into<String>, value: impl Into<String>) -> Result<(), InvalidAttributeName> {
-		let n: String = attr_name.into();
-		Element::check_attr_name(n.as_str())?;
-		let v: String = value.into();
-		self.attributes.insert(n, v);
-		Ok(())
+	/** Updates the namespace bound to the given prefix inherited from the parent element, and passes the change on to the descendants (unless this element declares the prefix itself) */
+	fn inherit_prefix(&mut self, prefix: &str, ns: Option<String>) {
+		match &ns {
+			Some(ns) => {self.inherited_xmlns_context.insert(prefix.to_string(), ns.clone());},
+			None => {self.inherited_xmlns_context.remove(prefix);}
+		}
+		if self.attributes.contains_key(&format!("xmlns:{prefix}")) {
+			return;
+		}
+		let old_ns = self.xmlns_context.get(prefix).cloned();
+		if old_ns == ns {
+			return;
+		}
+		match &ns {
+			Some(ns) => {self.xmlns_context.insert(prefix.to_string(), ns.clone());},
+			None => {self.xmlns_context.remove(prefix);}
+		}
+		if self.xmlns_prefix.as_deref() == Some(prefix) && self.xmlns == old_ns {
+			self.xmlns = ns.clone();
+		}
+		for child in self.child_elements_mut() {
+			child.inherit_prefix(prefix, ns.clone());
+		}
 	}
 
 
-	/// singleton regex matcher
-	const ATTR_NAME_CHECKER_SINGLETON: OnceCell<Regex> = OnceCell::new();
 	/// Checks if an attribute name is valid
-	fn check_attr_name(name: &str) -> Result<(), InvalidAttributeName> {
-		let singleton = Element::ATTR_NAME_CHECKER_SINGLETON;
-		let checker = singleton.get_or_init(
-			|| Regex::new(r#"^[_a-zA-Z]\S*$"#).unwrap()
-		);
-		if checker.is_match(name) {
-			Ok(())
-		} else {
-			Err(InvalidAttributeName::new(format!("'{}' is not a valid attribute name", name)))
+	pub(crate) fn check_attr_name(name: &str) -> Result<(), InvalidAttributeName> {
+		// every part must follow the XML name rules (empty parts are reported below)
+		if name.is_empty() || name.starts_with(':')
+			|| !name.split(':').all(|part| part.is_empty() || crate::is_ncname(part)) {
+			return Err(InvalidAttributeName::new(format!("'{}' is not a valid attribute name", name)));
+		}
+		// check namespace prefix syntax
+		match name.split_once(':') {
+			None => Ok(()),
+			Some((prefix, local_name)) => {
+				if local_name.contains(':') {
+					Err(InvalidAttributeName::new(format!(
+						"'{}' is not a valid attribute name (at most one ':' is allowed, separating the namespace prefix from the name)", name
+					)))
+				} else if prefix == "xmlns" && local_name.is_empty() {
+					Err(InvalidAttributeName::new(format!(
+						"'{}' is not a valid namespace declaration (use 'xmlns' to declare the default namespace or 'xmlns:prefix' to declare a prefixed namespace)", name
+					)))
+				} else if local_name.is_empty() {
+					Err(InvalidAttributeName::new(format!(
+						"'{}' is not a valid attribute name (a namespace prefix must be followed by a name, eg 'prefix:name')", name
+					)))
+				} else {
+					Ok(())
+				}
+			}
 		}
 	}
-	/// singleton regex matcher
-	const NAME_CHECKER_SINGLETON: OnceCell<Regex> = OnceCell::new();
-	/// Checks if an attribute name is valid
+	/// Checks if an element name (without namespace prefix) is valid
 	fn check_elem_name(name: &str) -> Result<(), InvalidElementName> {
-		let singleton = Element::NAME_CHECKER_SINGLETON;
-		let checker = singleton.get_or_init(
-			|| Regex::new(r#"^[_a-zA-Z]\S*$"#).unwrap()
-		);
-		if checker.is_match(name) {
+		if crate::is_ncname(name) {
 			Ok(())
+		} else if name.contains(':') {
+			Err(InvalidElementName::new(format!(
+				"'{}' is not a valid name (namespace prefixes are set separately from the element name)", name
+			)))
 		} else {
 			Err(InvalidElementName::new(format!("'{}' is not a valid name", name)))
 		}
 	}
-	/** Deletes an attribute from this element */
+	/** Deletes an attribute from this element. Removing an `xmlns` or `xmlns:prefix` attribute restores the namespace inherited from the parent element (if any) for this element and its descendants. */
 	pub fn remove_attr(&mut self, attr_name: impl Into<String>) -> Option<String> {
 		let n: String = attr_name.into();
-		self.attributes.remove(&n)
+		let removed = self.attributes.remove(&n);
+		if removed.is_some() {
+			self.sync_xmlns_attr(n.as_str());
+		}
+		removed
+	}
+	/**
+	Compares the attributes of this element to those of another element (including `xmlns` declarations), returning the attributes to add, remove, and change to turn this element's attributes into the other's. Element names, namespaces, and children are not compared.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		let a = kiss_xml::parse_str(r#"<property name="width" value="10" unit="px"/>"#)?;
+		let b = kiss_xml::parse_str(r#"<property name="width" value="12" min="0"/>"#)?;
+		let diff = a.root_element().attr_diff(b.root_element());
+		assert_eq!(diff.added, vec![("min".to_string(), "0".to_string())]);
+		assert_eq!(diff.removed, vec!["unit".to_string()]);
+		assert_eq!(diff.changed, vec![("value".to_string(), "10".to_string(), "12".to_string())]);
+		let mut patched = a.root_element().clone();
+		patched.apply_attr_diff(&diff)?;
+		assert_eq!(patched.attributes(), b.root_element().attributes());
+		Ok(())
+	}
+	```
+	*/
+	pub fn attr_diff(&self, other: &Element) -> AttrDiff {
+		let mut diff = AttrDiff::default();
+		for (k, v) in other.attributes.iter() {
+			match self.attributes.get(k) {
+				None => diff.added.push((k.clone(), v.clone())),
+				Some(old) if old != v => diff.changed.push((k.clone(), old.clone(), v.clone())),
+				_ => {}
+			}
+		}
+		diff.removed = self.attributes.keys().filter(|k| !other.attributes.contains_key(*k)).cloned().collect();
+		diff.added.sort();
+		diff.removed.sort();
+		diff.changed.sort();
+		diff
+	}
+	/**
+	Applies the attribute changes from [attr_diff(...)](Element::attr_diff()) to this element. Changes to `xmlns` and `xmlns:prefix` attributes are applied to this element's namespace context, the same as with [set_attr(...)](Element::set_attr()) and [remove_attr(...)](Element::remove_attr()). The diff is checked before anything is changed, so if an added attribute name is invalid or a removed or changed attribute does not exist on this element, an error is returned and this element is left unmodified.
+	*/
+	pub fn apply_attr_diff(&mut self, diff: &AttrDiff) -> Result<(), KissXmlError> {
+		for (k, _) in diff.added.iter() {
+			Element::check_attr_name(k.as_str())?;
+		}
+		for k in diff.removed.iter().chain(diff.changed.iter().map(|(k, _, _)| k)) {
+			if !self.attributes.contains_key(k) {
+				return Err(DoesNotExistError::new(format!("element <{}> has no attribute '{}'", self.name, k)).into());
+			}
+		}
+		for k in diff.removed.iter() {
+			self.remove_attr(k.as_str());
+		}
+		for (k, v) in diff.changed.iter().map(|(k, _, v)| (k, v)).chain(diff.added.iter().map(|(k, v)| (k, v))) {
+			self.set_attr(k.as_str(), v.as_str())?;
+		}
+		Ok(())
 	}
 	/** Deletes all attributes from this element */
 	pub fn clear_attributes(&mut self) {
-		self.attributes.clear()
+		self.retain_attrs(|_, _| false);
+	}
+	/** Returns the attribute names of any `nil` attributes whose prefix resolves to the XML Schema instance namespace */
+	fn xsi_nil_attr_names(&self) -> Vec<String> {
+		self.attributes.keys()
+			.filter(|k| match k.split_once(':') {
+				Some((prefix, "nil")) => self.xmlns_context.get(prefix).map(|s| s.as_str()) == Some(XSI_NAMESPACE),
+				_ => false
+			})
+			.cloned()
+			.collect()
+	}
+	/**
+	Returns `true` if this element is marked as nil with the XML Schema instance `nil` attribute (eg `<value xsi:nil="true"/>`), which is commonly used to represent null values. The attribute's prefix must resolve to the namespace `http://www.w3.org/2001/XMLSchema-instance` (the prefix does not have to be `xsi`) and its value must be `true` or `1`.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		let doc = kiss_xml::parse_str(r#"<root xmlns:i="http://www.w3.org/2001/XMLSchema-instance">
+		<value i:nil="true"/>
+	</root>"#)?;
+		assert!(doc.root_element().first_element_by_name("value")?.is_nil());
+		Ok(())
+	}
+	```
+	 */
+	pub fn is_nil(&self) -> bool {
+		self.xsi_nil_attr_names().iter()
+			.any(|k| matches!(self.attributes.get(k).map(|v| v.trim()), Some("true") | Some("1")))
+	}
+	/**
+	Marks this element as nil (or not nil) with the XML Schema instance `nil` attribute. If `nil` is true and no prefix for the namespace `http://www.w3.org/2001/XMLSchema-instance` is in scope, then an `xmlns:xsi` declaration is added to this element. If `nil` is false, any XML Schema instance `nil` attributes are removed (namespace declarations are left as-is).
+	 */
+	pub fn set_nil(&mut self, nil: bool) {
+		for k in self.xsi_nil_attr_names() {
+			self.attributes.remove(&k);
+		}
+		if !nil {return;}
+		let mut prefixes: Vec<&String> = self.xmlns_context.iter()
+			.filter(|(_, ns)| ns.as_str() == XSI_NAMESPACE)
+			.map(|(prefix, _)| prefix)
+			.collect();
+		prefixes.sort();
+		let prefix = match prefixes.first() {
+			Some(prefix) => prefix.to_string(),
+			None => {
+				// declare the xsi namespace here, avoiding prefixes that are already taken
+				let mut prefix = String::from("xsi");
+				let mut n = 1;
+				while self.xmlns_context.contains_key(&prefix) {
+					prefix = format!("xsi{n}");
+					n += 1;
+				}
+				self.attributes.insert(format!("xmlns:{prefix}"), XSI_NAMESPACE.to_string());
+				self.xmlns_context.insert(prefix.clone(), XSI_NAMESPACE.to_string());
+				prefix
+			}
+		};
+		self.attributes.insert(format!("{prefix}:nil"), "true".to_string());
 	}
 	/**
 	Performs a recursive search of all child nodes of this element (and all children of child elements, etc), returning an iterator of all nodes matching the given predicate.
@@ -1078,11 +2951,8 @@ impl Element {
 	```
 	 */
 	pub fn search_elements<'a, P>(&'a self, predicate: P) ->  Box<dyn Iterator<Item = &Element> + '_> where P: FnMut(&&Element) -> bool + 'a {
-		// recursive
 		Box::new(
-			self.search(|n| n.is_element())
-				.map(|n| n.as_element().expect("logic error"))
-				.filter(predicate)
+			self.descendant_elements().filter(predicate)
 		)
 	}
 	/**
@@ -1139,6 +3009,54 @@ impl Element {
 		)
 	}
 	/**
+	Replaces the content of every comment under this element (recursively) that matches the given predicate with the given replacement text, returning the number of replaced comments. If the replacement is not valid comment content, an error result is returned and the element is not modified. See [Document::scrub_comments(...)](Document::scrub_comments()) for an example.
+	 */
+	pub fn scrub_comments<P>(&mut self, predicate: P, replacement: &str) -> Result<usize, InvalidContent> where P: Fn(&Comment) -> bool {
+		let replacement = Comment::new(replacement)?;
+		Ok(self.scrub_comments_with(&predicate, &replacement))
+	}
+	/// recursive implementation of `scrub_comments()`
+	fn scrub_comments_with<P>(&mut self, predicate: &P, replacement: &Comment) -> usize where P: Fn(&Comment) -> bool {
+		let mut count = 0;
+		for c in self.child_nodes.iter_mut() {
+			if let Ok(e) = c.as_element_mut() {
+				count += e.scrub_comments_with(predicate, replacement);
+			} else if let Ok(comment) = c.as_comment_mut() {
+				if predicate(comment) {
+					*comment = replacement.clone();
+					count += 1;
+				}
+			}
+		}
+		count
+	}
+	/**
+	Compares this element to another, the same as `==` but ignoring any comments (at any depth) for which the given predicate returns true (use `|_| true` to ignore all comments). See [Document::eq_ignore_comments(...)](Document::eq_ignore_comments()) for an example.
+	 */
+	pub fn eq_ignore_comments<P>(&self, other: &Element, predicate: P) -> bool where P: Fn(&Comment) -> bool {
+		self.eq_ignore_comments_with(other, &predicate)
+	}
+	/// recursive implementation of `eq_ignore_comments()`
+	fn eq_ignore_comments_with<P>(&self, other: &Element, predicate: &P) -> bool where P: Fn(&Comment) -> bool {
+		if self.name != other.name || self.xmlns != other.xmlns
+			|| self.xmlns_prefix != other.xmlns_prefix
+			|| self.attributes != other.attributes {
+			return false;
+		}
+		fn kept<'e, P>(e: &'e Element, predicate: &P) -> Vec<&'e Box<dyn Node>> where P: Fn(&Comment) -> bool {
+			e.child_nodes.iter()
+				.filter(|n| !n.as_comment().is_ok_and(predicate))
+				.collect()
+		}
+		let (mine, theirs) = (kept(self, predicate), kept(other, predicate));
+		mine.len() == theirs.len() && mine.iter().zip(theirs.iter()).all(|(a, b)| {
+			match (a.as_element(), b.as_element()) {
+				(Ok(ea), Ok(eb)) => ea.eq_ignore_comments_with(eb, predicate),
+				_ => node_eq(a, b)
+			}
+		})
+	}
+	/**
 	Appends the given node to the children of this element.
 
 	# Example
@@ -1165,11 +3083,46 @@ impl Element {
 	}
 	/** same as [append(...)](Element::append()) but for a Box&lt;dyn Node&gt; */
 	pub fn append_boxed(&mut self, mut node: Box<dyn Node>) {
-		Self::apply_xmlns_context_to_child_node(self.default_namespace(), self.xmlns_context.clone(), &mut node);
+		Self::apply_xmlns_context_to_child_node(self.effective_default_namespace(), self.xmlns_context.clone(), &mut node);
 		self.child_nodes.push(node);
 		// clean-up text nodes
 		self.cleanup_text_nodes();
 	}
+	/** Appends a new text node with the given content to the children of this element (same as `append(Text::new(text))`) */
+	pub fn append_text(&mut self, text: impl Into<String>) {
+		self.append(Text::new(text));
+	}
+	/** Appends a new text node formatted from a [format_args!(...)](std::format_args) template (same as `append_text(format!(...))`). Text is stored unescaped and escaped when serialized, so interpolated values never need to be escaped by hand. */
+	pub fn append_text_fmt(&mut self, args: std::fmt::Arguments) {
+		self.append_text(std::fmt::format(args));
+	}
+	/** Appends a new comment with the given text to the children of this element (same as `append(Comment::new(text)?)`), returning an error if the text is not valid comment content */
+	pub fn append_comment(&mut self, text: impl Into<String>) -> Result<(), InvalidContent> {
+		self.append(Comment::new(text)?);
+		Ok(())
+	}
+	/** Appends a new CDATA node with the given content to the children of this element (same as `append(CData::new(text)?)`), returning an error if the text is not valid CDATA content */
+	pub fn append_cdata(&mut self, text: impl Into<String>) -> Result<(), InvalidContent> {
+		self.append(CData::new(text)?);
+		Ok(())
+	}
+	/**
+	Appends a new empty child element with the given name and returns a mutable reference to it, returning an error if the name is not a valid element name. The new element inherits this element's namespace context, as with [append(...)](Element::append()).
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml::dom::*;
+		let mut doc = Document::new(Element::new_from_name("album")?);
+		doc.root_element_mut().append_element("song")?.append_text("I Believe I Can Fly");
+		assert_eq!(doc.root_element().to_string(), "<album>\n  <song>I Believe I Can Fly</song>\n</album>");
+		Ok(())
+	}
+	```
+	 */
+	pub fn append_element(&mut self, name: &str) -> Result<&mut Element, KissXmlError> {
+		self.append(Element::new_from_name(name)?);
+		Ok(self.child_nodes.last_mut().expect("logic error").as_element_mut().expect("logic error"))
+	}
 	/** Applies this element's context to the given child */
 	fn apply_xmlns_context_to_child_node(df_xmlns: Option<String>, xmlns_context: HashMap<String, String>, node: &mut Box<dyn Node>) {
 		let is_element = node.is_element();
@@ -1188,6 +3141,46 @@ impl Element {
 			Some(xmlns_context)
 		);
 	}
+	/**
+	Converts the text child nodes of this element into CDATA nodes in place, returning the number of converted nodes. Text containing `]]>` cannot be stored as CDATA and is left as text. This is non-recursive, only the direct children of this element are converted.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		let mut doc = kiss_xml::parse_str("<code>if a &lt; b &amp;&amp; c &gt; d</code>")?;
+		assert_eq!(doc.root_element_mut().convert_text_children_to_cdata(), 1);
+		assert_eq!(doc.root_element().to_string(), "<code><![CDATA[if a < b && c > d]]></code>");
+		Ok(())
+	}
+	```
+	 */
+	pub fn convert_text_children_to_cdata(&mut self) -> usize {
+		let mut count = 0;
+		for node in self.child_nodes.iter_mut() {
+			if node.is_text() {
+				if let Ok(cdata) = Text::new(node.text()).into_cdata() {
+					*node = cdata.boxed();
+					count += 1;
+				}
+			}
+		}
+		count
+	}
+	/**
+	Converts the CDATA child nodes of this element into text nodes in place, returning the number of converted nodes. As when appending text, the converted text is merged with any adjacent text nodes and whitespace-only text is discarded. This is non-recursive, only the direct children of this element are converted.
+	 */
+	pub fn convert_cdata_children_to_text(&mut self) -> usize {
+		let mut count = 0;
+		for node in self.child_nodes.iter_mut() {
+			if node.is_cdata() {
+				*node = Text::new(node.text()).boxed();
+				count += 1;
+			}
+		}
+		if count > 0 {
+			self.cleanup_text_nodes();
+		}
+		count
+	}
 	/** Discards merges sequential text nodes and then whitespace-only text nodes */
 	fn cleanup_text_nodes(&mut self) {
 		// check if there are children
@@ -1260,7 +3253,7 @@ impl Element {
 		// then apply the xmlns context to the elements
 		for i in elem_indices {
 			Self::apply_xmlns_context_to_child_node(
-				self.default_namespace(), self.xmlns_context.clone(),
+				self.effective_default_namespace(), self.xmlns_context.clone(),
 			&mut self.child_nodes[i]
 			);
 		}
@@ -1277,8 +3270,8 @@ impl Element {
 		// Note: if this is an element, set the namespace context
 		self.child_nodes.insert(index, node.boxed());
 		Self::apply_xmlns_context_to_child_node(
-			self.default_namespace(), self.xmlns_context.clone(),
-			self.child_nodes.last_mut().unwrap()
+			self.effective_default_namespace(), self.xmlns_context.clone(),
+			&mut self.child_nodes[index]
 		);
 		// clean-up text nodes
 		self.cleanup_text_nodes();
@@ -1350,6 +3343,43 @@ impl Element {
 		}
 		return count;
 	}
+	/**
+	Finds child nodes that are equal to an earlier child node of this element (see [node_eq(...)](node_eq())), returning a list of `(earlier_index, later_index)` pairs of child node indices, in order of the later index. Each duplicate is paired with the first child node it is equal to. Element children are compared by deep equality (name, namespace, attributes, and all descendants), while text, comment, and CDATA children are compared by content.
+
+	This search is non-recursive and uses hashing, so each child is only deep-compared against earlier children with the same hash.
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		use kiss_xml;
+		let doc = kiss_xml::parse_str(r#"<list><item id="1"/><item id="2"/><item id="1"/></list>"#)?;
+		assert_eq!(doc.root_element().find_duplicate_children(), vec![(0, 2)]);
+		Ok(())
+	}
+	```
+	 */
+	pub fn find_duplicate_children(&self) -> Vec<(usize, usize)> {
+		use std::collections::hash_map::DefaultHasher;
+		let mut firsts: HashMap<u64, Vec<usize>> = HashMap::new();
+		let mut dupes: Vec<(usize, usize)> = Vec::new();
+		for (i, node) in self.child_nodes.iter().enumerate() {
+			let mut hasher = DefaultHasher::new();
+			hash_node(&**node, &mut hasher);
+			let candidates = firsts.entry(hasher.finish()).or_default();
+			match candidates.iter().find(|j| node_eq(&self.child_nodes[**j], node)) {
+				Some(j) => dupes.push((*j, i)),
+				None => candidates.push(i)
+			}
+		}
+		dupes
+	}
+	/** Removes every child node that is equal to an earlier child node of this element (see [find_duplicate_children()](Element::find_duplicate_children())), keeping the first of each set of equal children. Returns the number of removed nodes (non-recursive). */
+	pub fn dedup_equal_children(&mut self) -> usize {
+		let dupes = self.find_duplicate_children();
+		for (_, i) in dupes.iter().rev() {
+			self.child_nodes.remove(*i);
+		}
+		dupes.len()
+	}
 	/** Removes the Nth child element from this element, returning it as a result (or an `IndexOutOfBounds` error result if the index is out of range) */
 	pub fn remove_element(&mut self, index: usize) -> Result<Element, IndexOutOfBounds> {
 		// first, index the child elements
@@ -1410,9 +3440,18 @@ impl Element {
 		self.remove_elements(move |e| e.name == n)
 	}
 
+	/**
+	Produces the XML text representing this element and its children using the provided output options (see [OutputOptions] for details).
+	 */
+	pub fn to_string_with_options(&self, options: &OutputOptions) -> String {
+		self.to_string_with_prefix_and_options("", &checked_output_options(options), false, None, &HashMap::new())
+	}
+
 	/// Implementation of writing DOM to XML string
-	/// (inline = true to bypass pretty-printing
-	fn to_string_with_prefix_and_indent(&self, prefix: &str, indent: &str, mut inline: bool) -> String {
+	/// (inline = true to bypass pretty-printing, filter skips denied elements and attributes,
+	/// in_scope holds the namespace bindings declared by the ancestors being written, as
+	/// prefix -> namespace with "" for the default namespace)
+	fn to_string_with_prefix_and_options(&self, prefix: &str, options: &OutputOptions, mut inline: bool, filter: Option<&FilterContext>, in_scope: &HashMap<String, String>) -> String {
 		let mut out = String::new();
 		if !inline {out.push_str(prefix)}
 		// tag name
@@ -1420,24 +3459,43 @@ impl Element {
 		out.push_str("<");
 		out.push_str(tag_name.as_str());
 
-		// attributes
-		let mut attrs: Vec<(&String, &String)> = self.attributes().iter().map(|kv| (kv.0, kv.1)).collect();
-		attrs.sort_by(crate::attribute_order);  // ensure consistent and predictable attribute ordering
-		for (k, v) in attrs {
-			out.push_str(" ");
-			out.push_str(k.as_str());
-			out.push_str("=\"");
-			out.push_str(crate::attribute_escape(v).as_str());
-			out.push_str("\"");
+		// attributes, in a fixed order (omitting namespace declarations that are already in scope, if requested)
+		let mut scope: Cow<HashMap<String, String>> = Cow::Borrowed(in_scope);
+		let mut attrs: Vec<(&String, &String)> = Vec::with_capacity(self.attributes.len());
+		for (k, v) in self.sorted_attributes() {
+			if !filter.is_none_or(|f| f.keeps_attr(k)) {continue;}
+			if options.omit_redundant_namespace_declarations {
+				let ns_prefix = match k.as_str() {
+					"xmlns" => Some(""),
+					_ => k.strip_prefix("xmlns:")
+				};
+				if let Some(ns_prefix) = ns_prefix {
+					if in_scope.get(ns_prefix) == Some(v) {continue;}
+					scope.to_mut().insert(ns_prefix.to_string(), v.clone());
+				}
+			}
+			attrs.push((k, v));
 		}
+		crate::write_attributes(&mut out, attrs.into_iter().map(|(k, v)| (k.as_str(), v.as_str())), options.escape_attribute_whitespace);
 		// children (or not)
-		let child_count = self.child_nodes.len();
+		let child_nodes: Vec<&Box<dyn Node>> = self.child_nodes.iter()
+			.filter(|c| match (filter, c.as_element()) {
+				(Some(f), Ok(e)) => !f.skips(e),
+				_ => true
+			})
+			.collect();
+		let child_count = child_nodes.len();
 		if child_count == 0 {
+			if options.space_before_self_close {out.push(' ');}
 			out.push_str("/>");
-		} else if child_count == 1 && !self.child_nodes[0].is_element() {
+		} else if child_count == 1 && !child_nodes[0].is_element() {
 			// single non-element child, display inline
 			out.push_str(">");
-			out.push_str(&self.child_nodes[0].to_string_with_indent(""));
+			let child = child_nodes[0];
+			match child.is_text() {
+				true => out.push_str(crate::text_escape(child.text()).as_str()),
+				false => out.push_str(&child.to_string_with_indent(""))
+			}
 			out.push_str("</");
 			out.push_str(tag_name.as_str());
 			out.push_str(">");
@@ -1453,12 +3511,12 @@ impl Element {
 			-- http://usingxml.com/Basics/XmlSpace
 			*/
 			// check if this is a mixed element
-			inline = inline || self.child_nodes.iter().any(|n| n.is_text());
+			inline = inline || child_nodes.iter().any(|n| n.is_text());
 			if !inline{out.push('\n');}
 			// prettify variables
 			let mut next_prefix = String::from(prefix);
-			next_prefix.push_str(indent);
-			for c in &self.child_nodes {
+			next_prefix.push_str(options.indent.as_str());
+			for c in child_nodes {
 				if c.is_text() {
 					// text is always inline
 					let text = crate::text_escape(c.text());
@@ -1467,12 +3525,12 @@ impl Element {
 					// child element, recurse
 					out.push_str(
 						c.as_element().expect("logic error")
-							.to_string_with_prefix_and_indent(next_prefix.as_str(), indent, inline).as_str()
+							.to_string_with_prefix_and_options(next_prefix.as_str(), options, inline, filter, &scope).as_str()
 					);
 				} else {
 					// other
 					if !(inline) {out.push_str(next_prefix.as_str());}
-					out.push_str(c.to_string_with_indent(indent).as_str());
+					out.push_str(c.to_string_with_indent(options.indent.as_str()).as_str());
 				}
 				if !inline {out.push('\n');}
 			}
@@ -1516,6 +3574,8 @@ impl Node for Element {
 		false
 	}
 
+	fn node_type(&self) -> DomNodeType {DomNodeType::ElementNode}
+
 	fn as_element(&self) -> Result<&Element, TypeCastError> {Ok(&self)}
 
 	fn as_comment(&self) -> Result<&Comment, TypeCastError> {Err(TypeCastError::new("Cannot cast Element as Comment"))}
@@ -1541,13 +3601,7 @@ impl Node for Element {
 	fn as_any_mut(&mut self) -> &mut dyn Any{self}
 
 	fn to_string_with_indent(&self, indent: &str) -> String {
-		match crate::validate_indent(indent){
-			Ok(_) => self.to_string_with_prefix_and_indent("", indent, false),
-			Err(_) => {
-				eprintln!("WARNING: {:?} is not a valid indentation. Must be either 1 tab or any number of spaces. The default of 2 spaces will be used instead", indent);
-				self.to_string_with_prefix_and_indent("", "  ", false)
-			}
-		}
+		self.to_string_with_options(&OutputOptions{indent: indent.to_string(), ..Default::default()})
 	}
 
 	fn boxed(self) -> Box<dyn Node> {
@@ -1568,6 +3622,8 @@ impl Clone for Element {
 			xmlns: self.xmlns.clone(),
 			xmlns_prefix: self.xmlns_prefix.clone(),
 			xmlns_context: self.xmlns_context.clone(),
+			inherited_xmlns: self.inherited_xmlns.clone(),
+			inherited_xmlns_context: self.inherited_xmlns_context.clone(),
 		}
 	}
 }
@@ -1581,6 +3637,8 @@ impl Default for Element {
 			xmlns: None,
 			xmlns_prefix: None,
 			xmlns_context: HashMap::new(),
+			inherited_xmlns: None,
+			inherited_xmlns_context: HashMap::new(),
 		}
 	}
 }
@@ -1612,6 +3670,14 @@ impl Hash for Element {
 	fn hash<H: Hasher>(&self, state: &mut H) {
 		self.name.hash(state);
 		self.xmlns.hash(state);
+		self.xmlns_prefix.hash(state);
+		let mut attrs: Vec<(&String, &String)> = self.attributes.iter().collect();
+		attrs.sort();
+		attrs.hash(state);
+		self.child_nodes.len().hash(state);
+		for c in &self.child_nodes {
+			hash_node(&**c, state);
+		}
 	}
 }
 
@@ -1628,6 +3694,94 @@ impl std::fmt::Debug for Element {
 	}
 }
 
+/**
+A SAX-like XML parsing event, borrowing its content from the DOM it was generated from (see [Document::events()](Document::events())).
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum XmlEvent<'a> {
+	/// The XML declaration (eg `<?xml version="1.0" encoding="UTF-8"?>`)
+	Declaration(&'a Declaration),
+	/// A document type declaration
+	Dtd(&'a DTD),
+	/// The start of an element
+	StartElement {
+		/// element name (without namespace prefix)
+		name: &'a str,
+		/// namespace prefix, if any
+		prefix: Option<&'a str>,
+		/// namespace of the element, if any
+		namespace: Option<&'a str>,
+		/// attributes of the element (including xmlns declarations), in arbitrary order (see
+		/// [Element::sorted_attributes()] for a deterministic order)
+		attributes: &'a HashMap<String, String>
+	},
+	/// The end of an element
+	EndElement {
+		/// element name (without namespace prefix)
+		name: &'a str,
+		/// namespace prefix, if any
+		prefix: Option<&'a str>
+	},
+	/// Text content (unescaped)
+	Text(&'a str),
+	/// The content of a comment
+	Comment(&'a str),
+	/// The content of a CDATA section
+	CData(&'a str),
+}
+
+/** Iterator of the XML events for an element and its descendants, see [Element::events()](Element::events()) */
+pub struct ElementEvents<'a> {
+	/// the element to start with (None after the first event)
+	start: Option<&'a Element>,
+	/// stack of open elements and the index of the next child to visit
+	stack: Vec<(&'a Element, usize)>
+}
+
+impl<'a> ElementEvents<'a> {
+	/// start event for the given element
+	fn start_event(e: &'a Element) -> XmlEvent<'a> {
+		XmlEvent::StartElement{
+			name: e.name.as_str(),
+			prefix: e.xmlns_prefix.as_deref(),
+			namespace: e.xmlns.as_deref(),
+			attributes: &e.attributes
+		}
+	}
+}
+
+impl<'a> Iterator for ElementEvents<'a> {
+	type Item = XmlEvent<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(e) = self.start.take() {
+			self.stack.push((e, 0));
+			return Some(Self::start_event(e));
+		}
+		loop {
+			let (e, index) = self.stack.last_mut()?;
+			let e: &'a Element = e;
+			if *index >= e.child_nodes.len() {
+				self.stack.pop();
+				return Some(XmlEvent::EndElement{name: e.name.as_str(), prefix: e.xmlns_prefix.as_deref()});
+			}
+			let node = &e.child_nodes[*index];
+			*index += 1;
+			if let Ok(child) = node.as_element() {
+				self.stack.push((child, 0));
+				return Some(Self::start_event(child));
+			} else if let Ok(t) = node.as_text() {
+				return Some(XmlEvent::Text(t.content.as_str()));
+			} else if let Ok(c) = node.as_comment() {
+				return Some(XmlEvent::Comment(c.comment.as_str()));
+			} else if let Ok(c) = node.as_cdata() {
+				return Some(XmlEvent::CData(c.cdata.as_str()));
+			}
+			// other kinds of node do not produce events
+		}
+	}
+}
+
 /// Represents a string of text in the XML DOM
 #[derive(Clone)]
 pub struct Text {
@@ -1636,7 +3790,7 @@ pub struct Text {
 }
 
 /// singleton regex matcher
-const WSP_MATCHER_SINGLETON: OnceCell<Regex> = OnceCell::new();
+static WSP_MATCHER_SINGLETON: OnceLock<Regex> = OnceLock::new();
 
 impl Text {
 	/** Construct a new Text node from the provided string-like object */
@@ -1653,9 +3807,14 @@ impl Text {
 		Text{content}
 	}
 
+	/** Converts this Text node into a CDATA node with the same content. Returns an error if the content contains `]]>`, which cannot appear inside a CDATA section */
+	pub fn into_cdata(self) -> Result<CData, InvalidContent> {
+		CData::new(self.content)
+	}
+
 	/// checks if this Text node contains only whitespace
 	fn is_whitespace(&self) -> bool {
-		let singleton = WSP_MATCHER_SINGLETON;
+		let singleton = &WSP_MATCHER_SINGLETON;
 		let wsp_matcher = singleton.get_or_init(|| Regex::new(r#"^\s+$"#).unwrap());
 		wsp_matcher.is_match(self.content.as_str())
 	}
@@ -1695,6 +3854,8 @@ impl Node for Text {
 		false
 	}
 
+	fn node_type(&self) -> DomNodeType {DomNodeType::TextNode}
+
 	fn as_element(&self) -> Result<&Element, TypeCastError> {Err(TypeCastError::new("Cannot cast Text as Element"))}
 
 	fn as_comment(&self) -> Result<&Comment, TypeCastError> {Err(TypeCastError::new("Cannot cast Text as Comment"))}
@@ -1777,6 +3938,24 @@ impl Comment {
 		}
 	}
 
+	/**
+	Constructs a new Comment node from the given text, replacing any `-->` (which would end the comment early) with `-- >` instead of returning an error.
+	# Example
+	```rust
+	use kiss_xml::dom::*;
+	let mut e = Element::new_from_name("a").unwrap();
+	e.append(Comment::from_str_lossy("arrow: -->"));
+	assert_eq!(e.to_string(), "<a><!--arrow: -- >--></a>");
+	```
+	*/
+	pub fn from_str_lossy(comment: impl Into<String>) -> Self {
+		let mut content: String = comment.into();
+		while content.contains("-->") {
+			content = content.replace("-->", "-- >");
+		}
+		Self { comment: content }
+	}
+
 	/// Gets the content of this comment
 	pub fn get_content(&self) -> &str {
 		self.comment.as_str()
@@ -1815,6 +3994,8 @@ impl Node for Comment {
 		false
 	}
 
+	fn node_type(&self) -> DomNodeType {DomNodeType::CommentNode}
+
 	fn as_element(&self) -> Result<&Element, TypeCastError> {Err(TypeCastError::new("Cannot cast Comment as Element"))}
 
 	fn as_comment(&self) -> Result<&Comment, TypeCastError> {Ok(&self)}
@@ -1918,6 +4099,11 @@ impl CData {
 			Ok(())
 		}
 	}
+
+	/// Converts this CDATA into a Text node with the same content
+	pub fn into_text(self) -> Text {
+		Text::new(self.cdata)
+	}
 }
 
 impl Node for CData {
@@ -1942,6 +4128,8 @@ impl Node for CData {
 		true
 	}
 
+	fn node_type(&self) -> DomNodeType {DomNodeType::CDataNode}
+
 	fn as_element(&self) -> Result<&Element, TypeCastError> {Err(TypeCastError::new("Cannot cast CData as Element"))}
 
 	fn as_comment(&self) -> Result<&Comment, TypeCastError> {Err(TypeCastError::new("Cannot cast CData as Comment"))}
@@ -2063,10 +4251,21 @@ impl std::fmt::Debug for Declaration {
 
 /**
 An XML document type declaration (DTD) defines custom behavior for XML documents, but `kiss_xml` does not support DTDs beyond copying them verbatum.
+
+A DTD parsed from XML keeps the exact source text of the `<!DOCTYPE ...>` block, which is written back out unchanged when the document is serialized. The root element name, external ID, and internal subset are also available as separate fields; modifying any of them replaces the original source text with a DTD generated from those fields.
+
+DTDs are compared (and hashed and ordered) by their root element name, external ID, and internal subset only, so the formatting of the original source text does not matter.
 */
-#[derive(Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+#[derive(Clone)]
 pub struct DTD {
-	dtd_str: String
+	/// exact source text, if this DTD has not been modified since it was parsed
+	raw: Option<String>,
+	/// the root element name
+	name: String,
+	/// external ID (eg `SYSTEM "note.dtd"`), if any
+	external_id: Option<String>,
+	/// content of the internal subset (between `[` and `]`), if any
+	internal_subset: Option<String>
 }
 
 impl DTD {
@@ -2074,16 +4273,118 @@ impl DTD {
 	pub fn from_string(text: impl Into<String>) -> Result<DTD, KissXmlError> {
 		// parsing DTDs is beyond the scope of the kiss_xml crate
 		let buffer: String = text.into().trim().to_string();
-		if buffer.starts_with("<!DOCTYPE") && buffer.ends_with(">"){
-			Ok(Self{dtd_str: buffer.strip_prefix("<!DOCTYPE").unwrap().strip_suffix(">").unwrap().to_string()})
-		} else {
-			Err(ParsingError::new("Invalid DTD syntax").into())
+		if !(buffer.starts_with("<!DOCTYPE") && buffer.ends_with(">")) {
+			return Err(ParsingError::new("Invalid DTD syntax").into());
+		}
+		let body = buffer.strip_prefix("<!DOCTYPE").unwrap().strip_suffix(">").unwrap();
+		// the internal subset is enclosed in [...]
+		let (header, internal_subset) = match crate::quote_aware_find(body, "[", 0) {
+			None => (body, None),
+			Some(i) => match body.rfind(']') {
+				Some(j) if j > i => (&body[..i], Some(body[i+1..j].to_string())),
+				_ => return Err(ParsingError::new("Invalid DTD syntax: '[' without matching ']'").into())
+			}
+		};
+		let header = header.trim();
+		let (name, external_id) = match header.split_once(char::is_whitespace) {
+			None => (header, None),
+			Some((name, ext)) => (name, Some(ext.trim().to_string()))
+		};
+		if name.is_empty() {
+			return Err(ParsingError::new("Invalid DTD syntax: missing root element name").into());
 		}
+		Ok(Self{
+			raw: Some(buffer.clone()),
+			name: name.to_string(),
+			external_id,
+			internal_subset
+		})
+	}
+	/// Creates a new DTD with the given root element name and optional external ID (eg `SYSTEM "note.dtd"`) and internal subset (the content between `[` and `]`)
+	pub fn new(name: impl Into<String>, external_id: Option<String>, internal_subset: Option<String>) -> DTD {
+		Self{raw: None, name: name.into(), external_id, internal_subset}
+	}
+	/// Returns the root element name declared by this DTD (eg "note" for `<!DOCTYPE note [...]>`)
+	pub fn name(&self) -> &str {
+		self.name.as_str()
+	}
+	/// Returns the external ID of this DTD (eg `SYSTEM "note.dtd"`), if it has one
+	pub fn external_id(&self) -> Option<&str> {
+		self.external_id.as_deref()
+	}
+	/// Returns the content of the internal subset of this DTD (the text between `[` and `]`), if it has one
+	pub fn internal_subset(&self) -> Option<&str> {
+		self.internal_subset.as_deref()
+	}
+	/// Returns the exact source text of this DTD, or `None` if this DTD was not parsed from XML or has since been modified
+	pub fn raw(&self) -> Option<&str> {
+		self.raw.as_deref()
+	}
+	/// Sets the root element name declared by this DTD
+	pub fn set_name(&mut self, name: impl Into<String>) {
+		self.name = name.into();
+		self.raw = None;
+	}
+	/// Sets (or removes) the external ID of this DTD
+	pub fn set_external_id(&mut self, external_id: Option<String>) {
+		self.external_id = external_id;
+		self.raw = None;
+	}
+	/// Sets (or removes) the internal subset of this DTD
+	pub fn set_internal_subset(&mut self, internal_subset: Option<String>) {
+		self.internal_subset = internal_subset;
+		self.raw = None;
+	}
+	/// the fields that define the identity of a DTD (used for comparing and hashing)
+	fn key(&self) -> (&String, &Option<String>, &Option<String>) {
+		(&self.name, &self.external_id, &self.internal_subset)
+	}
+}
+
+impl PartialEq for DTD {
+	fn eq(&self, other: &Self) -> bool {
+		self.key() == other.key()
+	}
+}
+
+impl Eq for DTD {}
+
+impl PartialOrd for DTD {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for DTD {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.key().cmp(&other.key())
+	}
+}
+
+impl Hash for DTD {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.key().hash(state);
+	}
+}
+
+impl std::fmt::Debug for DTD {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self)
 	}
 }
 
 impl std::fmt::Display for DTD {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-		write!(f, "{}", self.dtd_str)
+		if let Some(raw) = &self.raw {
+			return write!(f, "{}", raw);
+		}
+		write!(f, "<!DOCTYPE {}", self.name)?;
+		if let Some(ext) = &self.external_id {
+			write!(f, " {}", ext)?;
+		}
+		if let Some(subset) = &self.internal_subset {
+			write!(f, " [{}]", subset)?;
+		}
+		write!(f, ">")
 	}
 }
@@ -25,6 +25,10 @@ pub enum KissXmlError {
 	InvalidContent(InvalidContent),
 	/// Error indicating an attempt to do something that is valid XML, but not supported by KISS-XML
 	NotSupportedError(NotSupportedError),
+	/// Error indicating that the input exceeded a configured resource limit (eg maximum input size)
+	LimitExceeded(LimitExceeded),
+	/// Error indicating that a document's root element was not the expected one
+	UnexpectedRoot(UnexpectedRoot),
 	/// An I/O error when writing or reading a file
 	IOError(std::io::Error),
 }
@@ -44,6 +48,8 @@ impl Display for KissXmlError {
 			KissXmlError::InvalidElementName(e) => write!(f, "{}", e),
 			KissXmlError::InvalidContent(e) => write!(f, "{}", e),
 			KissXmlError::NotSupportedError(e) => write!(f, "{}", e),
+			KissXmlError::LimitExceeded(e) => write!(f, "{}", e),
+			KissXmlError::UnexpectedRoot(e) => write!(f, "{}", e),
 			KissXmlError::IOError(e) => write!(f, "{}", e),
 		}
 	}
@@ -51,12 +57,116 @@ impl Display for KissXmlError {
 
 impl std::error::Error for KissXmlError{}
 
+impl KissXmlError {
+	/** Returns a structured, machine-readable summary of this error (see [ErrorReport]). The
+	`kind` and field names of the report are stable, so tools can rely on them instead of
+	scraping the text of the error message.
+
+	# Example
+	```rust
+	fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+		let err = kiss_xml::parse_str("<root>\n  <a></b>\n</root>").unwrap_err();
+		let report = err.report();
+		assert_eq!(report.kind, "ParsingError");
+		assert_eq!(report.line, Some(2));
+		assert_eq!(report.column, Some(6));
+		assert_eq!(report.path.as_deref(), Some("/root/a"));
+		Ok(())
+	}
+	```
+	*/
+	pub fn report(&self) -> ErrorReport {
+		let (kind, message) = match self {
+			KissXmlError::ParsingError(e) => ("ParsingError", e.msg.clone()),
+			KissXmlError::TypeCastError(e) => ("TypeCastError", e.msg.clone()),
+			KissXmlError::DoesNotExistError(e) => ("DoesNotExistError", e.msg.clone()),
+			KissXmlError::IndexOutOfBounds(e) => ("IndexOutOfBounds", e.to_string()),
+			KissXmlError::InvalidAttributeName(e) => ("InvalidAttributeName", e.msg.clone()),
+			KissXmlError::InvalidElementName(e) => ("InvalidElementName", e.msg.clone()),
+			KissXmlError::InvalidContent(e) => ("InvalidContent", e.msg.clone()),
+			KissXmlError::NotSupportedError(e) => ("NotSupportedError", e.msg.clone()),
+			KissXmlError::LimitExceeded(e) => ("LimitExceeded", e.msg.clone()),
+			KissXmlError::UnexpectedRoot(e) => ("UnexpectedRoot", e.msg.clone()),
+			KissXmlError::IOError(e) => ("IOError", e.to_string()),
+		};
+		let mut report = ErrorReport{
+			kind: kind.to_string(), message, line: None, column: None, offset: None, path: None
+		};
+		let open_elements: &[String] = match self {
+			KissXmlError::ParsingError(e) => {
+				report.line = e.line;
+				report.column = e.column;
+				report.offset = e.position;
+				&e.open_elements
+			},
+			KissXmlError::LimitExceeded(e) => {
+				report.offset = e.position;
+				&e.open_elements
+			},
+			_ => &[]
+		};
+		if !open_elements.is_empty() {
+			report.path = Some(format!("/{}", open_elements.join("/")));
+		}
+		report
+	}
+
+	/** Returns the [report()](KissXmlError::report()) of this error as a JSON object (requires
+	the `json` feature), eg
+	`{"kind":"ParsingError","message":"...","line":2,"column":8,"offset":15,"path":"/root/a"}`.
+	Fields that are not known for this error are `null`.
+	*/
+	#[cfg(feature = "json")]
+	pub fn to_json(&self) -> String {
+		serde_json::to_string(&self.report()).expect("error report is always serializable")
+	}
+}
+
+/// A structured, machine-readable summary of a [KissXmlError], for tools (eg CI annotations)
+/// that need the error position without parsing the error message. With the `json` feature,
+/// this struct implements serde's `Serialize` and `Deserialize`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+pub struct ErrorReport {
+	/// The kind of error, which is the name of the [KissXmlError] variant (eg `"ParsingError"`)
+	pub kind: String,
+	/// The error message (without the kind prefix)
+	pub message: String,
+	/// Line number (starting at 1) of the error position, if known
+	pub line: Option<usize>,
+	/// Column number (starting at 1, counted in characters) of the error position, if known
+	pub column: Option<usize>,
+	/// Byte offset in the XML input of the error position, if known
+	pub offset: Option<usize>,
+	/// Path of the innermost open element where the error was found, as the tag names from the
+	/// root element separated by '/' (eg `"/root/a"`), if known
+	pub path: Option<String>,
+}
+
+impl Display for ErrorReport {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}: {}", self.kind, self.message)
+	}
+}
+
+impl std::error::Error for ErrorReport{}
+
 
-/// Represents an error that occurs during parsing with additional information.
+/// Represents an error that occurs during parsing with additional information. The position of
+/// the error and the elements that were open at that point are available from the accessor methods.
 #[derive(Clone, Debug)]
 pub struct ParsingError {
 	/// The error message.
-	pub msg: String
+	pub msg: String,
+	/// Byte offset in the XML input where the error was found, if known
+	pub(crate) position: Option<usize>,
+	/// Line number (starting at 1) of the error position, if known
+	pub(crate) line: Option<usize>,
+	/// Column number (starting at 1, counted in characters) of the error position, if known
+	pub(crate) column: Option<usize>,
+	/// Tag names of the elements that were open when the error was found, from the root
+	/// element to the innermost element (empty if the error was found outside of the elements)
+	pub(crate) open_elements: Vec<String>,
 }
 
 impl ParsingError{
@@ -65,7 +175,31 @@ impl ParsingError{
 		let mut m = msg.into();
 		// parsing error messages may be nested, make them pretty
 		m = m.trim_start_matches("ParsingError: ").to_string();
-		Self{msg: m}
+		Self{msg: m, position: None, line: None, column: None, open_elements: Vec::new()}
+	}
+	/// Byte offset in the XML input where the error was found, if known
+	pub fn position(&self) -> Option<usize> {
+		self.position
+	}
+	/// Line number (starting at 1) of the error position, if known
+	pub fn line(&self) -> Option<usize> {
+		self.line
+	}
+	/// Column number (starting at 1, counted in characters) of the error position, if known
+	pub fn column(&self) -> Option<usize> {
+		self.column
+	}
+	/// Tag names of the elements that were open when the error was found, from the root
+	/// element to the innermost element (empty if the error was found outside of the elements)
+	pub fn open_elements(&self) -> &[String] {
+		&self.open_elements
+	}
+	/// Sets the position (byte offset, line and column) where the error was found
+	pub(crate) fn at(mut self, position: usize, line: usize, column: usize) -> Self {
+		self.position = Some(position);
+		self.line = Some(line);
+		self.column = Some(column);
+		self
 	}
 }
 
@@ -278,3 +412,86 @@ impl Display for NotSupportedError {
 impl std::error::Error for NotSupportedError{}
 
 
+
+
+/// Error indicating that the input exceeded a configured resource limit (eg maximum input size)
+#[derive(Clone, Debug)]
+pub struct LimitExceeded {
+	/// The error message.
+	pub msg: String,
+	/// Byte offset in the XML input where the limit was exceeded, if known
+	pub position: Option<usize>,
+	/// Tag names of the elements that were open when the limit was exceeded, from the root
+	/// element to the innermost element (empty if the limit was exceeded outside of the elements)
+	pub open_elements: Vec<String>,
+	/// The document parsed up to the point where the limit was exceeded, with all open elements
+	/// closed (only if requested with the `return_partial_on_limit` parse option)
+	pub partial_document: Option<Box<crate::dom::Document>>,
+}
+
+impl LimitExceeded{
+	/// New error with a given message
+	pub fn new(msg: impl Into<String>) -> Self {
+		Self{msg: msg.into(), position: None, open_elements: Vec::new(), partial_document: None}
+	}
+}
+
+impl From<LimitExceeded> for KissXmlError {
+	fn from(e: LimitExceeded) -> Self {KissXmlError::LimitExceeded(e)}
+}
+
+impl Display for LimitExceeded {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "LimitExceeded: {}", self.msg)
+	}
+}
+
+impl std::error::Error for LimitExceeded{}
+
+/// Error indicating that a document's root element did not have the expected name or namespace
+/// (see [parse_str_expecting(...)](crate::parse_str_expecting()))
+#[derive(Clone, Debug)]
+pub struct UnexpectedRoot {
+	/// The error message.
+	pub msg: String,
+	/// The expected root element name (without prefix)
+	pub expected_name: String,
+	/// The expected root element namespace (`None` for no namespace)
+	pub expected_namespace: Option<String>,
+	/// The name (without prefix) of the root element that was found
+	pub actual_name: String,
+	/// The namespace of the root element that was found (`None` for no namespace)
+	pub actual_namespace: Option<String>,
+}
+
+impl UnexpectedRoot{
+	/// New error for the given expected and actual root element names and namespaces
+	pub fn new(
+		expected_name: impl Into<String>, expected_namespace: Option<String>,
+		actual_name: impl Into<String>, actual_namespace: Option<String>
+	) -> Self {
+		let expected_name = expected_name.into();
+		let actual_name = actual_name.into();
+		let describe = |ns: &Option<String>| match ns {
+			None => String::from("no namespace"),
+			Some(ns) => format!("namespace {ns}")
+		};
+		let msg = format!(
+			"expected root <{expected_name}> in {}, found <{actual_name}> in {}",
+			describe(&expected_namespace), describe(&actual_namespace)
+		);
+		Self{msg, expected_name, expected_namespace, actual_name, actual_namespace}
+	}
+}
+
+impl From<UnexpectedRoot> for KissXmlError {
+	fn from(e: UnexpectedRoot) -> Self {KissXmlError::UnexpectedRoot(e)}
+}
+
+impl Display for UnexpectedRoot {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "UnexpectedRoot: {}", self.msg)
+	}
+}
+
+impl std::error::Error for UnexpectedRoot{}
@@ -69,8 +69,8 @@ fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
 	for prop in properties {
 		println!(
 			"{} = {}",
-			prop.get_attr("name").ok_or(DoesNotExistError::default())?,
-			prop.get_attr("value").ok_or(DoesNotExistError::default())?
+			prop.require_attr("name")?,
+			prop.require_attr("value")?
 		);
 	}
 	// print children of the root element
@@ -85,6 +85,24 @@ fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
 }
 ```
 
+## Optional lookups: `Option` vs `Result`
+Lookups of things that might not be there come in two flavors: the `find_*(...)` and `get_*(...)` methods return an `Option`, while the `first_*(...)` and `require_*(...)` methods return a `Result` with a `DoesNotExistError` so that they can be used with the `?` operator. Use whichever suits the calling code.
+
+```rust
+fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+	use kiss_xml;
+	let dom = kiss_xml::parse_str(r#"<config><sound volume="11"/></config>"#)?;
+	// missing content is an error
+	let volume = dom.root_element().first_element_by_name("sound")?.require_attr("volume")?;
+	println!("volume = {}", volume);
+	// missing content is expected
+	if let Some(mixer) = dom.root_element().find_element_by_name("sound").and_then(|e| e.get_attr("mixer")) {
+		println!("mixer = {}", mixer);
+	}
+	Ok(())
+}
+```
+
 ## Create and edit DOM from scratch
 To modify the DOM, use the `.*_mut(...)` methods to get mutable references to the elements. You and insert, append, and remove elements (and other kinds of nodes) from the DOM.
 
@@ -107,7 +125,7 @@ fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
 	// print first element content
 	println!("First politician: {}", doc.root_element().first_element_by_name("person")?.text());
 	// write to file
-	doc.write_to_filepath("tests/politics.xml");
+	doc.write_to_filepath(std::env::temp_dir().join("politics.xml"))?;
 	Ok(())
 }
 ```
@@ -120,7 +138,6 @@ fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
 	use kiss_xml;
 	use kiss_xml::dom::*;
 	use kiss_xml::errors::*;
-	use std::collections::HashMap;
 	let mut doc = kiss_xml::parse_str(
 r#"<html>
 	<!-- this is a comment -->
@@ -138,22 +155,16 @@ r#"<html>
 	println!("Comment: {}", first_comment.text());
 	doc.root_element_mut().remove_all(&|n| n.is_comment());
 	// replace content of <body> with some HTML
-	doc.root_element_mut().first_element_by_name_mut("body")?.remove_all(&|_| true);
-	doc.root_element_mut().first_element_by_name_mut("body")?.append_all(
-		vec![
-			Element::new_with_text("h1", "Chapter 1")?.boxed(),
-			Comment::new("Note: there is only one chapter")?.boxed(),
-			Element::new_with_children("p", vec![
-				Text::new("Once upon a time, there was a little ").boxed(),
-				Element::new_with_attributes_and_text::<&str,&str>(
-					"a",
-					HashMap::from([("href","https://en.wikipedia.org/wiki/Gnome")]),
-					"gnome"
-				)?.boxed(),
-				Text::new(" who lived in a walnut tree...").boxed()
-			])?.boxed()
-		]
-	);
+	let body = doc.root_element_mut().first_element_by_name_mut("body")?;
+	body.remove_all(&|_| true);
+	body.append_element("h1")?.append_text("Chapter 1");
+	body.append_comment("Note: there is only one chapter")?;
+	let p = body.append_element("p")?;
+	p.append_text("Once upon a time, there was a little ");
+	let a = p.append_element("a")?;
+	a.set_attr("href", "https://en.wikipedia.org/wiki/Gnome")?;
+	a.append_text("gnome");
+	p.append_text(" who lived in a walnut tree...");
 	// print the results
 	println!("{}", doc.to_string());
 	// prints:
@@ -185,6 +196,7 @@ fn ws_example_1() -> Result<(), Box<dyn std::error::Error>> {
 	println!("{tree}");
 	Ok(())
 }
+# ws_example_1().unwrap();
 ```
 
 The above code will print the following:
@@ -208,6 +220,7 @@ fn ws_example_2() -> Result<(), Box<dyn std::error::Error>> {
 	println!("{tree}");
 	Ok(())
 }
+# ws_example_2().unwrap();
 ```
 
 The above code will print the following:
@@ -233,18 +246,255 @@ as-is or with modification, without any limitations.
 
  */
 
-use std::cell::{OnceCell};
+/// Compiles and runs the examples in the README as doc tests
+#[cfg(doctest)]
+#[doc = include_str!("../README.md")]
+struct ReadmeDoctests;
+
+use std::borrow::Cow;
+use std::sync::OnceLock;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fs;
 use std::io::Read;
+use std::ops::Range;
 use std::path::Path;
 use regex::Regex;
 use crate::errors::KissXmlError;
 
 pub mod errors;
 pub mod dom;
+pub mod tagutil;
+pub mod lint;
 mod parsing;
+mod paths;
+
+/**
+Options for controlling the behavior of the XML parser. Use `ParseOptions::default()` to get the
+same behavior as [parse_str(...)](parse_str()).
+# Example
+```rust
+fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+	use kiss_xml;
+	let options = kiss_xml::ParseOptions{source_spans: true, ..Default::default()};
+	let doc = kiss_xml::parse_str_with_options("<root><a/></root>", &options)?;
+	assert!(doc.source_spans().is_some());
+	Ok(())
+}
+```
+*/
+#[derive(Clone, Debug)]
+pub struct ParseOptions {
+	/// If true, the parser records the byte spans of every element in the source XML, which
+	/// can then be retrieved with [Document::source_spans()](dom::Document::source_spans())
+	/// (default: false, to avoid the overhead)
+	pub source_spans: bool,
+	/// Maximum size of the XML input in bytes, or `None` for no limit (the default). When reading
+	/// from a file or stream, the limit is enforced while reading, so that an oversized input
+	/// results in a [LimitExceeded](errors::LimitExceeded) error instead of exhausting memory.
+	pub max_input_bytes: Option<usize>,
+	/// If true, carriage returns in text, CDATA, comments, attribute values, and DTDs are kept as-is.
+	/// By default (false), the parser follows the XML specification and normalizes `\r\n` and
+	/// lone `\r` line endings to `\n`, so that the same document saved with Windows and Unix line
+	/// endings parses to the same DOM.
+	pub preserve_carriage_returns: bool,
+	/// Maximum length in bytes of a `<!DOCTYPE ...>` (or other `<!...>` markup declaration) that
+	/// the parser will scan looking for its closing `>` (default: 1 MiB). This prevents a
+	/// DOCTYPE with unbalanced brackets from swallowing the rest of the document.
+	pub max_dtd_bytes: usize,
+	/// Maximum nesting depth of `<` ... `>` brackets inside a `<!DOCTYPE ...>` (or other `<!...>`
+	/// markup declaration) before the parser gives up looking for its closing `>` (default: 64)
+	pub max_dtd_depth: usize,
+	/// Maximum nesting depth of elements (the root element is at depth 1), or `None` for no limit
+	/// (the default). A deeper element results in a [LimitExceeded](errors::LimitExceeded) error
+	/// that reports where the limit was exceeded and which elements were open at that point.
+	pub max_depth: Option<usize>,
+	/// If true, when a resource limit (eg `max_depth`) is exceeded while parsing the elements, the
+	/// parser closes all open elements and includes the document parsed so far in the
+	/// [LimitExceeded](errors::LimitExceeded) error (default: false)
+	pub return_partial_on_limit: bool,
+}
+
+impl Default for ParseOptions {
+	fn default() -> Self {
+		ParseOptions{
+			source_spans: false,
+			max_input_bytes: None,
+			preserve_carriage_returns: false,
+			max_dtd_bytes: 1024 * 1024,
+			max_dtd_depth: 64,
+			max_depth: None,
+			return_partial_on_limit: false,
+		}
+	}
+}
+
+/// Chainable setters for building options compactly, eg
+/// `ParseOptions::new().source_spans(true).max_depth(Some(64))`
+impl ParseOptions {
+	/** Same as `ParseOptions::default()` */
+	pub fn new() -> Self {
+		Self::default()
+	}
+	/** Sets the `source_spans` option */
+	pub fn source_spans(mut self, source_spans: bool) -> Self {
+		self.source_spans = source_spans;
+		self
+	}
+	/** Sets the `max_input_bytes` option */
+	pub fn max_input_bytes(mut self, max_input_bytes: Option<usize>) -> Self {
+		self.max_input_bytes = max_input_bytes;
+		self
+	}
+	/** Sets the `preserve_carriage_returns` option */
+	pub fn preserve_carriage_returns(mut self, preserve_carriage_returns: bool) -> Self {
+		self.preserve_carriage_returns = preserve_carriage_returns;
+		self
+	}
+	/** Sets the `max_dtd_bytes` option */
+	pub fn max_dtd_bytes(mut self, max_dtd_bytes: usize) -> Self {
+		self.max_dtd_bytes = max_dtd_bytes;
+		self
+	}
+	/** Sets the `max_dtd_depth` option */
+	pub fn max_dtd_depth(mut self, max_dtd_depth: usize) -> Self {
+		self.max_dtd_depth = max_dtd_depth;
+		self
+	}
+	/** Sets the `max_depth` option */
+	pub fn max_depth(mut self, max_depth: Option<usize>) -> Self {
+		self.max_depth = max_depth;
+		self
+	}
+	/** Sets the `return_partial_on_limit` option */
+	pub fn return_partial_on_limit(mut self, return_partial_on_limit: bool) -> Self {
+		self.return_partial_on_limit = return_partial_on_limit;
+		self
+	}
+}
+
+/**
+Options for controlling how a DOM is written as XML text. Use `OutputOptions::default()` to get
+the same output as [Document::to_string()](dom::Document::to_string()).
+# Example
+```rust
+fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+	use kiss_xml;
+	let doc = kiss_xml::parse_str(r#"<root><a x="1"/></root>"#)?;
+	let options = kiss_xml::OutputOptions{space_before_self_close: true, ..Default::default()};
+	assert_eq!(doc.root_element().to_string_with_options(&options), "<root>\n  <a x=\"1\" />\n</root>");
+	Ok(())
+}
+```
+*/
+#[derive(Clone, Debug)]
+pub struct OutputOptions {
+	/// Prefix string to use for each level of indentation (default: two spaces). The indent must
+	/// be either a single tab character or any number of spaces (otherwise a warning will be
+	/// printed and the default indent used instead)
+	pub indent: String,
+	/// If true, empty elements are written with a space before the `/>` (eg `<a x="1" />`). By
+	/// default (false), there is no space (eg `<a x="1"/>`), regardless of how the element was
+	/// written in the parsed source.
+	pub space_before_self_close: bool,
+	/// If true, `xmlns` and `xmlns:prefix` attributes that re-declare exactly the same binding
+	/// that is already in scope from an ancestor element being written are omitted from the
+	/// output (default: false). This does not change the namespace of any element when the
+	/// output is parsed again.
+	pub omit_redundant_namespace_declarations: bool,
+	/// If true, tabs, newlines, and carriage returns in attribute values are written as character
+	/// references (eg `&#10;`) instead of as-is (default: false). Other XML parsers replace raw
+	/// whitespace in attribute values with spaces, so this keeps such values intact for any parser.
+	pub escape_attribute_whitespace: bool,
+	/// Whether documents are written with a newline at the end. By default (`None`), there is a
+	/// trailing newline unless the document is a fragment (see
+	/// [Document::is_fragment()](dom::Document::is_fragment())), while `Some(true)` or
+	/// `Some(false)` applies to every document. This option does not apply when writing a single
+	/// element.
+	pub trailing_newline: Option<bool>,
+}
+
+impl Default for OutputOptions {
+	fn default() -> Self {
+		OutputOptions{
+			indent: "  ".to_string(),
+			space_before_self_close: false,
+			omit_redundant_namespace_declarations: false,
+			escape_attribute_whitespace: false,
+			trailing_newline: None,
+		}
+	}
+}
+
+/// Chainable setters for building options compactly, eg
+/// `OutputOptions::new().indent("\t").space_before_self_close(true)`
+impl OutputOptions {
+	/** Same as `OutputOptions::default()` */
+	pub fn new() -> Self {
+		Self::default()
+	}
+	/** Sets the `indent` option */
+	pub fn indent(mut self, indent: impl Into<String>) -> Self {
+		self.indent = indent.into();
+		self
+	}
+	/** Sets the `space_before_self_close` option */
+	pub fn space_before_self_close(mut self, space_before_self_close: bool) -> Self {
+		self.space_before_self_close = space_before_self_close;
+		self
+	}
+	/** Sets the `omit_redundant_namespace_declarations` option */
+	pub fn omit_redundant_namespace_declarations(mut self, omit_redundant_namespace_declarations: bool) -> Self {
+		self.omit_redundant_namespace_declarations = omit_redundant_namespace_declarations;
+		self
+	}
+	/** Sets the `escape_attribute_whitespace` option */
+	pub fn escape_attribute_whitespace(mut self, escape_attribute_whitespace: bool) -> Self {
+		self.escape_attribute_whitespace = escape_attribute_whitespace;
+		self
+	}
+	/** Sets the `trailing_newline` option */
+	pub fn trailing_newline(mut self, trailing_newline: Option<bool>) -> Self {
+		self.trailing_newline = trailing_newline;
+		self
+	}
+}
+
+/**
+A filter for writing a redacted projection of a DOM as XML text with
+[Document::to_string_filtered(...)](dom::Document::to_string_filtered()), without modifying the DOM.
+Denied elements are skipped along with all of their content, and denied attributes are omitted.
+The root element is never skipped (only its attributes are filtered), and namespace declarations
+(`xmlns` and `xmlns:prefix` attributes) are never omitted, so that the output is always well-formed.
+# Example
+```rust
+fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+	use kiss_xml;
+	let doc = kiss_xml::parse_str(r#"<user id="7" password="hunter2"><name>Bob</name><token>abc</token></user>"#)?;
+	let filter = kiss_xml::OutputFilter{
+		deny_elements: vec!["token".to_string()],
+		deny_attributes: vec!["password".to_string()],
+		..Default::default()
+	};
+	assert_eq!(doc.to_string_filtered(&filter), "<user id=\"7\">\n  <name>Bob</name>\n</user>");
+	Ok(())
+}
+```
+*/
+#[derive(Clone, Debug, Default)]
+pub struct OutputFilter {
+	/// Names of elements to skip (matches either the element name without its namespace prefix, eg
+	/// `property`, or the prefixed tag name, eg `svg:g`)
+	pub deny_elements: Vec<String>,
+	/// Paths of elements to skip, using the same syntax as
+	/// [Document::element_at_path(...)](dom::Document::element_at_path()). Invalid paths (see
+	/// [validate_path(...)](validate_path())) do not match any element.
+	pub deny_paths: Vec<String>,
+	/// Names of attributes to omit (including the namespace prefix, if any)
+	pub deny_attributes: Vec<String>,
+	/// Options for formatting the filtered output
+	pub output_options: OutputOptions,
+}
 
 
 /// Escapes a subset of XML reserved characters (&, <, and >) in a text string
@@ -275,7 +525,8 @@ pub fn escape(text: impl Into<String>) -> String {
 
 /// Reverses any escaped characters (&, <, >, ', and ") in XML-compatible text
 /// to regenerate the original text, eg replacing "&amp;amp;" with "&" and "&amp;lt;"
-/// with "<"
+/// with "<". Character references are also replaced, eg "&amp;#10;" (decimal) and "&amp;#xA;"
+/// (hexadecimal) with a newline, while an invalid reference such as "&amp;#1F;" is left as-is
 pub fn unescape(text: impl Into<String>) -> String {
 	let mut buffer: String = text.into();
 	let mut last_i: usize = 0;
@@ -309,8 +560,13 @@ pub fn unescape(text: impl Into<String>) -> String {
 						if slice == "&quot" {
 							string_insert(&mut buffer, (start, end), "\"");
 						}
-						if slice.starts_with("&#") {
-							match u32::from_str_radix(&slice[2..], 16) {
+						if let Some(digits) = slice.strip_prefix("&#") {
+							// &#x...; is hexadecimal and &#...; is always decimal
+							let codepoint = match digits.strip_prefix('x') {
+								Some(hex) => u32::from_str_radix(hex, 16),
+								None => digits.parse::<u32>()
+							};
+							match codepoint {
 								Ok(codepoint) => {
 									match char::from_u32(codepoint) {
 										Some(unicode) => {
@@ -333,21 +589,99 @@ pub fn unescape(text: impl Into<String>) -> String {
 	buffer
 }
 
-/// comparator for ordering attributes
-pub(crate) fn attribute_order(kv_tup1: &(&String, &String), kv_tup2: &(&String, &String)) -> Ordering {
-	// sort xmlns before rest
+/**
+Comparator used to order attributes when serializing an element, for use with `sort_by(...)` on a list of (key, value) pairs. The default namespace declaration `xmlns` comes first, followed by the prefixed namespace declarations (`xmlns:*`) sorted by prefix, followed by all other attributes sorted alphabetically. Attributes with the same key are ordered by value.
+# Example
+```rust
+let (xmlns, xmlns_b, xmlns_a, id) = (
+	("xmlns".to_string(), "internal://default".to_string()),
+	("xmlns:b".to_string(), "internal://b".to_string()),
+	("xmlns:a".to_string(), "internal://a".to_string()),
+	("id".to_string(), "123".to_string())
+);
+let mut attrs = vec![(&id.0, &id.1), (&xmlns_b.0, &xmlns_b.1), (&xmlns.0, &xmlns.1), (&xmlns_a.0, &xmlns_a.1)];
+attrs.sort_by(kiss_xml::attribute_order);
+let keys: Vec<&str> = attrs.iter().map(|(k, _)| k.as_str()).collect();
+assert_eq!(keys, vec!["xmlns", "xmlns:a", "xmlns:b", "id"]);
+```
+ */
+pub fn attribute_order(kv_tup1: &(&String, &String), kv_tup2: &(&String, &String)) -> Ordering {
+	// sort xmlns before xmlns:* before the rest
+	fn group(key: &str) -> u8 {
+		if key == "xmlns" {
+			0
+		} else if key.starts_with("xmlns:") {
+			1
+		} else {
+			2
+		}
+	}
 	let a = kv_tup1.0.as_str();
 	let b = kv_tup2.0.as_str();
-	if a == b {
-		return kv_tup1.1.cmp(&kv_tup2.1);
-	}
-	if a.starts_with("xmlns") && !b.starts_with("xmlns") {
-		return Ordering::Less;
-	} else if !a.starts_with("xmlns") && b.starts_with("xmlns") {
-		return Ordering::Greater;
-	} else {
-		return a.cmp(&b);
-	}
+	group(a).cmp(&group(b))
+		.then_with(|| a.cmp(b))
+		.then_with(|| kv_tup1.1.cmp(kv_tup2.1))
+}
+
+/// true if the character may start an XML name (see https://www.w3.org/TR/REC-xml/#NT-NameStartChar),
+/// excluding ':' which is only allowed as the separator between a namespace prefix and a name
+pub(crate) fn is_name_start_char(c: char) -> bool {
+	matches!(c,
+		'A'..='Z' | '_' | 'a'..='z' | '\u{C0}'..='\u{D6}' | '\u{D8}'..='\u{F6}' | '\u{F8}'..='\u{2FF}'
+		| '\u{370}'..='\u{37D}' | '\u{37F}'..='\u{1FFF}' | '\u{200C}'..='\u{200D}' | '\u{2070}'..='\u{218F}'
+		| '\u{2C00}'..='\u{2FEF}' | '\u{3001}'..='\u{D7FF}' | '\u{F900}'..='\u{FDCF}' | '\u{FDF0}'..='\u{FFFD}'
+		| '\u{10000}'..='\u{EFFFF}'
+	)
+}
+
+/// true if the character may appear in an XML name after the first character (see
+/// https://www.w3.org/TR/REC-xml/#NT-NameChar), excluding ':'
+pub(crate) fn is_name_char(c: char) -> bool {
+	is_name_start_char(c)
+		|| matches!(c, '-' | '.' | '0'..='9' | '\u{B7}' | '\u{300}'..='\u{36F}' | '\u{203F}'..='\u{2040}')
+}
+
+/// true if the string is a valid XML name without a namespace prefix (ie an NCName), which
+/// excludes '/', '[', ']', '@', whitespace, and the other characters used by the path syntax
+pub(crate) fn is_ncname(name: &str) -> bool {
+	let mut chars = name.chars();
+	chars.next().is_some_and(is_name_start_char) && chars.all(is_name_char)
+}
+
+/**
+Checks that the given element path (eg `/root/mydata/properties` or `sound/property[2]`, as used by
+[Document::element_at_path(...)](dom::Document::element_at_path()) and the other path functions) is
+well formed and that every element name in it is valid, without needing a DOM. This is useful for
+checking user-supplied paths when loading a configuration file, rather than discovering mistakes
+when a lookup fails. The error message identifies the offending path segment and its character
+position within the path string.
+
+Element names must follow the XML name rules, which are enforced when elements are created or
+parsed. The characters that have a special meaning in paths (`/`, `[`, `]`, and `@`) can never
+appear in an element name, so any element can be addressed by a path without escaping.
+# Example
+```rust
+assert!(kiss_xml::validate_path("/root/mydata/properties").is_ok());
+assert!(kiss_xml::validate_path("sound/property[2]").is_ok());
+assert!(kiss_xml::validate_path("sound//property").is_err());
+assert!(kiss_xml::validate_path("sound/property[0]").is_err());
+```
+ */
+pub fn validate_path(path: &str) -> Result<(), errors::KissXmlError> {
+	paths::validate_path(path)
+}
+
+/**
+Checks that the given string is a valid attribute name (the same check as used by
+[Element::set_attr(...)](dom::Element::set_attr())), without needing a DOM.
+# Example
+```rust
+assert!(kiss_xml::validate_attr_name("xlink:href").is_ok());
+assert!(kiss_xml::validate_attr_name("bad name").is_err());
+```
+ */
+pub fn validate_attr_name(name: &str) -> Result<(), errors::InvalidAttributeName> {
+	dom::Element::check_attr_name(name)
 }
 
 /// replaces indices (a, b) in given string with a new string (in-place)
@@ -361,60 +695,494 @@ fn string_insert(buffer: &mut String, indices: (usize, usize), insert: &str) {
 /** Reads the file from the given filepath and parses it as an XML document
 */
 pub fn parse_filepath(path: impl AsRef<Path>) -> Result<dom::Document, errors::KissXmlError> {
+	parse_filepath_with_options(path, &ParseOptions::default())
+}
+
+/** Reads the file from the given filepath and parses it as an XML document, returning a
+`LimitExceeded` error (without reading the file) if the file is larger than `max_bytes` bytes.
+ */
+pub fn parse_filepath_limited(path: impl AsRef<Path>, max_bytes: usize) -> Result<dom::Document, errors::KissXmlError> {
+	parse_filepath_with_options(path, &ParseOptions{max_input_bytes: Some(max_bytes), ..Default::default()})
+}
+
+/** Reads the file from the given filepath and parses it as an XML document, using the given
+parser options
+*/
+pub fn parse_filepath_with_options(path: impl AsRef<Path>, options: &ParseOptions) -> Result<dom::Document, errors::KissXmlError> {
 	let path_ref = path.as_ref();
-	let content = fs::read_to_string(path_ref)?;
-	parse_str(content)
+	let file = fs::File::open(path_ref)?;
+	if let Some(limit) = options.max_input_bytes {
+		let size = file.metadata()?.len();
+		if size > limit as u64 {
+			return Err(errors::LimitExceeded::new(format!(
+				"file {} is {size} bytes, which exceeds the limit of {limit} bytes", path_ref.display()
+			)).into());
+		}
+	}
+	parse_stream_with_options(file, options)
 }
 
 /** Reads the XML content from the given stream reader and parses it as an
 XML document. Note that this function will read to EOF before returning.
  */
-pub fn parse_stream(mut reader: impl Read) -> Result<dom::Document, errors::KissXmlError> {
-	let mut buffer = String::new();
-	reader.read_to_string(&mut buffer)?;
-	parse_str(buffer)
+pub fn parse_stream(reader: impl Read) -> Result<dom::Document, errors::KissXmlError> {
+	parse_stream_with_options(reader, &ParseOptions::default())
+}
+
+/** Reads the XML content from the given stream reader and parses it as an XML document, using
+the given parser options. Note that this function will read to EOF before returning, unless
+the `max_input_bytes` option is set, in which case reading stops with a `LimitExceeded` error
+as soon as more than that many bytes have been read.
+ */
+pub fn parse_stream_with_options(reader: impl Read, options: &ParseOptions) -> Result<dom::Document, errors::KissXmlError> {
+	let mut bytes: Vec<u8> = Vec::new();
+	match options.max_input_bytes {
+		None => {
+			let mut reader = reader;
+			reader.read_to_end(&mut bytes)?;
+		},
+		Some(limit) => {
+			// read at most 1 byte more than the limit to detect oversized input
+			reader.take((limit as u64).saturating_add(1)).read_to_end(&mut bytes)?;
+			if bytes.len() > limit {
+				return Err(errors::LimitExceeded::new(format!(
+					"input stream exceeds the limit of {limit} bytes"
+				)).into());
+			}
+		}
+	}
+	let buffer = String::from_utf8(bytes)
+		.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+	parse_str_with_options(buffer, options)
+}
+
+
+/**
+Parses the XML document at the start of the given text, ignoring anything after the end tag of the
+root element (or the end of a self-closing root element), and returns the document along with the
+number of bytes of the text that it occupies. Leading whitespace, an XML declaration, and other
+prologue markup are allowed before the root element, but any other text before the root element
+is an error. This is useful when XML is followed by other content in the same buffer.
+# Example
+```rust
+fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+	let text = "<msg>hello</msg> and then some other stuff";
+	let (doc, len) = kiss_xml::parse_first_document(text)?;
+	assert_eq!(doc.root_element().name(), "msg");
+	assert_eq!(&text[len..], " and then some other stuff");
+	Ok(())
+}
+```
+ */
+pub fn parse_first_document(xml: &str) -> Result<(dom::Document, usize), errors::KissXmlError> {
+	match first_document(xml, &mut Parser::new()) {
+		None => Err(errors::ParsingError::new("no complete XML document found (root element not closed)").into()),
+		Some((result, end)) => Ok((result?, end))
+	}
+}
+
+/// parses the XML document at the start of the buffer (see `parse_first_document(...)`), returning
+/// the parse result and the end of the document, or `None` if no complete document was found
+fn first_document(buffer: &str, parser: &mut Parser) -> Option<(Result<dom::Document, errors::KissXmlError>, usize)> {
+	let end = find_document_end(buffer, parser.options())?;
+	Some((parser.parse(&buffer[..end]), end))
+}
+
+/**
+Scans arbitrary text (eg a log file) for embedded XML documents, each starting with an XML
+declaration (`<?xml ...?>`) or a root element start tag, and parses each one with
+[parse_first_document(...)](parse_first_document()). Returns the byte range of each document
+found in the text, along with the parse result. A `<` that does not start a complete document
+(eg `a < b` or an unclosed tag) is skipped, while a complete document that is not valid XML (eg
+mismatched end tags) is reported with its parsing error.
+# Example
+```rust
+fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+	let log = "12:00 received <msg id=\"1\">hello</msg>\n12:01 if x < 3 then <ack/>";
+	let docs = kiss_xml::extract_documents(log);
+	assert_eq!(docs.len(), 2);
+	assert_eq!(&log[docs[0].0.clone()], "<msg id=\"1\">hello</msg>");
+	assert_eq!(docs[1].1.as_ref().unwrap().root_element().name(), "ack");
+	Ok(())
+}
+```
+ */
+pub fn extract_documents(text: &str) -> Vec<(Range<usize>, Result<dom::Document, errors::KissXmlError>)> {
+	let mut parser = Parser::new();
+	// ends of the elements starting at each start tag that has already been scanned, so that
+	// the text after an unclosed tag is not scanned again for every `<` in it
+	let mut element_ends: HashMap<usize, Option<usize>> = HashMap::new();
+	let mut found = Vec::new();
+	let mut pos = 0;
+	while let Some(i) = text[pos..].find('<') {
+		let start = pos + i;
+		let rest = &text[start..];
+		pos = start + 1;
+		let root_start = if is_declaration(rest) {
+			match find_root_start(text, start, parser.options()) {
+				Some(root_start) => root_start,
+				None => continue
+			}
+		} else if rest[1..].starts_with(|c: char| c == '_' || c.is_alphabetic()) {
+			start
+		} else {
+			continue;
+		};
+		if !element_ends.contains_key(&root_start) {
+			scan_element_ends(text, root_start, parser.options(), &mut element_ends);
+		}
+		if element_ends[&root_start].is_none() {
+			continue;
+		}
+		if let Some((result, end)) = first_document(rest, &mut parser) {
+			found.push((start .. start + end, result));
+			pos = start + end;
+		}
+	}
+	found
+}
+
+/// finds the start tag of the root element after the XML declaration (and any other prologue markup)
+/// at `from`, returning `None` if there is text or an end tag before it
+fn find_root_start(buffer: &str, from: usize, options: &ParseOptions) -> Option<usize> {
+	let mut pos = from;
+	loop {
+		let (start, end) = next_tag(buffer, pos, options);
+		let (start, end) = (start?, end?);
+		if real_text(&buffer[pos..start]).is_some() || buffer[start..].starts_with("</") {
+			return None;
+		}
+		if !(buffer[start..].starts_with("<!") || buffer[start..].starts_with("<?")) {
+			return Some(start);
+		}
+		pos = end;
+	}
+}
+
+/// scans the tags from the start tag at `from` until its element is closed (or the tags run out),
+/// recording the end of each element started on the way, or `None` for elements that are not
+/// closed before the end of the text
+fn scan_element_ends(buffer: &str, from: usize, options: &ParseOptions, ends: &mut HashMap<usize, Option<usize>>) {
+	let mut open: Vec<usize> = Vec::new();
+	let mut pos = from;
+	while let (Some(start), Some(end)) = next_tag(buffer, pos, options) {
+		let tag = &buffer[start..end];
+		pos = end;
+		if tag.starts_with("<!") || tag.starts_with("<?") {
+			continue;
+		} else if tag.starts_with("</") {
+			match open.pop() {
+				Some(element_start) => {ends.insert(element_start, Some(end));},
+				None => break
+			}
+		} else if tag.ends_with("/>") {
+			ends.insert(start, Some(end));
+		} else {
+			open.push(start);
+		}
+		if open.is_empty() {
+			return;
+		}
+	}
+	for element_start in open {
+		ends.insert(element_start, None);
+	}
+	ends.entry(from).or_insert(None);
+}
+
+/// finds the end of the root element of the XML document at the start of the buffer, returning
+/// `None` if there is text before the root element or the root element is not closed
+fn find_document_end(buffer: &str, options: &ParseOptions) -> Option<usize> {
+	let mut pos = 0;
+	let mut depth: usize = 0;
+	loop {
+		let (start, end) = next_tag(buffer, pos, options);
+		let (start, end) = (start?, end?);
+		if depth == 0 && real_text(&buffer[pos..start]).is_some() {
+			return None;
+		}
+		let tag = &buffer[start..end];
+		pos = end;
+		if tag.starts_with("<!") || tag.starts_with("<?") {
+			continue;
+		} else if tag.starts_with("</") {
+			depth = depth.checked_sub(1)?;
+		} else if !tag.ends_with("/>") {
+			depth += 1;
+		}
+		if depth == 0 {
+			return Some(end);
+		}
+	}
+}
+
+/**
+Reads one length-prefixed XML document from the given stream, as written by
+[write_framed(...)](write_framed()). The frame is a 4-byte big-endian unsigned integer giving the
+length in bytes of the document, followed by that many bytes of UTF-8 encoded XML. Exactly one
+frame is read from the stream, so that it can be called repeatedly to read consecutive documents.
+If the stream ends before a complete frame is read, an `IOError` error result with
+`std::io::ErrorKind::UnexpectedEof` is returned.
+# Example
+```rust
+fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+	let doc = kiss_xml::parse_str("<msg>hello</msg>")?;
+	let mut buffer: Vec<u8> = Vec::new();
+	kiss_xml::write_framed(&mut buffer, &doc)?;
+	let doc2 = kiss_xml::read_framed(&mut buffer.as_slice())?;
+	assert_eq!(doc, doc2);
+	Ok(())
+}
+```
+ */
+pub fn read_framed(reader: &mut impl Read) -> Result<dom::Document, errors::KissXmlError> {
+	read_framed_with_options(reader, &ParseOptions::default())
 }
 
+/** Reads one length-prefixed XML document from the given stream (see [read_framed(...)](read_framed())),
+using the given parser options. If the `max_input_bytes` option is set, a frame longer than the limit
+results in a `LimitExceeded` error without reading the frame content.
+ */
+pub fn read_framed_with_options(reader: &mut impl Read, options: &ParseOptions) -> Result<dom::Document, errors::KissXmlError> {
+	let mut prefix = [0u8; 4];
+	reader.read_exact(&mut prefix)?;
+	let len = u32::from_be_bytes(prefix) as usize;
+	if let Some(limit) = options.max_input_bytes {
+		if len > limit {
+			return Err(errors::LimitExceeded::new(format!(
+				"framed document is {len} bytes, which exceeds the limit of {limit} bytes"
+			)).into());
+		}
+	}
+	// the length prefix is untrusted, so the buffer grows as the content arrives instead of being
+	// allocated up front
+	let mut bytes: Vec<u8> = Vec::new();
+	reader.take(len as u64).read_to_end(&mut bytes)?;
+	if bytes.len() < len {
+		return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, format!(
+			"framed document is {len} bytes, but the stream ended after {} bytes", bytes.len()
+		)).into());
+	}
+	let buffer = String::from_utf8(bytes)
+		.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+	Parser::with_options(options.clone()).parse(buffer.as_str())
+}
 
-/** Reads the XML content from the UTF-8 encoded text string and parses it as an XML document
+/** Writes the given document to the given stream as a length-prefixed frame (see
+[read_framed(...)](read_framed()) for the format), returning an `InvalidInput` error if the
+document is too large for the 4-byte length prefix.
+ */
+pub fn write_framed(writer: &mut impl std::io::Write, doc: &dom::Document) -> std::io::Result<()> {
+	let xml = doc.to_string();
+	let len = u32::try_from(xml.len()).map_err(|_| std::io::Error::new(
+		std::io::ErrorKind::InvalidInput, format!("document is too large to frame ({} bytes)", xml.len())
+	))?;
+	writer.write_all(&len.to_be_bytes())?;
+	writer.write_all(xml.as_bytes())
+}
+
+/**
+A reusable XML parser. Parsing many documents with the same `Parser` reuses its internal scratch
+buffers instead of allocating new ones for every document, which can be noticeably faster when
+parsing lots of small XML messages. The parsed documents are fully owned and are the same as the
+output of [parse_str(...)](parse_str()).
+# Example
+```rust
+fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+	use kiss_xml::dom::Node;
+	let mut parser = kiss_xml::Parser::new();
+	for msg in ["<msg id=\"1\">hello</msg>", "<msg id=\"2\">world</msg>"] {
+		let doc = parser.parse(msg)?;
+		println!("{}: {}", doc.root_element().get_attr("id").unwrap(), doc.root_element().text());
+	}
+	Ok(())
+}
+```
+*/
+#[derive(Debug, Default)]
+pub struct Parser {
+	/// parser options
+	options: ParseOptions,
+	/// element stack and node arena, cleared between documents
+	tree: parsing::ParseTree,
+	/// buffers for splitting tags into names and attributes
+	tag_scratch: TagScratch,
+	/// line index of the document being parsed, for error messages
+	lines: parsing::LineIndex,
+}
+
+impl Parser {
+	/** Creates a new parser with the default options */
+	pub fn new() -> Self {
+		Self::default()
+	}
+	/** Creates a new parser with the given options */
+	pub fn with_options(options: ParseOptions) -> Self {
+		Parser{options, ..Default::default()}
+	}
+	/** Returns the options used by this parser */
+	pub fn options(&self) -> &ParseOptions {
+		&self.options
+	}
+	/** Parses the given XML string as an XML document */
+	pub fn parse(&mut self, xml: &str) -> Result<dom::Document, errors::KissXmlError> {
+		parse_document(xml, &self.options, &mut self.tree, &mut self.tag_scratch, &mut self.lines)
+			.map_err(|e| match e {
+				// record where in the element tree the syntax error was found
+				KissXmlError::ParsingError(mut pe) if pe.position.is_some() && pe.open_elements.is_empty() => {
+					pe.open_elements = self.tree.open_element_names();
+					pe.into()
+				},
+				e => e
+			})
+	}
+}
+
+/** Reads the XML content from the UTF-8 encoded text string and parses it as an XML document.
+XML without a declaration that does not end with a newline is parsed as a fragment, which is
+written back without a trailing newline (see [Document::is_fragment()](dom::Document::is_fragment())).
  */
 pub fn parse_str(xml_string: impl Into<String>) -> Result<dom::Document, errors::KissXmlError> {
-	let buffer = xml_string.into();
-	let mut decl: Option<dom::Declaration> = None;
-	let mut dtds: Vec<dom::DTD> = Vec::new();
+	parse_str_with_options(xml_string, &ParseOptions::default())
+}
+
+/**
+Reads the XML content from the UTF-8 encoded text string and parses it as an XML document, then
+checks that the root element has the given name (without prefix) and namespace (`None` for no
+namespace), returning an `UnexpectedRoot` error that describes the root element that was found if
+it does not.
+# Example
+```rust
+fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+	use kiss_xml;
+	use kiss_xml::errors::KissXmlError;
+	let doc = kiss_xml::parse_str_expecting(r#"<config xmlns="urn:app"/>"#, "config", Some("urn:app"))?;
+	assert_eq!(doc.root_element().name(), "config");
+	match kiss_xml::parse_str_expecting("<settings/>", "config", Some("urn:app")) {
+		Err(KissXmlError::UnexpectedRoot(e)) => assert_eq!(e.actual_name, "settings"),
+		_ => panic!("expected an UnexpectedRoot error")
+	}
+	Ok(())
+}
+```
+*/
+pub fn parse_str_expecting(xml_string: impl Into<String>, root_name: &str, root_namespace: Option<&str>) -> Result<dom::Document, errors::KissXmlError> {
+	check_root(parse_str(xml_string)?, root_name, root_namespace)
+}
+
+/** Same as [parse_str_expecting(...)](parse_str_expecting()), but reads the XML from the given filepath
+*/
+pub fn parse_filepath_expecting(path: impl AsRef<Path>, root_name: &str, root_namespace: Option<&str>) -> Result<dom::Document, errors::KissXmlError> {
+	check_root(parse_filepath(path)?, root_name, root_namespace)
+}
+
+/** Same as [parse_str_expecting(...)](parse_str_expecting()), but reads the XML from the given stream
+reader (to EOF)
+*/
+pub fn parse_stream_expecting(reader: impl Read, root_name: &str, root_namespace: Option<&str>) -> Result<dom::Document, errors::KissXmlError> {
+	check_root(parse_stream(reader)?, root_name, root_namespace)
+}
+
+/// returns the document if its root element has the given name and namespace, or an
+/// UnexpectedRoot error otherwise
+fn check_root(doc: dom::Document, root_name: &str, root_namespace: Option<&str>) -> Result<dom::Document, errors::KissXmlError> {
+	let root = doc.root_element();
+	let actual_namespace = root.namespace();
+	if root.name() == root_name && actual_namespace.as_deref() == root_namespace {
+		return Ok(doc);
+	}
+	Err(errors::UnexpectedRoot::new(
+		root_name, root_namespace.map(String::from), root.name(), actual_namespace
+	).into())
+}
+
+/** Same as [parse_str(...)](parse_str()), but returns a structured [ErrorReport](errors::ErrorReport)
+instead of a [KissXmlError](errors::KissXmlError) if the XML cannot be parsed. This is convenient
+for tools (eg CI checks) that report the error position in a machine-readable format.
+
+# Example
+```rust
+fn main() -> Result<(), kiss_xml::errors::ErrorReport> {
+	let doc = kiss_xml::parse_str_report("<config/>")?;
+	assert_eq!(doc.root_element().name(), "config");
+	let report = kiss_xml::parse_str_report("<config>\n\t<sound volume=80/>\n</config>").unwrap_err();
+	assert_eq!(report.kind, "ParsingError");
+	assert_eq!((report.line, report.column), (Some(2), Some(2)));
+	assert_eq!(report.path.as_deref(), Some("/config"));
+	Ok(())
+}
+```
+ */
+pub fn parse_str_report(xml_string: impl Into<String>) -> Result<dom::Document, errors::ErrorReport> {
+	parse_str(xml_string).map_err(|e| e.report())
+}
+
+/** Reads the XML content from the UTF-8 encoded text string and parses it as an XML document,
+using the given parser options
+ */
+pub fn parse_str_with_options(xml_string: impl Into<String>, options: &ParseOptions) -> Result<dom::Document, errors::KissXmlError> {
+	let buffer: String = xml_string.into();
+	Parser::with_options(options.clone()).parse(buffer.as_str())
+}
+
+/// parses an XML document, using (and first clearing) the given parse tree, scratch buffers, and line index
+fn parse_document(buffer: &str, options: &ParseOptions, parse_stack: &mut parsing::ParseTree, tag_scratch: &mut TagScratch, lines: &mut parsing::LineIndex) -> Result<dom::Document, errors::KissXmlError> {
+	lines.clear();
+	let lines: &parsing::LineIndex = lines;
+	// clear the stack first, so that errors never report open elements from a previous parse
+	parse_stack.clear();
+	if let Some(limit) = options.max_input_bytes {
+		if buffer.len() > limit {
+			return Err(errors::LimitExceeded::new(format!(
+				"XML input is {} bytes, which exceeds the limit of {limit} bytes", buffer.len()
+			)).into());
+		}
+	}
+	let normalize = !options.preserve_carriage_returns;
+	let mut source_map = match options.source_spans {
+		true => Some(parsing::SourceMapBuilder::new()),
+		false => None
+	};
+	let mut prologue = Prologue::default();
 	let mut no_comment_warn = 0;
 	let mut tag_span: (usize, usize) = (0, 0);
 	// parse decl and dtds, break on start of root element
 	loop {
-		let (tag_start, tag_end) = next_tag(&buffer, tag_span.1);
+		let (tag_start, tag_end) = next_tag(buffer, tag_span.1, options);
 		if tag_start.is_none() {
 			// not XML
 			return Err(errors::ParsingError::new(format!("no XML content")).into());
 		}
 		if tag_end.is_none(){
-			let (line, col) = line_and_column(&buffer, tag_start.unwrap());
+			let (line, col) = lines.line_and_column(buffer, tag_start.unwrap());
+			if buffer[tag_start.unwrap()..].starts_with("<!DOCTYPE") {
+				return Err(errors::ParsingError::new(format!(
+					"unterminated DOCTYPE starting at line {line}, column {col} (no matching '>' within {} bytes and {} levels of nested brackets)",
+					options.max_dtd_bytes, options.max_dtd_depth
+				)).at(tag_start.unwrap(), line, col).into());
+			}
 			return Err(errors::ParsingError::new(format!(
 				"'<' has not matching '>' (syntax error on line {line}, column {col})"
-			)).into());
+			)).at(tag_start.unwrap(), line, col).into());
 		}
 		let tag_start = tag_start.unwrap();
 		let tag_end = tag_end.unwrap();
 		let text_between = &buffer[tag_span.1..tag_start];
 		if real_text(text_between).is_some() {
-			let (line, col) = line_and_column(&buffer, tag_span.1);
+			let (line, col) = lines.line_and_column(buffer, tag_span.1);
 			return Err(errors::ParsingError::new(format!(
 				"Text outside the root element is not supported (syntax error on line {line}, column {col})"
-			)).into());
+			)).at(tag_span.1, line, col).into());
 		}
 		let slice = &buffer[tag_start..tag_end];
-		if slice.starts_with("<?xml") {
+		if is_declaration(slice) {
 			if tag_span.0 != 0 {
-				let (line, col) = line_and_column(&buffer, tag_start);
+				let (line, col) = lines.line_and_column(buffer, tag_start);
 				return Err(errors::ParsingError::new(format!(
 					"<?xml ...?> declaration must at start of XML (syntax error on line {line}, column {col})"
-				)).into());
+				)).at(tag_start, line, col).into());
 			}
-			decl = Some(dom::Declaration::from_str(slice)?);
+			prologue.decl = Some(dom::Declaration::from_str(slice)?);
 		} else if slice.starts_with("<!--") {
 			// comments outside root element not supported
 			if no_comment_warn == 0 {
@@ -423,24 +1191,24 @@ pub fn parse_str(xml_string: impl Into<String>) -> Result<dom::Document, errors:
 			no_comment_warn += 1;
 		} else if slice.starts_with("<!DOCTYPE") {
 			// DTD
-			let dtd = dom::DTD::from_string(slice)?;
-			dtds.push(dtd);
-		} else if slice.starts_with("<!"){
-			// some other XML mallarky
-			eprintln!("WARNING: Ignoring {slice} (not supported outside root element)");
+			let dtd = dom::DTD::from_string(normalize_newlines(slice, normalize))?;
+			prologue.dtds.push(dtd);
+		} else if is_opaque_markup(slice) {
+			// some other XML mallarky, keep as-is
+			prologue.opaque_markup.push(dom::OpaqueMarkup::new(normalize_newlines(slice, normalize), dom::MarkupPlacement::Prologue(prologue.dtds.len()), tag_start));
 		} else if slice.starts_with("</") {
 			// bad XML
-			let (line, col) = line_and_column(&buffer, tag_start);
+			let (line, col) = lines.line_and_column(buffer, tag_start);
 			return Err(errors::ParsingError::new(format!(
 				"cannot start with closing tag (syntax error on line {line}, column {col})"
-			)).into());
+			)).at(tag_start, line, col).into());
 		} else {
 			// root element?
 			check_element_tag(slice).map_err(|_e| {
-				let (line, col) = line_and_column(&buffer, tag_start);
+				let (line, col) = lines.line_and_column(buffer, tag_start);
 				errors::ParsingError::new(format!(
 					"invalid XML syntax on line {line}, column {col}"
-				))
+				)).at(tag_start, line, col)
 			})?;
 			tag_span = (tag_start, tag_end);
 			break;
@@ -448,33 +1216,49 @@ pub fn parse_str(xml_string: impl Into<String>) -> Result<dom::Document, errors:
 		tag_span = (tag_start, tag_end);
 	}
 	// now parse the elements, keeping a stack of parents as the tree is traversed
-	let mut parse_stack = parsing::ParseTree::new();
 	let root_slice = &buffer[tag_span.0 .. tag_span.1];
-	let root_element: dom::Element = parse_new_element(strip_tag(root_slice).as_str(), &buffer, &tag_span, None)?;
+	let root_element: dom::Element = parse_new_element(strip_tag(root_slice).as_str(), buffer, &tag_span, None, tag_scratch, lines, normalize)?;
+	// attributes with raw whitespace, as (index of the element in document order, attribute name)
+	let mut raw_whitespace_attrs: Vec<(usize, String)> = tag_scratch.raw_whitespace_attrs.drain(..).map(|a| (0, a)).collect();
+	let mut element_count: usize = 1;
+	if options.max_depth == Some(0) {
+		return Err(depth_limit_exceeded(buffer, tag_span.0, options, parse_stack, lines, prologue));
+	}
+	if let Some(sm) = source_map.as_mut() {sm.open(root_element.name().as_str(), tag_span);}
 	parse_stack.push(root_element);
 	let selfclosing_root = root_slice.ends_with("/>");
+	if selfclosing_root {
+		if let Some(sm) = source_map.as_mut() {sm.close(None);}
+	}
 	if selfclosing_root {parse_stack.pop()?;}  // pop root if it is  self-closing
 	let mut last_span: (usize, usize);
 	loop {
 		// find next tag
-		let next_span = next_tag(&buffer, tag_span.1);
+		let next_span = next_tag(buffer, tag_span.1, options);
 		if next_span.0.is_none() {
 			// EoF
 			break
 		} else if next_span.1.is_none() {
 			// broken tag?
-			let (line, col) = line_and_column(&buffer, next_span.0.unwrap());
+			let (line, col) = lines.line_and_column(buffer, next_span.0.unwrap());
 			return Err(errors::ParsingError::new(format!(
 				"invalid XML syntax on line {line}, column {col}"
-			)).into());
+			)).at(next_span.0.unwrap(), line, col).into());
 		} else {
 			// next tag
+			let next_slice = &buffer[next_span.0.unwrap() .. next_span.1.unwrap()];
+			if parse_stack.empty_stack() && is_opaque_markup(next_slice) {
+				// unrecognized markup after the root element, keep as-is
+				prologue.opaque_markup.push(dom::OpaqueMarkup::new(normalize_newlines(next_slice, normalize), dom::MarkupPlacement::Epilogue, next_span.0.unwrap()));
+				tag_span = (next_span.0.unwrap(), next_span.1.unwrap());
+				continue;
+			}
 			if selfclosing_root {
 				// next tag not allowed
-				let (line, col) = line_and_column(&buffer, next_span.0.unwrap());
+				let (line, col) = lines.line_and_column(buffer, next_span.0.unwrap());
 				return Err(errors::ParsingError::new(format!(
 					"only 1 root element is allowed (syntax error on line {line}, column {col})"
-				)).into());
+				)).at(next_span.0.unwrap(), line, col).into());
 			}
 			last_span = tag_span;
 			tag_span = (next_span.0.unwrap(), next_span.1.unwrap());
@@ -483,15 +1267,15 @@ pub fn parse_str(xml_string: impl Into<String>) -> Result<dom::Document, errors:
 		let text = &buffer[last_span.1 .. tag_span.0];
 
 		// if text is not empty, add text node
-		match real_text(text) {
+		match real_text(&normalize_newlines(text, normalize)) {
 			None => {},
 			Some(content) => {
 				parse_stack.append(dom::Text::new(content))
 					.map_err(|e|{
-						let (line, col) = line_and_column(&buffer, next_span.0.unwrap());
+						let (line, col) = lines.line_and_column(buffer, next_span.0.unwrap());
 						errors::ParsingError::new(format!(
 							"{} (syntax error on line {line}, column {col})", e
-						))
+						)).at(next_span.0.unwrap(), line, col)
 					})?;
 			}
 		};
@@ -499,31 +1283,31 @@ pub fn parse_str(xml_string: impl Into<String>) -> Result<dom::Document, errors:
 		let slice = &buffer[tag_span.0 .. tag_span.1];
 		if slice.starts_with("<!--") && slice.ends_with("-->") {
 			// comment
-			parse_stack.append(dom::Comment::new(&slice[4 .. slice.len().saturating_sub(3)])?)
+			parse_stack.append(dom::Comment::new(normalize_newlines(&slice[4 .. slice.len().saturating_sub(3)], normalize))?)
 				.map_err(|e|{
-					let (line, col) = line_and_column(&buffer, next_span.0.unwrap());
+					let (line, col) = lines.line_and_column(buffer, next_span.0.unwrap());
 					errors::ParsingError::new(format!(
 						"{} (syntax error on line {line}, column {col})", e
-					))
+					)).at(next_span.0.unwrap(), line, col)
 				})?;
 		} else if slice.starts_with("<![CDATA["){
 			// CDATA
 			if !slice.ends_with("]]>") {
-				let (line, col) = line_and_column(&buffer,  next_span.0.unwrap());
+				let (line, col) = lines.line_and_column(buffer, next_span.0.unwrap());
 				return Err(errors::ParsingError::new(format!(
 					"Unclosed CDATA. '<![CDATA[' must be followed by ']]>' (syntax error on line {line}, column {col})"
-				)).into());
+				)).at(next_span.0.unwrap(), line, col).into());
 			}
-			parse_stack.append(dom::CData::new(&slice[9 .. slice.len().saturating_sub(3)])?)
+			parse_stack.append(dom::CData::new(normalize_newlines(&slice[9 .. slice.len().saturating_sub(3)], normalize))?)
 				.map_err(|e|{
-					let (line, col) = line_and_column(&buffer, next_span.0.unwrap());
+					let (line, col) = lines.line_and_column(buffer, next_span.0.unwrap());
 					errors::ParsingError::new(format!(
 						"{} (syntax error on line {line}, column {col})", e
-					))
+					)).at(next_span.0.unwrap(), line, col)
 				})?;
 		} else if slice.starts_with("<!") {
 			// other unsupported thing
-			let (line, col) = line_and_column(&buffer, tag_span.0);
+			let (line, col) = lines.line_and_column(buffer, tag_span.0);
 			return Err(errors::NotSupportedError::new(format!(
 				"kiss-xml does not support '{}' (error on line {line}, column {col})",
 				abbreviate(slice, 32)
@@ -533,38 +1317,46 @@ pub fn parse_str(xml_string: impl Into<String>) -> Result<dom::Document, errors:
 			let tag_def = strip_tag(slice);
 			// sanity check
 			check_element_tag(slice).map_err(|e| {
-				let (line, col) = line_and_column(&buffer, tag_span.0);
+				let (line, col) = lines.line_and_column(buffer, tag_span.0);
 				errors::ParsingError::new(format!(
 					"{} (syntax error on line {line}, column {col})", e
-				))
+				)).at(tag_span.0, line, col)
 			})?;
 			// is it a closing tag? If so, pop the parent stack
 			if slice.starts_with("</") {
 				let active_element = parse_stack.top_element()
 					.ok_or_else(||{
-						let (line, col) = line_and_column(&buffer, next_span.0.unwrap());
+						let (line, col) = lines.line_and_column(buffer, next_span.0.unwrap());
 						errors::ParsingError::new(format!(
 							"root element already closed (syntax error on line {line}, column {col})"
-						))
+						)).at(next_span.0.unwrap(), line, col)
 					})?;
 				let open_tagname = active_element.tag_name();
 				if tag_def != open_tagname {
-					let (line, col) = line_and_column(&buffer, tag_span.0);
+					let (line, col) = lines.line_and_column(buffer, tag_span.0);
 					return Err(errors::ParsingError::new(format!(
 						"closing tag {slice} does not match <{open_tagname}> (syntax error on line {line}, column {col})"
-					)).into());
+					)).at(tag_span.0, line, col).into());
 				}
 				parse_stack.pop()?;
+				if let Some(sm) = source_map.as_mut() {sm.close(Some(tag_span));}
 			} else {
 				// add new element to the stack, unless it is self-closing
-				let new_element = parse_new_element(tag_def.as_str(), &buffer, &tag_span, parse_stack.top_element())?;
+				if options.max_depth.is_some_and(|max_depth| parse_stack.depth() >= max_depth) {
+					return Err(depth_limit_exceeded(buffer, tag_span.0, options, parse_stack, lines, prologue));
+				}
+				let new_element = parse_new_element(tag_def.as_str(), buffer, &tag_span, parse_stack.top_element(), tag_scratch, lines, normalize)?;
+				raw_whitespace_attrs.extend(tag_scratch.raw_whitespace_attrs.drain(..).map(|a| (element_count, a)));
+				element_count += 1;
+				if let Some(sm) = source_map.as_mut() {sm.open(new_element.name().as_str(), tag_span);}
 				if slice.ends_with("/>") {
+					if let Some(sm) = source_map.as_mut() {sm.close(None);}
 					// self-closing
 					parse_stack.append(new_element).map_err(|e| {
-						let (line, col) = line_and_column(&buffer, tag_span.0);
+						let (line, col) = lines.line_and_column(buffer, tag_span.0);
 						errors::ParsingError::new(format!(
 							"{} (syntax error on line {line}, column {col})", e
-						))
+						)).at(tag_span.0, line, col)
 					})?;
 				} else {
 					parse_stack.push(new_element);
@@ -575,16 +1367,76 @@ pub fn parse_str(xml_string: impl Into<String>) -> Result<dom::Document, errors:
 	}
 	// check that root was closed
 	if ! parse_stack.empty_stack() {
+		let (line, col) = lines.line_and_column(buffer, buffer.len());
 		return Err(errors::ParsingError::new(format!(
 			"root element not closed"
-		)).into());
+		)).at(buffer.len(), line, col).into());
 	}
 	// return a DOM document
-	Ok(dom::Document::new_with_decl_dtd(
-		parse_stack.to_dom()?,
-		decl,
-		Some(&dtds)
-	))
+	let mut doc = prologue.into_document(parse_stack.to_dom()?);
+	doc.set_source_spans(source_map.map(|sm| sm.build()));
+	// no declaration and no trailing newline: keep it that way when written
+	if doc.declaration().is_none() && !buffer.ends_with('\n') {
+		doc.set_fragment(true);
+	}
+	if !raw_whitespace_attrs.is_empty() {
+		let paths: Vec<String> = lint::element_paths(doc.root_element()).into_iter().map(|(p, _)| p).collect();
+		for (i, attr) in raw_whitespace_attrs {
+			doc.push_parse_warning(lint::LintWarning{
+				kind: lint::LintKind::RawWhitespaceInAttributeValue,
+				path: paths[i].clone(),
+				message: format!("value of attribute '{attr}' contains a raw tab, newline, or carriage return, which other XML parsers normalize to a space"),
+			});
+		}
+	}
+	Ok(doc)
+}
+
+/// the declaration, DTDs, and opaque markup found around the root element while parsing
+#[derive(Default)]
+struct Prologue {
+	/// the `<?xml ...?>` declaration, if any
+	decl: Option<dom::Declaration>,
+	/// DOCTYPE definitions, in document order
+	dtds: Vec<dom::DTD>,
+	/// unrecognized markup before and after the root element
+	opaque_markup: Vec<dom::OpaqueMarkup>,
+}
+
+impl Prologue {
+	/// makes a document with the given root element and this prologue
+	fn into_document(self, root: dom::Element) -> dom::Document {
+		let mut doc = dom::Document::new_with_decl_dtd(root, self.decl, Some(&self.dtds));
+		for markup in self.opaque_markup {
+			doc.push_opaque_markup(markup);
+		}
+		doc
+	}
+}
+
+/// makes the error for an element that is nested deeper than the `max_depth` option allows, at byte
+/// offset `pos`, including the partial document if requested in the options
+fn depth_limit_exceeded(
+	buffer: &str, pos: usize, options: &ParseOptions, parse_stack: &mut parsing::ParseTree, lines: &parsing::LineIndex,
+	prologue: Prologue
+) -> errors::KissXmlError {
+	let (line, col) = lines.line_and_column(buffer, pos);
+	let open_elements = parse_stack.open_element_names();
+	let mut error = errors::LimitExceeded::new(format!(
+		"element nesting depth exceeds the limit of {} (on line {line}, column {col}, inside {})",
+		options.max_depth.unwrap_or(0), match open_elements.is_empty() {
+			true => "the document".to_string(),
+			false => format!("<{}>", open_elements.join("><"))
+		}
+	));
+	error.position = Some(pos);
+	error.open_elements = open_elements;
+	if options.return_partial_on_limit {
+		if let Ok(root) = parse_stack.to_dom() {
+			error.partial_document = Some(Box::new(prologue.into_document(root)));
+		}
+	}
+	error.into()
 }
 
 /// abbreviates long strings with ...
@@ -603,37 +1455,20 @@ fn abbreviate(text: &str, limit: usize) -> String {
 /// # Args:
 /// * tag_content - XML tag with the leading and trailing </> and whitespace removed (ie output of
 /// `strip_tag(...)`)
-fn parse_new_element(tag_content: &str, buffer: &String, tag_span: &(usize, usize), parent: Option<&dom::Element>) -> Result<dom::Element, KissXmlError> {
-	let components = quote_aware_split(tag_content);
-	if components.len() == 0 {
-		let (line, col) = line_and_column(&buffer, tag_span.0);
-		return Err(errors::ParsingError::new(format!(
-			"invalid XML syntax on line {line}, column {col}: empty tags not supported"
-		)).into());
-	}
-	// parse attributes
-	let mut attrs: HashMap<String, String> = HashMap::new();
-	for i in 1..components.len() {
-		let kv = &components[i];
-		if !kv.contains("=") {
-			let (line, col) = line_and_column(&buffer, tag_span.0);
-			return Err(errors::ParsingError::new(format!(
-				"invalid XML syntax on line {line}, column {col}: attributes must be in the form 'key=\"value\"'"
-			)).into());
-		}
-		let (k, mut v) = kv.split_once("=").unwrap();
-		// note: v string contains enclosing quotes
-		v = &v[1..(v.len()-1)]; // remove quotes
-		attrs.insert(k.to_string(), v.to_string());
-	}
+fn parse_new_element(tag_content: &str, buffer: &str, tag_span: &(usize, usize), parent: Option<&dom::Element>, scratch: &mut TagScratch, lines: &parsing::LineIndex, normalize_line_endings: bool) -> Result<dom::Element, KissXmlError> {
+	let tag_name = split_tag_content_into(tag_content, scratch, normalize_line_endings).map_err(|msg| {
+		let (line, col) = lines.line_and_column(buffer, tag_span.0);
+		errors::ParsingError::new(format!("invalid XML syntax on line {line}, column {col}: {msg}")).at(tag_span.0, line, col)
+	})?;
+	let attrs: HashMap<String, String> = scratch.attrs.drain(..).collect();
 	// parse name and namespace
-	let mut name = components[0].as_str();
+	let mut name = tag_name.as_str();
 	let mut xmlns: Option<String> = None;
 	let mut xmlns_prefix: Option<String> = None;
 	// check parent for inherited namespaces
 	let (inherited_default_namespace, inherited_xmlns_context) = match parent {
 		None => (None, None),
-		Some(parent) => (parent.default_namespace(), Some(parent.get_namespace_context()))
+		Some(parent) => (parent.effective_default_namespace(), Some(parent.get_namespace_context()))
 	};
 	if name.contains(":"){
 		let (a, b) = name.split_once(":").unwrap();
@@ -645,10 +1480,10 @@ fn parse_new_element(tag_content: &str, buffer: &String, tag_span: &(usize, usiz
 			true => attrs.get(prefix_key.as_str()).map(String::clone),
 			false => match &inherited_xmlns_context{
 				None => {
-					let (line, col) = line_and_column(&buffer, tag_span.0);
+					let (line, col) = lines.line_and_column(buffer, tag_span.0);
 					return Err(errors::ParsingError::new(format!(
 						"invalid XML syntax on line {line}, column {col}: XML namespace prefix '{a}' has no defined namespace (missing 'xmlns:{a}=\"...\"')"
-					)).into());
+					)).at(tag_span.0, line, col).into());
 				}
 				Some(ctx) => {ctx.get(prefix_key.as_str()).map(String::clone)}
 			}
@@ -661,8 +1496,97 @@ fn parse_new_element(tag_content: &str, buffer: &String, tag_span: &(usize, usiz
 	Ok(new_element)
 }
 
+/// reusable buffers for splitting tags into names and attributes
+#[derive(Debug, Default)]
+pub(crate) struct TagScratch {
+	/// byte ranges of the whitespace-separated parts of a tag
+	parts: Vec<Range<usize>>,
+	/// the attributes of the last split tag, as (key, value) pairs in source order
+	attrs: Vec<(String, String)>,
+	/// names of the attributes of the last split tag whose source value contains a raw tab,
+	/// newline, or carriage return
+	raw_whitespace_attrs: Vec<String>,
+}
+
+/// splits the content of a start tag (ie output of `strip_tag(...)`) into the tag name and the
+/// attributes as (key, value) pairs in source order, with the attribute values unescaped.
+/// Returns an error message if the tag content is malformed.
+pub(crate) fn split_tag_content(tag_content: &str) -> Result<(String, Vec<(String, String)>), String> {
+	let mut scratch = TagScratch::default();
+	let name = split_tag_content_into(tag_content, &mut scratch, true)?;
+	Ok((name, scratch.attrs))
+}
+
+/// same as `split_tag_content(...)`, but using the given buffers and leaving the attributes in
+/// `scratch.attrs`, and only normalizing line endings in attribute values if requested
+fn split_tag_content_into(tag_content: &str, scratch: &mut TagScratch, normalize_line_endings: bool) -> Result<String, String> {
+	quote_aware_split(tag_content, &mut scratch.parts);
+	scratch.attrs.clear();
+	scratch.raw_whitespace_attrs.clear();
+	if scratch.parts.is_empty() {
+		return Err("empty tags not supported".to_string());
+	}
+	for kv in scratch.parts[1..].iter().map(|r| &tag_content[r.clone()]) {
+		let malformed = || "attributes must be in the form 'key=\"value\"'".to_string();
+		let (k, v) = kv.split_once("=").ok_or_else(malformed)?;
+		// note: v string contains enclosing quotes
+		let quoted = v.len() >= 2 && (
+			(v.starts_with('"') && v.ends_with('"')) || (v.starts_with('\'') && v.ends_with('\''))
+		);
+		if k.is_empty() || !quoted {
+			return Err(malformed());
+		}
+		if v.contains(['\t', '\n', '\r']) {
+			scratch.raw_whitespace_attrs.push(k.to_string());
+		}
+		let value = normalize_newlines(&v[1..(v.len()-1)], normalize_line_endings);
+		scratch.attrs.push((k.to_string(), unescape(value.as_ref())));
+	}
+	Ok(tag_content[scratch.parts[0].clone()].to_string())
+}
+
+/// writes attributes as ` key="value"` pairs (escaping the values), as used in start tags. If
+/// `escape_whitespace` is true, tabs, newlines, and carriage returns are written as character
+/// references so that other XML parsers do not normalize them to spaces
+pub(crate) fn write_attributes<'a>(out: &mut String, attrs: impl Iterator<Item = (&'a str, &'a str)>, escape_whitespace: bool) {
+	for (k, v) in attrs {
+		out.push(' ');
+		out.push_str(k);
+		out.push_str("=\"");
+		let escaped = attribute_escape(v);
+		if escape_whitespace {
+			out.push_str(escaped.replace('\t', "&#9;").replace('\n', "&#10;").replace('\r', "&#13;").as_str());
+		} else {
+			out.push_str(escaped.as_str());
+		}
+		out.push('"');
+	}
+}
+
+/// true if the tag is the `<?xml ...?>` declaration (and not some other processing instruction)
+fn is_declaration(tag: &str) -> bool {
+	match tag.strip_prefix("<?xml") {
+		None => false,
+		Some(rest) => rest.starts_with(|c: char| c.is_whitespace() || c == '?')
+	}
+}
+
+/// true if the tag is well-delimited markup that may appear outside the root element but is not
+/// modeled by the DOM (ie processing instructions and `<!...>` constructs other than comments,
+/// CDATA, and DOCTYPEs)
+fn is_opaque_markup(tag: &str) -> bool {
+	if tag.starts_with("<?") {
+		!is_declaration(tag) && tag.ends_with("?>")
+	} else if tag.starts_with("<!") {
+		!(tag.starts_with("<!--") || tag.starts_with("<![CDATA[") || tag.starts_with("<!DOCTYPE"))
+			&& tag.ends_with('>')
+	} else {
+		false
+	}
+}
+
 /// removes leading and trailing <> and/or /
-fn strip_tag(tag: &str) -> String {
+pub(crate) fn strip_tag(tag: &str) -> String {
 	let mut tag = tag;
 	if tag.starts_with("<") {tag = &tag[1..];}
 	if tag.starts_with("/") {tag = &tag[1..];}
@@ -673,10 +1597,10 @@ fn strip_tag(tag: &str) -> String {
 
 
 /// singleton regex matcher
-const ELEM_MATCHER_SINGLETON: OnceCell<Regex> = OnceCell::new();
+static ELEM_MATCHER_SINGLETON: OnceLock<Regex> = OnceLock::new();
 /// checks if a tag has valid syntax for an element (does not parse)
-fn check_element_tag(text: &str) -> Result<(), errors::KissXmlError> {
-	let singleton = ELEM_MATCHER_SINGLETON;
+pub(crate) fn check_element_tag(text: &str) -> Result<(), errors::KissXmlError> {
+	let singleton = &ELEM_MATCHER_SINGLETON;
 	let matcher = singleton.get_or_init(||{
 		// see https://www.w3.org/TR/REC-xml/#sec-common-syn
 		let name_start_char = r#"[:A-Z_a-z\xC0-\xD6\xD8-\xF6\xF8-\x{2FF}\x{370}-\x{37D}\x{37F}-\x{1FFF}\x{200C}-\x{200D}\x{2070}-\x{218F}\x{2C00}-\x{2FEF}\x{3001}-\x{D7FF}\x{F900}-\x{FDCF}\x{FDF0}-\x{FFFD}\x{10000}-\x{EFFFF}]"#;
@@ -691,8 +1615,8 @@ fn check_element_tag(text: &str) -> Result<(), errors::KissXmlError> {
 }
 
 
-/// finds next <> enclosed thing (or None if EoF is reached)
-fn next_tag(buffer: &String, from: usize) -> (Option<usize>, Option<usize>) {
+/// finds next <> enclosed thing (or None if EoF is reached, or if a `<!...>` declaration exceeds the limits in the options)
+fn next_tag(buffer: &str, from: usize, options: &ParseOptions) -> (Option<usize>, Option<usize>) {
 	let _i = from;
 	let start: Option<usize> = (&buffer[from..]).find("<")
 		.map(|i|i+from);
@@ -713,47 +1637,46 @@ fn next_tag(buffer: &String, from: usize) -> (Option<usize>, Option<usize>) {
 		return (start, sub_buffer.find("]]>").map(|i|i+start_index+3));
 	} else if sub_buffer.starts_with("<!") {
 		// DTD or other XML weirdness, do nested search for closing >
-		return (start, nested_quote_aware_find_close(sub_buffer,2).map(|i|i+start_index+1))
+		let limits = (options.max_dtd_bytes, options.max_dtd_depth);
+		return (start, nested_quote_aware_find_close(sub_buffer, 2, limits).map(|i|i+start_index+1))
 	} else {
 		// normal element tag (we assume)
 		return (start, quote_aware_find(sub_buffer, ">", 1).map(|i|i+start_index+1))
 	}
 }
 
-/// splits by whitespace, respecting quotes
-fn quote_aware_split(text: &str) -> Vec<String> {
-	let mut builder = String::new();
-	let mut vec: Vec<String> = Vec::new();
+/// splits by whitespace, respecting quotes, writing the byte ranges of the parts into `out`
+/// (which is cleared first)
+fn quote_aware_split(text: &str, out: &mut Vec<Range<usize>>) {
+	out.clear();
+	let mut start: Option<usize> = None;
 	let mut in_quote = false;
 	let mut quote_char = '\0';
-	for (_i, c) in text.char_indices() {
+	for (i, c) in text.char_indices() {
 		if !in_quote && (c == '\'' || c == '"') {
 			// start of quoted text
 			in_quote = true;
 			quote_char = c;
-			builder.push(c);
+			start.get_or_insert(i);
 		} else if in_quote {
 			// quoted text
-			builder.push(c);
 			if c == quote_char {
 				// end of quoted text
 				in_quote = false;
 			}
 		} else if c.is_whitespace() {
 			// break on whitespace
-			if builder.len() > 0 {
-				vec.push(builder);
-				builder = String::new();
+			if let Some(s) = start.take() {
+				out.push(s..i);
 			}
 		} else {
 			// normal text
-			builder.push(c);
+			start.get_or_insert(i);
 		}
 	}
-	if !builder.is_empty() {
-		vec.push(builder);
+	if let Some(s) = start {
+		out.push(s..text.len());
 	}
-	return vec;
 }
 /// like `String.find()` but skipping quoted content
 fn quote_aware_find(text: &str, pattern: &str, from: usize) -> Option<usize> {
@@ -779,12 +1702,26 @@ fn quote_aware_find(text: &str, pattern: &str, from: usize) -> Option<usize> {
 	None
 }
 
-/// like `quote_aware_find()` above, but the pattern is '>' and it skips both quoted content and nested <tags>
-fn nested_quote_aware_find_close(text: &str, from: usize) -> Option<usize> {
-	let mut depth: i32 = 0;
+/// like `quote_aware_find()` above, but the pattern is '>' and it skips quoted content, comments, and nested <tags>
+/// (gives up and returns None if the closing '>' is not found within `max_len` bytes or the nesting exceeds `max_depth`)
+fn nested_quote_aware_find_close(text: &str, from: usize, (max_len, max_depth): (usize, usize)) -> Option<usize> {
+	let mut depth: usize = 0;
 	let mut in_quote = false;
 	let mut quote_char = '\0';
+	let mut comment_end: usize = 0;
 	for (i, c) in text[from..].char_indices() {
+		if from + i >= max_len {
+			return None;
+		}
+		if from + i < comment_end {
+			// inside a <!-- comment -->
+			continue;
+		}
+		if !in_quote && text[(from + i)..].starts_with("<!--") {
+			// skip comment (may contain quotes or > characters)
+			comment_end = text[(from + i)..].find("-->").map(|j| from + i + j + 3)?;
+			continue;
+		}
 		if in_quote {
 			if c == quote_char { // end of quoted field
 				in_quote = false;
@@ -798,6 +1735,9 @@ fn nested_quote_aware_find_close(text: &str, from: usize) -> Option<usize> {
 				in_quote = true;
 			} else if c == '<' {
 				depth += 1;
+				if depth > max_depth {
+					return None;
+				}
 			} else if c == '>' {
 				if depth == 0 {
 					return Some(from+i)
@@ -811,12 +1751,12 @@ fn nested_quote_aware_find_close(text: &str, from: usize) -> Option<usize> {
 
 
 /// singleton regex matcher
-const IS_BLANK_MATCHER_SINGLETON: OnceCell<Regex> = OnceCell::new();
+static IS_BLANK_MATCHER_SINGLETON: OnceLock<Regex> = OnceLock::new();
 /// extracts the actual text from a string slice,
 /// returning None if it is all whitespace
 fn real_text(text: &str) -> Option<String> {
 	// check for empty string
-	let singleton = IS_BLANK_MATCHER_SINGLETON;
+	let singleton = &IS_BLANK_MATCHER_SINGLETON;
 	let matcher = singleton.get_or_init(|| Regex::new(r#"^\s*$"#).unwrap());
 	if matcher.is_match(text) {
 		return None;
@@ -825,20 +1765,16 @@ fn real_text(text: &str) -> Option<String> {
 	Some(unescape(text))
 }
 
-/// get line and column number for index to use for error reporting
-fn line_and_column(text: &String, pos: usize) -> (usize, usize){
-	let mut line = 1;
-	let mut col = 1;
-	for (i, c) in text.char_indices(){
-		col += 1;
-		if c == '\n' {
-			line += 1;
-			col = 1;
-		}
-		if i >= pos {break;}
+/// replaces `\r\n` and lone `\r` line endings with `\n` (if `normalize` is true), as required
+/// by the XML spec for character data
+fn normalize_newlines(text: &str, normalize: bool) -> Cow<'_, str> {
+	if normalize && text.contains('\r') {
+		Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"))
+	} else {
+		Cow::Borrowed(text)
 	}
-	(line, col)
 }
+
 /// returns Ok result if indent is valid (spaces or tabs), Err otherwise.
 /// Valid indents are 1 tab character or any number of spaces
 pub(crate) fn validate_indent(indent: &str) -> Result<(), ()> {
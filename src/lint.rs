@@ -0,0 +1,232 @@
+/*!
+Checks for constructs that are legal XML but are likely to be mistakes, such as an attribute
+value that looks like nested markup or a namespace prefix that is declared but never used.
+
+Lint warnings are never errors: a document that produces warnings is still a valid document and
+will be serialized as-is. Use [Document::lint()](crate::dom::Document::lint()) to run all of the
+checks, or a [Linter] to choose which checks to run.
+
+# Example
+```rust
+fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+	use kiss_xml::lint::{LintKind, Linter};
+	let doc = kiss_xml::parse_str(r#"<root xmlns:x="internal://x" note="&lt;b&gt;"/>"#)?;
+	let warnings = doc.lint();
+	assert_eq!(warnings.len(), 2);
+	let linter = Linter::new().disable(LintKind::UnusedNamespacePrefix);
+	let warnings = linter.lint(&doc);
+	assert_eq!(warnings[0].kind, LintKind::MarkupInAttributeValue);
+	assert_eq!(warnings[0].path, "/root[1]");
+	Ok(())
+}
+```
+*/
+
+use std::collections::{HashMap, HashSet};
+use crate::dom::*;
+
+/// The kinds of checks performed by a [Linter]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LintKind {
+	/// An attribute value that looks like un-escaped markup (eg `value="<b>"`), which was probably intended as nested XML
+	MarkupInAttributeValue,
+	/// Two child elements of the same parent with the same name and the same `id` attribute
+	DuplicateChild,
+	/// A CDATA node that contains only whitespace (or nothing at all)
+	WhitespaceOnlyCData,
+	/// A namespace prefix that is declared with `xmlns:prefix` but is not used by the declaring element or any of its descendants
+	UnusedNamespacePrefix,
+	/// An attribute with an empty value (eg `id=""`), which was probably meant to be left out. Namespace declarations with empty values (eg `xmlns=""`) are not reported, because they undeclare a namespace.
+	EmptyAttributeValue,
+	/// An attribute value that contained a raw tab, newline, or carriage return in the parsed XML, which other XML parsers normalize to a space. This is only detected when parsing (see [Document::parse_warnings()](crate::dom::Document::parse_warnings())); write the document with the `escape_attribute_whitespace` output option to fix it.
+	RawWhitespaceInAttributeValue,
+}
+
+impl LintKind {
+	/// All lint kinds, in the order that they are checked
+	pub const ALL: [LintKind; 6] = [
+		LintKind::MarkupInAttributeValue,
+		LintKind::DuplicateChild,
+		LintKind::WhitespaceOnlyCData,
+		LintKind::UnusedNamespacePrefix,
+		LintKind::EmptyAttributeValue,
+		LintKind::RawWhitespaceInAttributeValue,
+	];
+}
+
+impl std::fmt::Display for LintKind {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		std::fmt::Debug::fmt(self, f)
+	}
+}
+
+/// A single warning produced by a [Linter]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LintWarning {
+	/// The check that produced this warning
+	pub kind: LintKind,
+	/// Absolute path of the element that the warning applies to, with an index for every segment (eg `/root[1]/item[2]`), which can be passed to [Document::element_at_path()](crate::dom::Document::element_at_path())
+	pub path: String,
+	/// Human-readable description of the problem
+	pub message: String,
+}
+
+impl std::fmt::Display for LintWarning {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} at {}: {}", self.kind, self.path, self.message)
+	}
+}
+
+/**
+Runs a configurable set of lint checks against a document or element. All checks are enabled by default.
+# Example
+```rust
+fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+	use kiss_xml::lint::{LintKind, Linter};
+	let doc = kiss_xml::parse_str(r#"<root><a><![CDATA[ ]]></a></root>"#)?;
+	assert_eq!(Linter::new().lint(&doc).len(), 1);
+	assert!(Linter::new().disable(LintKind::WhitespaceOnlyCData).lint(&doc).is_empty());
+	assert!(Linter::none().enable(LintKind::DuplicateChild).lint(&doc).is_empty());
+	Ok(())
+}
+```
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Linter {
+	/// the enabled checks
+	enabled: HashSet<LintKind>,
+}
+
+impl Linter {
+	/// Creates a new linter with all checks enabled
+	pub fn new() -> Self {
+		Linter{enabled: LintKind::ALL.into_iter().collect()}
+	}
+	/// Creates a new linter with all checks disabled
+	pub fn none() -> Self {
+		Linter{enabled: HashSet::new()}
+	}
+	/// Enables the given check
+	pub fn enable(mut self, kind: LintKind) -> Self {
+		self.enabled.insert(kind);
+		self
+	}
+	/// Disables the given check
+	pub fn disable(mut self, kind: LintKind) -> Self {
+		self.enabled.remove(&kind);
+		self
+	}
+	/// Returns true if the given check is enabled
+	pub fn is_enabled(&self, kind: LintKind) -> bool {
+		self.enabled.contains(&kind)
+	}
+	/// Runs the enabled checks on the given document and returns the warnings in document order, followed by the enabled warnings that were found when the document was parsed (see [Document::parse_warnings()](crate::dom::Document::parse_warnings()))
+	pub fn lint(&self, doc: &Document) -> Vec<LintWarning> {
+		let mut warnings = self.lint_element(doc.root_element());
+		warnings.extend(doc.parse_warnings().iter().filter(|w| self.is_enabled(w.kind)).cloned());
+		warnings
+	}
+	/// Runs the enabled checks on the given element and its descendants and returns the warnings in document order (paths are relative to the given element, which is treated as the root)
+	pub fn lint_element(&self, root: &Element) -> Vec<LintWarning> {
+		let mut warnings: Vec<LintWarning> = Vec::new();
+		for (path, elem) in element_paths(root) {
+			self.check_element(&path, elem, &mut warnings);
+		}
+		warnings
+	}
+	/// runs the enabled checks that apply to a single element
+	fn check_element(&self, path: &str, elem: &Element, warnings: &mut Vec<LintWarning>) {
+		let mut warn = |kind: LintKind, message: String| {
+			warnings.push(LintWarning{kind, path: path.to_string(), message});
+		};
+		if self.is_enabled(LintKind::MarkupInAttributeValue) {
+			let mut attrs: Vec<(&String, &String)> = elem.attributes().iter().collect();
+			attrs.sort();
+			for (k, v) in attrs {
+				if looks_like_markup(v) {
+					warn(LintKind::MarkupInAttributeValue, format!("value of attribute '{k}' looks like markup: {v:?}"));
+				}
+			}
+		}
+		if self.is_enabled(LintKind::DuplicateChild) {
+			let mut seen: HashSet<(String, &String)> = HashSet::new();
+			for child in elem.child_elements() {
+				if let Some(id) = child.get_attr("id") {
+					if !seen.insert((child.tag_name(), id)) {
+						warn(LintKind::DuplicateChild, format!("duplicate child element <{}> with id {id:?}", child.tag_name()));
+					}
+				}
+			}
+		}
+		if self.is_enabled(LintKind::WhitespaceOnlyCData) {
+			for cdata in elem.children().filter_map(|n| n.as_cdata().ok()) {
+				if cdata.text().trim().is_empty() {
+					warn(LintKind::WhitespaceOnlyCData, "CDATA section contains only whitespace".to_string());
+				}
+			}
+		}
+		if self.is_enabled(LintKind::UnusedNamespacePrefix) {
+			let mut prefixes: Vec<String> = elem.namespace_prefixes().unwrap_or_default().into_keys().collect();
+			prefixes.sort();
+			for prefix in prefixes {
+				if !prefix_used(elem, &prefix) {
+					warn(LintKind::UnusedNamespacePrefix, format!("namespace prefix '{prefix}' is declared but never used"));
+				}
+			}
+		}
+		if self.is_enabled(LintKind::EmptyAttributeValue) {
+			for name in elem.attrs_with_empty_values() {
+				warn(LintKind::EmptyAttributeValue, format!("attribute '{name}' has an empty value"));
+			}
+		}
+	}
+}
+
+impl Default for Linter {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// returns the given element and all of its descendants in document order, each with its
+/// absolute path (eg `/root[1]/item[2]`), treating the given element as the root
+pub(crate) fn element_paths(root: &Element) -> Vec<(String, &Element)> {
+	let mut elements: Vec<(String, &Element)> = Vec::new();
+	let mut stack: Vec<(String, &Element)> = vec![(format!("/{}[1]", root.name()), root)];
+	while let Some((path, elem)) = stack.pop() {
+		let mut counts: HashMap<String, usize> = HashMap::new();
+		let mut children: Vec<(String, &Element)> = Vec::new();
+		for child in elem.child_elements() {
+			let n = counts.entry(child.name()).or_insert(0);
+			*n += 1;
+			children.push((format!("{path}/{}[{n}]", child.name()), child));
+		}
+		stack.extend(children.into_iter().rev());
+		elements.push((path, elem));
+	}
+	elements
+}
+
+/// returns true if the text contains something that looks like a tag, eg `<b>`, `</b>` or `<!-- -->`
+fn looks_like_markup(text: &str) -> bool {
+	let mut rest = text;
+	while let Some(i) = rest.find('<') {
+		rest = &rest[i+1..];
+		let starts_tag = rest.chars().next()
+			.map(|c| c.is_alphabetic() || c == '_' || c == '/' || c == '!' || c == '?')
+			.unwrap_or(false);
+		if starts_tag && rest.contains('>') {
+			return true;
+		}
+	}
+	false
+}
+
+/// returns true if the prefix is used by the element or any of its descendants, either on the element name or an attribute name
+fn prefix_used(elem: &Element, prefix: &str) -> bool {
+	let attr_prefix = format!("{prefix}:");
+	elem.self_and_descendants().any(|e| {
+		e.namespace_prefix().as_deref() == Some(prefix)
+			|| e.attributes().keys().any(|k| k.starts_with(&attr_prefix))
+	})
+}
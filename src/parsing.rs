@@ -1,3 +1,4 @@
+use std::cell::OnceCell;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::hash::Hasher;
@@ -14,13 +15,34 @@ pub struct ParseTree{
 	/** hold data in a non-tree format because Rust's lifetime syntax doesn't let you retrieve the lifetime from the parent of a node at runtime (lifetimes exist only at compile time, and even then there is no syntax for separately disentanlging reference-lifetimes from data-lifetimes) */
 	data: HashMap<usize, ParseTreeNode>,
 	/// current tip of the parsing tree, pointing to the "top" of the stack
-	pos: Option<usize>
+	pos: Option<usize>,
+	/// number of elements on the stack
+	depth: usize
 }
 
 impl ParseTree {
-	/// new parser tree
-	pub fn new() -> Self {
-		Self::default()
+	/// removes all nodes, keeping the allocated memory for reuse
+	pub fn clear(&mut self) {
+		self.data.clear();
+		self.pos = None;
+		self.depth = 0;
+	}
+	/// returns the number of elements on the stack (ie the nesting depth of the top element)
+	pub fn depth(&self) -> usize {
+		self.depth
+	}
+	/// returns the tag names of the elements on the stack, from the root to the top element
+	pub fn open_element_names(&self) -> Vec<String> {
+		let mut names: Vec<String> = Vec::with_capacity(self.depth);
+		let mut pos = self.pos;
+		while let Some(node) = pos.and_then(|p| self.data.get(&p)) {
+			if let Ok(e) = node.value.as_element() {
+				names.push(e.tag_name());
+			}
+			pos = node.parent_id;
+		}
+		names.reverse();
+		names
 	}
 	/// returns true if the simulated "stack" is empty, false otherwise. This can still return true
 	/// even if the internal HashMap is not empty
@@ -32,6 +54,7 @@ impl ParseTree {
 	}
 	/// push a new element to the stack
 	pub fn push(&mut self, new_element: Element) {
+		self.depth += 1;
 		if self.pos.is_none() {
 			self.data.insert(0, ParseTreeNode{
 				id: 0,
@@ -63,6 +86,7 @@ impl ParseTree {
 		let new_pos = self.data
 			.get(&pos).expect("logic error").parent_id;
 		self.pos = new_pos;
+		self.depth -= 1;
 		Ok(())
 	}
 	/// append a node to the top element on the stack (without adding the new node to the stack)
@@ -93,7 +117,7 @@ impl ParseTree {
 		}
 	}
 	/// converts the whole parse tree to a DOM, returning the root element
-	pub fn to_dom(mut self) -> Result<Element, KissXmlError> {
+	pub fn to_dom(&mut self) -> Result<Element, KissXmlError> {
 		if self.data.is_empty() {
 			return Err(ParsingError::new("no root element").into());
 		}
@@ -115,6 +139,8 @@ impl ParseTree {
 				.value.as_element_mut().expect("logic error: parent is not an Element")
 				.append_boxed(node.value);
 		}
+		self.pos = None;
+		self.depth = 0;
 		let root_node = self.data.remove(&0).expect("logic error: no root element");
 		let mut root = root_node.destruct();
 		// let mut e = **(root.as_any()
@@ -129,6 +155,81 @@ impl ParseTree {
 	}
 }
 
+/** records the source spans of elements while parsing, keeping track of element paths */
+#[derive(Debug, Default)]
+pub struct SourceMapBuilder {
+	/// the table being built
+	table: SourceMapTable,
+	/// stack of open elements: (index in table, canonical path, counts of child element names)
+	stack: Vec<(usize, String, HashMap<String, usize>)>,
+	/// number of elements added so far
+	count: usize
+}
+
+impl SourceMapBuilder {
+	/// new builder
+	pub fn new() -> Self {
+		Self::default()
+	}
+	/// records the start tag of a new element, which becomes the current open element
+	pub fn open(&mut self, name: &str, start_tag: (usize, usize)) {
+		let path = match self.stack.last_mut() {
+			None => format!("/{name}[1]"),
+			Some((_, parent_path, counts)) => {
+				let n = counts.entry(name.to_string()).or_insert(0);
+				*n += 1;
+				format!("{parent_path}/{name}[{n}]")
+			}
+		};
+		self.table.insert(path.clone(), SourceSpan{
+			start_tag: start_tag.0 .. start_tag.1,
+			end_tag: None,
+			extent: start_tag.0 .. start_tag.1,
+		});
+		self.stack.push((self.count, path, HashMap::new()));
+		self.count += 1;
+	}
+	/// closes the current open element with the given end tag span (`None` if self-closing)
+	pub fn close(&mut self, end_tag: Option<(usize, usize)>) {
+		if let Some((index, _, _)) = self.stack.pop() {
+			if let Some(end) = end_tag {
+				let span = self.table.get_mut(index).expect("logic error");
+				span.end_tag = Some(end.0 .. end.1);
+				span.extent = span.start_tag.start .. end.1;
+			}
+		}
+	}
+	/// returns the finished source map
+	pub fn build(self) -> SourceMapTable {
+		self.table
+	}
+}
+
+/** index of the line start offsets in the text being parsed, used to report error positions. The index is only built when the first position is requested, and at most once per parse */
+#[derive(Debug, Default)]
+pub struct LineIndex {
+	/// byte offsets of the start of each line (built lazily)
+	starts: OnceCell<Vec<usize>>
+}
+
+impl LineIndex {
+	/// forgets the index of the previously parsed text
+	pub fn clear(&mut self) {
+		self.starts.take();
+	}
+	/// returns the 1-based (line, column) of the given byte offset in `text`, where `text` must be
+	/// the same text for every call between calls to `clear()`
+	pub fn line_and_column(&self, text: &str, pos: usize) -> (usize, usize) {
+		let starts = self.starts.get_or_init(|| {
+			std::iter::once(0).chain(text.match_indices('\n').map(|(i, _)| i + 1)).collect()
+		});
+		let pos = pos.min(text.len());
+		let line = starts.partition_point(|start| *start <= pos);
+		let col = text[starts[line - 1]..pos].chars().count() + 1;
+		(line, col)
+	}
+}
+
 /** nodes in the parser tree */
 #[derive(Debug)]
 pub struct ParseTreeNode{
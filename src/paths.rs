@@ -0,0 +1,173 @@
+/*!
+Utilities for parsing the slash-separated element paths (eg `/root/mydata/properties` or
+`sound/property[2]`) used to address elements in a DOM.
+
+Each path segment is an element name (namespace prefixes are ignored, only the local name is
+matched) with an optional 1-based index in square brackets that selects the Nth sibling
+element of that name. A segment without an index selects the first matching element. Paths
+that start with `/` are absolute (the first segment must match the root element), otherwise
+they are relative to the element they are applied to.
+
+Element names follow the XML name rules (enforced when elements are created or parsed), which
+do not allow `/`, `[`, `]`, `@`, or whitespace, so element names never need to be escaped in a
+path and a path can never be confused by an element's name.
+*/
+
+use crate::errors::*;
+
+/// One step in an element path, eg `property[2]`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PathSegment {
+	/// local name of the element
+	pub name: String,
+	/// 1-based index among siblings with the same name
+	pub index: usize,
+}
+
+impl std::fmt::Display for PathSegment {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}[{}]", self.name, self.index)
+	}
+}
+
+/// A parsed element path
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ElementPath {
+	/// true if the path started with a `/`
+	pub absolute: bool,
+	/// the path segments, in order
+	pub segments: Vec<PathSegment>,
+}
+
+impl ElementPath {
+	/// Parses a path string, eg `/root/a[2]`
+	pub fn parse(path: &str) -> Result<ElementPath, KissXmlError> {
+		let absolute = path.starts_with('/');
+		let body = if absolute {&path[1..]} else {path};
+		let mut segments: Vec<PathSegment> = Vec::new();
+		let mut offset = if absolute {1} else {0};
+		for seg in body.split('/') {
+			segments.push(parse_segment(seg, offset, path)?);
+			offset += seg.len() + 1;
+		}
+		Ok(ElementPath{absolute, segments})
+	}
+}
+
+impl std::fmt::Display for ElementPath {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let joined = self.segments.iter().map(|s| s.to_string()).collect::<Vec<_>>().join("/");
+		if self.absolute {
+			write!(f, "/{}", joined)
+		} else {
+			write!(f, "{}", joined)
+		}
+	}
+}
+
+/// Checks that a path string is well formed and that every element name in it is valid, without
+/// needing a DOM. Error messages give the position of the problem within the path string.
+pub(crate) fn validate_path(path: &str) -> Result<(), KissXmlError> {
+	ElementPath::parse(path)?;
+	let absolute = path.starts_with('/');
+	let body = if absolute {&path[1..]} else {path};
+	let mut offset = if absolute {1} else {0};
+	for seg in body.split('/') {
+		let name = &seg[..seg.find('[').unwrap_or(seg.len())];
+		validate_segment_name(name, offset, path)?;
+		offset += seg.len() + 1;
+	}
+	Ok(())
+}
+
+/// checks the (optionally prefixed) element name of a path segment, where `offset` is the position of the name in `path`
+fn validate_segment_name(name: &str, offset: usize, path: &str) -> Result<(), KissXmlError> {
+	let mut name_start = true;
+	let mut seen_colon = false;
+	for (i, c) in name.char_indices() {
+		let valid = if c == ':' {
+			let ok = !name_start && !seen_colon;
+			seen_colon = true;
+			name_start = true;
+			ok
+		} else if name_start {
+			name_start = false;
+			crate::is_name_start_char(c)
+		} else {
+			crate::is_name_char(c)
+		};
+		if !valid {
+			return Err(ParsingError::new(format!(
+				"invalid path '{path}': invalid character '{c}' in element name '{name}' at position {}", offset + i
+			)).into());
+		}
+	}
+	Ok(())
+}
+
+/// parses a single segment of a path, where `offset` is the position of the segment in `path`
+fn parse_segment(seg: &str, offset: usize, path: &str) -> Result<PathSegment, KissXmlError> {
+	if seg.is_empty() {
+		return Err(ParsingError::new(format!(
+			"invalid path '{path}': empty path segment at position {offset}"
+		)).into());
+	}
+	let (name, index) = match seg.find('[') {
+		None => (seg, 1),
+		Some(i) => {
+			if !seg.ends_with(']') {
+				return Err(ParsingError::new(format!(
+					"invalid path '{path}': unclosed '[' at position {}", offset + i
+				)).into());
+			}
+			let index_str = &seg[i+1 .. seg.len()-1];
+			let index = match index_str.parse::<usize>() {
+				Ok(n) if n > 0 => n,
+				_ => return Err(ParsingError::new(format!(
+					"invalid path '{path}': '{index_str}' at position {} is not a valid index (indices start at 1)",
+					offset + i + 1
+				)).into())
+			};
+			(&seg[..i], index)
+		}
+	};
+	// namespace prefixes are ignored
+	let local_name = match name.split_once(':') {
+		None => name,
+		Some((_prefix, local)) => local
+	};
+	if local_name.is_empty() {
+		return Err(ParsingError::new(format!(
+			"invalid path '{path}': missing element name at position {offset}"
+		)).into());
+	}
+	Ok(PathSegment{name: local_name.to_string(), index})
+}
+
+/// A tree of path segments, used to resolve many paths in a single traversal
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PathTrie {
+	/// ids of the paths that end at this node
+	pub terminal: Vec<usize>,
+	/// next path segments
+	pub children: Vec<(PathSegment, PathTrie)>,
+}
+
+impl PathTrie {
+	/// adds the given path segments (identified by `id`) to this tree
+	pub fn insert(&mut self, segments: &[PathSegment], id: usize) {
+		match segments.split_first() {
+			None => self.terminal.push(id),
+			Some((first, rest)) => {
+				let pos = match self.children.iter().position(|(seg, _)| seg == first) {
+					Some(pos) => pos,
+					None => {
+						self.children.push((first.clone(), PathTrie::default()));
+						self.children.len() - 1
+					}
+				};
+				self.children[pos].1.insert(rest, id);
+			}
+		}
+	}
+}
@@ -0,0 +1,91 @@
+/*!
+Utilities for working with raw XML start tags (eg `<a href="x">`) without parsing a whole
+document, such as injecting an attribute into a tag in a template.
+
+These functions share their implementation with the kiss_xml parser and serializer, so a tag
+accepted by [parse_start_tag(...)](parse_start_tag()) is parsed exactly as the parser would
+parse it, and [render_start_tag(...)](render_start_tag()) writes attributes exactly as the
+serializer does.
+
+# Example
+```rust
+fn main() -> Result<(), kiss_xml::errors::KissXmlError> {
+	use kiss_xml::tagutil::*;
+	let (name, mut attrs, self_closing) = parse_start_tag(r#"<a href="x">"#)?;
+	attrs.push(("title".to_string(), "Say \"hi\"".to_string()));
+	assert_eq!(
+		render_start_tag(&name.to_string(), &attrs, self_closing),
+		r#"<a href="x" title="Say &quot;hi&quot;">"#
+	);
+	Ok(())
+}
+```
+*/
+
+use crate::errors::*;
+
+/// A qualified XML name, consisting of a local name and an optional namespace prefix (eg `svg:g`)
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct QName {
+	/// The namespace prefix, if any
+	pub prefix: Option<String>,
+	/// The local name
+	pub name: String
+}
+
+impl QName {
+	/// Splits a qualified name (eg `svg:g`) into its prefix and local name
+	fn split(qname: &str) -> QName {
+		match qname.split_once(':') {
+			None => QName{prefix: None, name: qname.to_string()},
+			Some((prefix, name)) => QName{prefix: Some(prefix.to_string()), name: name.to_string()}
+		}
+	}
+}
+
+/// Splits a qualified name (eg `"svg:g".parse::<QName>()`) into its prefix and local name. This
+/// never fails, because the name is not validated.
+impl std::str::FromStr for QName {
+	type Err = std::convert::Infallible;
+	fn from_str(qname: &str) -> Result<Self, Self::Err> {
+		Ok(QName::split(qname))
+	}
+}
+
+impl std::fmt::Display for QName {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match &self.prefix {
+			None => write!(f, "{}", self.name),
+			Some(prefix) => write!(f, "{}:{}", prefix, self.name)
+		}
+	}
+}
+
+/// A parsed start tag: the tag name, the attributes in the order they appear in the tag, and
+/// whether the tag is self-closing (see [parse_start_tag(...)](parse_start_tag()))
+pub type StartTag = (QName, Vec<(String, String)>, bool);
+
+/**
+Parses a start tag (eg `<svg:path d="M 3,58 Z" id='triangle'/>`), returning the tag name, the attributes in the order they appear in the tag (with their values unescaped), and whether the tag is self-closing. Returns an error result if the tag is malformed or is not a start tag (eg a closing tag or a comment).
+ */
+pub fn parse_start_tag(tag: &str) -> Result<StartTag, KissXmlError> {
+	let tag = tag.trim();
+	if tag.starts_with("</") || tag.starts_with("<!") || tag.starts_with("<?") {
+		return Err(ParsingError::new(format!("'{tag}' is not a start tag")).into());
+	}
+	crate::check_element_tag(tag)?;
+	let (name, attrs) = crate::split_tag_content(crate::strip_tag(tag).as_str())
+		.map_err(|msg| ParsingError::new(format!("invalid start tag '{tag}': {msg}")))?;
+	Ok((QName::split(name.as_str()), attrs, tag.ends_with("/>")))
+}
+
+/**
+Writes a start tag with the given (qualified) name and attributes, escaping the attribute values the same way as the kiss_xml serializer. The attributes are written in the given order. Note that the name and attribute names are not validated.
+ */
+pub fn render_start_tag<K: AsRef<str>, V: AsRef<str>>(name: &str, attributes: &[(K, V)], self_closing: bool) -> String {
+	let mut out = String::from("<");
+	out.push_str(name);
+	crate::write_attributes(&mut out, attributes.iter().map(|(k, v)| (k.as_ref(), v.as_ref())), false);
+	out.push_str(match self_closing {true => "/>", false => ">"});
+	out
+}
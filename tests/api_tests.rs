@@ -11,8 +11,20 @@ fn test_xml_escapes() {
 	assert_eq!(kiss_xml::unescape(escaped), unescaped, "Incorrect unescaping of XML reserved characters");
 	assert_eq!(kiss_xml::text_escape(unescaped), escaped_text, "Incorrect escaping of XML reserved characters");
 	assert_eq!(kiss_xml::attribute_escape(unescaped), escaped_attribute, "Incorrect escaping of XML reserved characters");
-	assert_eq!(kiss_xml::unescape("&#263c;"), "☼", "Incorrect unescaping of unicode character #236c '☼'");
-	assert_eq!(kiss_xml::unescape("&#263C;"), "☼", "Incorrect unescaping of unicode character #236C '☼'");
+	assert_eq!(kiss_xml::unescape("&#x263c;"), "☼", "Incorrect unescaping of unicode character #236c '☼'");
+	assert_eq!(kiss_xml::unescape("&#x263C;"), "☼", "Incorrect unescaping of unicode character #236C '☼'");
+	assert_eq!(kiss_xml::unescape("&#9788;"), "☼", "Incorrect unescaping of unicode character #9788 '☼'");
+}
+
+#[test]
+fn test_unescape_character_references() {
+	use kiss_xml;
+	// &#N; is decimal and &#xN; is hexadecimal
+	assert_eq!(kiss_xml::unescape("&#65;&#x41;&#10;&#xA;"), "AA\n\n");
+	assert_eq!(kiss_xml::unescape("&#20;"), "\u{14}");
+	// hexadecimal digits without the x are not a valid reference
+	assert_eq!(kiss_xml::unescape("&#1F;"), "&#1F;");
+	assert_eq!(kiss_xml::unescape("&#263c;"), "&#263c;");
 }
 
 fn sample_xml_1() -> &'static str {
@@ -435,3 +447,1827 @@ r#"<html>
 	println!("{}", doc.to_string());
 }
 
+
+#[test]
+fn test_append_conveniences() {
+	use kiss_xml;
+	use kiss_xml::dom::*;
+	use std::collections::HashMap;
+	let xml = r#"<html>
+	<!-- this is a comment -->
+	<body>
+		TODO: content here
+	</body>
+</html>"#;
+	// original approach
+	let mut doc1 = kiss_xml::parse_str(xml).unwrap();
+	doc1.root_element_mut().remove_all(&|n| n.is_comment());
+	let body = doc1.root_element_mut().first_element_by_name_mut("body").unwrap();
+	body.remove_all(&|_| true);
+	body.append_all(
+		vec![
+			Element::new_with_text("h1", "Chapter 1").unwrap().boxed(),
+			Comment::new("Note: there is only one chapter").unwrap().boxed(),
+			Element::new_with_children("p", vec![
+				Text::new("Once upon a time, there was a little ").boxed(),
+				Element::new_with_attributes_and_text(
+					"a",
+					HashMap::from([("href","https://en.wikipedia.org/wiki/Gnome")]),
+					"gnome"
+				).unwrap().boxed(),
+				Text::new(" who lived in a walnut tree...").boxed()
+			]).unwrap().boxed(),
+			CData::new("<raw/>").unwrap().boxed()
+		]
+	);
+	// with the append_* conveniences
+	let mut doc2 = kiss_xml::parse_str(xml).unwrap();
+	doc2.root_element_mut().remove_all(&|n| n.is_comment());
+	let body = doc2.root_element_mut().first_element_by_name_mut("body").unwrap();
+	body.remove_all(&|_| true);
+	body.append_element("h1").unwrap().append_text("Chapter 1");
+	body.append_comment("Note: there is only one chapter").unwrap();
+	let p = body.append_element("p").unwrap();
+	p.append_text("Once upon a time, ");
+	p.append_text("there was a little ");
+	let a = p.append_element("a").unwrap();
+	a.set_attr("href", "https://en.wikipedia.org/wiki/Gnome").unwrap();
+	a.append_text("gnome");
+	p.append_text(" who lived in a walnut tree...");
+	body.append_cdata("<raw/>").unwrap();
+	assert_eq!(doc1.to_string(), doc2.to_string());
+	assert_eq!(doc1, doc2);
+	// validation
+	let mut e = Element::new_from_name("e").unwrap();
+	assert!(e.append_comment("a --> b").is_err());
+	assert!(e.append_cdata("a ]]> b").is_err());
+	assert!(e.append_element("bad name").is_err());
+	assert_eq!(e.children().count(), 0);
+	// namespace propagation
+	let mut doc = kiss_xml::parse_str(r#"<root xmlns="internal://a"/>"#).unwrap();
+	let child = doc.root_element_mut().append_element("child").unwrap();
+	assert_eq!(child.namespace(), Some("internal://a".to_string()));
+}
+
+#[test]
+fn test_children_between() {
+	use kiss_xml;
+	use kiss_xml::dom::*;
+	let doc = kiss_xml::parse_str(r#"<html>
+	<body>
+		<h1>Introduction</h1>
+		<p>Welcome</p>
+		<!--intro ends here-->
+		<p>Read on</p>
+		<h1>Chapter 1</h1>
+		<p>Once upon a time</p>
+		<h1>Chapter 2</h1>
+		<h1>Epilogue</h1>
+		<p>The end</p>
+		<ul><li>notes</li></ul>
+	</body>
+</html>"#).unwrap();
+	let body = doc.root_element().first_element_by_name("body").unwrap();
+	// split the body into header-delimited sections
+	let header_count = body.elements_by_name("h1").count();
+	let mut sections: Vec<Vec<String>> = Vec::new();
+	for section in 0..header_count {
+		let mut seen = 0;
+		let is_nth_h1 = |n: &Box<dyn Node>| {
+			if n.is_element() && n.as_element().unwrap().name() == "h1" {
+				seen += 1;
+				seen == section + 1
+			} else { false }
+		};
+		let is_h1 = |n: &Box<dyn Node>| n.is_element() && n.as_element().unwrap().name() == "h1";
+		sections.push(body.children_between(is_nth_h1, is_h1).map(|n| n.text()).collect());
+	}
+	assert_eq!(sections, vec![
+		vec!["Welcome".to_string(), "intro ends here".to_string(), "Read on".to_string()],
+		vec!["Once upon a time".to_string()],
+		vec![],
+		vec!["The end".to_string(), "notes".to_string()],
+	], "children not correctly grouped by <h1> headers");
+	// no start match means nothing is returned
+	assert_eq!(body.children_between(|n| n.is_cdata(), |_| true).count(), 0, "should be empty when start predicate never matches");
+	// element-only variant skips the comment
+	let intro = body.elements_between(|e| e.name() == "h1", |e| e.name() == "h1")
+		.map(|e| e.text()).collect::<Vec<_>>();
+	assert_eq!(intro, vec!["Welcome".to_string(), "Read on".to_string()], "wrong elements in first section");
+	let epilogue = body.elements_between(|e| e.text() == "Epilogue", |e| e.name() == "h1")
+		.map(|e| e.name()).collect::<Vec<_>>();
+	assert_eq!(epilogue, vec!["p".to_string(), "ul".to_string()], "last section should run to the end");
+}
+
+#[test]
+fn test_source_spans() {
+	use kiss_xml;
+	let xml = sample_xml_2();
+	// not recorded by default
+	assert!(kiss_xml::parse_str(xml).unwrap().source_spans().is_none(), "source spans should only be recorded when requested");
+	let options = kiss_xml::ParseOptions{source_spans: true, ..Default::default()};
+	let doc = kiss_xml::parse_str_with_options(xml, &options).unwrap();
+	let spans = doc.source_spans().expect("source spans not recorded");
+	assert_eq!(spans.len(), 9, "wrong number of elements in source map");
+	let span = spans.get("/root/mydata/properties").unwrap().expect("no span for /root/mydata/properties");
+	assert_eq!(&xml[span.extent.clone()], r#"<properties>
+			<property name="a" value="1"/>
+			<property name="b" value="2"/>
+		</properties>"#, "span does not match the subtree's source text");
+	assert_eq!(&xml[span.start_tag.clone()], "<properties>");
+	assert_eq!(&xml[span.end_tag.clone().unwrap()], "</properties>");
+	// indexed and self-closing elements
+	let span = spans.get("/root/mydata/properties/property[2]").unwrap().unwrap();
+	assert_eq!(&xml[span.extent.clone()], r#"<property name="b" value="2"/>"#);
+	assert!(span.end_tag.is_none(), "self-closing element should have no end tag");
+	assert_eq!(spans.get("/root[1]/mydata[1]/other[2]").unwrap(), spans.iter().last().map(|(_, s)| s));
+	assert!(spans.get("/root/mydata/other[3]").unwrap().is_none(), "non-existent element should not have a span");
+	assert!(spans.get("/root//other").is_err(), "invalid path should be an error");
+	// root span covers the whole root element
+	let span = spans.get("/root").unwrap().unwrap();
+	assert_eq!(&xml[span.extent.clone()], xml[xml.find("<root").unwrap()..].trim_end());
+}
+
+#[test]
+fn test_xsi_nil() {
+	use kiss_xml;
+	use kiss_xml::dom::Node;
+	// conventional prefix
+	let mut doc = kiss_xml::parse_str(r#"<root xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+	<value xsi:nil="true"/>
+	<other xsi:nil="1"/>
+	<present>5</present>
+	<fake nil="true"/>
+</root>"#).unwrap();
+	let root = doc.root_element();
+	assert!(root.first_element_by_name("value").unwrap().is_nil(), "xsi:nil=\"true\" not detected");
+	assert!(root.first_element_by_name("other").unwrap().is_nil(), "xsi:nil=\"1\" not detected");
+	assert!(!root.first_element_by_name("present").unwrap().is_nil(), "element without xsi:nil should not be nil");
+	assert!(!root.first_element_by_name("fake").unwrap().is_nil(), "un-prefixed nil attribute is not xsi:nil");
+	doc.root_element_mut().first_element_by_name_mut("value").unwrap().set_nil(false);
+	doc.root_element_mut().first_element_by_name_mut("present").unwrap().set_nil(true);
+	assert_eq!(doc.root_element().to_string_with_indent("\t"), r#"<root xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+	<value/>
+	<other xsi:nil="1"/>
+	<present xsi:nil="true">5</present>
+	<fake nil="true"/>
+</root>"#, "incorrect serialization after set_nil()");
+	// renamed prefix
+	let mut doc = kiss_xml::parse_str(r#"<root xmlns:i="http://www.w3.org/2001/XMLSchema-instance" xmlns:xsi="internal://not/xsi">
+	<value i:nil="true"/>
+	<wrong xsi:nil="true"/>
+	<empty/>
+</root>"#).unwrap();
+	assert!(doc.root_element().first_element_by_name("value").unwrap().is_nil(), "renamed xsi prefix not resolved");
+	assert!(!doc.root_element().first_element_by_name("wrong").unwrap().is_nil(), "xsi prefix bound to another namespace should not count");
+	doc.root_element_mut().first_element_by_name_mut("empty").unwrap().set_nil(true);
+	assert_eq!(
+		doc.root_element().first_element_by_name("empty").unwrap().to_string(),
+		r#"<empty i:nil="true"/>"#, "set_nil() should reuse the in-scope prefix"
+	);
+	// missing declaration
+	let mut doc = kiss_xml::parse_str(r#"<root><value>x</value></root>"#).unwrap();
+	let value = doc.root_element_mut().first_element_by_name_mut("value").unwrap();
+	value.clear_children();
+	value.set_nil(true);
+	assert!(value.is_nil(), "set_nil(true) did not mark element as nil");
+	assert_eq!(
+		doc.to_string_with_indent("\t"),
+		r#"<root>
+	<value xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:nil="true"/>
+</root>"#, "set_nil() should declare the xsi namespace when it is not in scope"
+	);
+	let reparsed = kiss_xml::parse_str(doc.to_string()).unwrap();
+	assert!(reparsed.root_element().first_element_by_name("value").unwrap().is_nil(), "nil lost in round trip");
+}
+
+#[test]
+fn test_attribute_bulk_edits() {
+	use kiss_xml;
+	use kiss_xml::dom::*;
+	let mut doc = kiss_xml::parse_str(r#"<root>
+	<div id=" main " class="  big box" data-x="1" data-y="2" title="Hello   "/>
+</root>"#).unwrap();
+	let div = doc.root_element_mut().first_element_by_name_mut("div").unwrap();
+	// trim all values
+	div.attributes_entries_mut(|_, v| *v = v.trim().to_string());
+	assert_eq!(div.get_attr("id").unwrap(), "main");
+	assert_eq!(div.get_attr("class").unwrap(), "big box");
+	assert_eq!(div.get_attr("title").unwrap(), "Hello");
+	// drop all data-* attributes
+	let removed = div.retain_attrs(|k, _| !k.starts_with("data-"));
+	assert_eq!(removed, 2, "wrong number of removed attributes");
+	assert_eq!(div.to_string(), r#"<div class="big box" id="main" title="Hello"/>"#);
+	// namespace context stays consistent when xmlns:x is retained
+	let mut e1 = Element::new_with_attributes("e", std::collections::HashMap::from([
+		("xmlns:x", " internal://x "), ("x:a", "1"), ("b", "2")
+	])).unwrap();
+	let mut e2 = e1.clone();
+	e1.attributes_entries_mut(|_, v| *v = v.trim().to_string());
+	assert_eq!(e1.retain_attrs(|k, _| k != "b"), 1);
+	e1.append(Element::new::<&str, &str>("child", None, None, None, Some("x".into()), None).unwrap());
+	assert_eq!(
+		e1.first_element_by_name("child").unwrap().namespace().unwrap(), "internal://x",
+		"retained (and trimmed) xmlns:x should still be resolvable"
+	);
+	// ...and when xmlns:x is dropped
+	assert_eq!(e2.retain_attrs(|k, _| !k.starts_with("xmlns")), 1);
+	assert!(e2.namespace_prefixes().is_none());
+	e2.append(Element::new::<&str, &str>("child", None, None, None, Some("x".into()), None).unwrap());
+	assert!(
+		e2.first_element_by_name("child").unwrap().namespace().is_none(),
+		"dropped xmlns:x should no longer be resolvable"
+	);
+	// set_attr and remove_attr use the same rules
+	e2.set_attr("xmlns:x", "internal://y").unwrap();
+	e2.append(Element::new::<&str, &str>("child2", None, None, None, Some("x".into()), None).unwrap());
+	assert_eq!(e2.first_element_by_name("child2").unwrap().namespace().unwrap(), "internal://y");
+	e2.remove_attr("xmlns:x");
+	e2.append(Element::new::<&str, &str>("child3", None, None, None, Some("x".into()), None).unwrap());
+	assert!(e2.first_element_by_name("child3").unwrap().namespace().is_none());
+	// existing children follow a retained (and trimmed) xmlns:x...
+	let source = r#"<root xmlns:x=" internal://x " data-x="1"><x:child><x:leaf/></x:child></root>"#;
+	let mut doc = kiss_xml::parse_str(source).unwrap();
+	doc.root_element_mut().attributes_entries_mut(|_, v| *v = v.trim().to_string());
+	assert_eq!(doc.root_element_mut().retain_attrs(|k, _| !k.starts_with("data-")), 1);
+	let leaf = doc.root_element().search_elements_by_name("leaf").next().unwrap();
+	assert_eq!(leaf.namespace().unwrap(), "internal://x");
+	assert_eq!(kiss_xml::parse_str(doc.to_string()).unwrap(), doc);
+	// ...and a dropped one
+	let mut doc = kiss_xml::parse_str(source).unwrap();
+	assert_eq!(doc.root_element_mut().retain_attrs(|k, _| !k.starts_with("xmlns")), 1);
+	for elem in doc.root_element().search_elements(|_| true) {
+		assert!(elem.namespace().is_none(), "dropped xmlns:x should no longer apply to <{}>", elem.tag_name());
+	}
+	// changing a declaration changes the namespace of the existing descendants
+	let mut doc = kiss_xml::parse_str(r#"<root xmlns="internal://a" xmlns:x="internal://x"><x:child><leaf/></x:child></root>"#).unwrap();
+	doc.root_element_mut().set_attr("xmlns:x", "internal://y").unwrap();
+	doc.root_element_mut().set_attr("xmlns", "internal://b").unwrap();
+	let child = doc.root_element().first_element_by_name("child").unwrap();
+	assert_eq!(child.namespace().unwrap(), "internal://y");
+	assert_eq!(child.first_element_by_name("leaf").unwrap().namespace().unwrap(), "internal://b");
+	assert_eq!(kiss_xml::parse_str(doc.to_string()).unwrap(), doc);
+	// removing a declaration restores the binding inherited from the parent
+	let mut doc = kiss_xml::parse_str(r#"<root xmlns:x="internal://a"><x:c xmlns:x="internal://b"><x:d/></x:c></root>"#).unwrap();
+	let c = doc.root_element_mut().first_element_by_name_mut("c").unwrap();
+	assert_eq!(c.remove_attr("xmlns:x").unwrap(), "internal://b");
+	assert_eq!(c.namespace().unwrap(), "internal://a");
+	assert_eq!(c.first_element_by_name("d").unwrap().namespace().unwrap(), "internal://a");
+	assert_eq!(kiss_xml::parse_str(doc.to_string()).unwrap(), doc);
+	let mut doc = kiss_xml::parse_str(r#"<root xmlns="internal://a"><c xmlns="internal://b"><d/></c></root>"#).unwrap();
+	let c = doc.root_element_mut().first_element_by_name_mut("c").unwrap();
+	c.remove_attr("xmlns");
+	assert_eq!(c.namespace().unwrap(), "internal://a");
+	assert_eq!(c.first_element_by_name("d").unwrap().namespace().unwrap(), "internal://a");
+	assert_eq!(kiss_xml::parse_str(doc.to_string()).unwrap(), doc);
+}
+
+#[test]
+fn test_ensure_path() {
+	use kiss_xml;
+	use kiss_xml::dom::*;
+	let mut doc = Document::new(Element::new_from_name("config").unwrap());
+	doc.root_element_mut().ensure_path("sound/effects/reverb").unwrap().set_attr("level", "3").unwrap();
+	let reverb = doc.root_element_mut().ensure_path("sound/effects/reverb").unwrap();
+	assert_eq!(reverb.get_attr("level").unwrap(), "3", "ensure_path() did not return the existing element");
+	reverb.set_text("on");
+	doc.root_element_mut().ensure_child("sound").unwrap().ensure_child("volume").unwrap().set_text("11");
+	doc.root_element_mut().ensure_path("sound/property[2]").unwrap().set_attr("name", "b").unwrap();
+	assert_eq!(doc.root_element().to_string_with_indent("\t"), r#"<config>
+	<sound>
+		<effects>
+			<reverb level="3">on</reverb>
+		</effects>
+		<volume>11</volume>
+		<property/>
+		<property name="b"/>
+	</sound>
+</config>"#, "ensure_path() created the wrong elements");
+	assert_eq!(doc.root_element().search_elements_by_name("reverb").count(), 1, "ensure_path() duplicated elements");
+	// namespace inheritance
+	let mut doc = kiss_xml::parse_str(r#"<config xmlns="internal://ns/a"/>"#).unwrap();
+	let e = doc.root_element_mut().ensure_path("a/b").unwrap();
+	assert_eq!(e.namespace().unwrap(), "internal://ns/a", "created element did not inherit namespace");
+	// errors
+	assert!(doc.root_element_mut().ensure_path("a//b").is_err(), "empty path segment should be an error");
+	assert!(doc.root_element_mut().ensure_path("/config/a").is_err(), "absolute path should be an error");
+	assert!(doc.root_element_mut().ensure_child("1abc").is_err(), "invalid name should be an error");
+}
+
+#[test]
+fn test_document_events() {
+	use kiss_xml;
+	use kiss_xml::dom::*;
+	use std::collections::HashMap;
+	let doc = kiss_xml::parse_str(sample_xml_1()).unwrap();
+	let no_attrs: HashMap<String, String> = HashMap::new();
+	let signer = HashMap::from([("signer".to_string(), "Jani Jane".to_string())]);
+	let start = |name| XmlEvent::StartElement{name, prefix: None, namespace: None, attributes: &no_attrs};
+	let end = |name| XmlEvent::EndElement{name, prefix: None};
+	let events = doc.events().collect::<Vec<_>>();
+	assert!(matches!(events[0], XmlEvent::Declaration(_)), "first event should be the declaration");
+	assert!(matches!(events[1], XmlEvent::Dtd(_)), "second event should be the DTD");
+	assert_eq!(events[2..], vec![
+		start("note"),
+		XmlEvent::Comment(" Note: commented out the following elements:
+	<region>somewhere</region>
+	<language>ISL-2108</language>
+	"),
+		start("to"), XmlEvent::Text("Tove"), end("to"),
+		start("from"), XmlEvent::Text("Jani"), end("from"),
+		start("heading"), XmlEvent::Text("Reminder"), end("heading"),
+		start("paragraph"), XmlEvent::Text("Don't forget "),
+			start("b"), XmlEvent::Text("me"), end("b"),
+			XmlEvent::Text(" this weekend!"),
+		end("paragraph"),
+		start("paragraph"), XmlEvent::Text(" - Jani"), end("paragraph"),
+		start("footer"), XmlEvent::Text("&writer;&nbsp;&copyright;"), end("footer"),
+		XmlEvent::StartElement{name: "signed", prefix: None, namespace: None, attributes: &signer},
+		end("signed"),
+		end("note"),
+	][..], "wrong sequence of events");
+	// every start has a matching end, and the events cover every node in the DOM
+	let starts = events.iter().filter(|e| matches!(e, XmlEvent::StartElement{..})).count();
+	let ends = events.iter().filter(|e| matches!(e, XmlEvent::EndElement{..})).count();
+	assert_eq!(starts, ends);
+	assert_eq!(starts, 1 + doc.root_element().search(|n| n.is_element()).count());
+	// namespaces and CDATA
+	let doc = kiss_xml::parse_str(r#"<img:root xmlns:img="internal://ns/a"><img:data><![CDATA[<raw>]]></img:data></img:root>"#).unwrap();
+	let events = doc.root_element().first_element_by_name("data").unwrap().events().collect::<Vec<_>>();
+	assert_eq!(events, vec![
+		XmlEvent::StartElement{name: "data", prefix: Some("img"), namespace: Some("internal://ns/a"), attributes: &no_attrs},
+		XmlEvent::CData("<raw>"),
+		XmlEvent::EndElement{name: "data", prefix: Some("img")},
+	]);
+}
+
+#[test]
+fn test_doctype_round_trip() {
+	use kiss_xml;
+	use kiss_xml::dom::*;
+	// multi-line internal subset
+	let doctype = r#"<!DOCTYPE note [
+<!ENTITY ignore "kiss-xml ignores DOCTYPE stuff">
+<!ENTITY nbsp "&#xA0;">
+<!ENTITY writer "Writer: Donald Duck.">
+<!ENTITY copyright "Copyright: W3Schools.">
+]>"#;
+	let doc = kiss_xml::parse_str(sample_xml_1()).unwrap();
+	let dtd = doc.doctype_defs().next().unwrap();
+	assert_eq!(dtd.to_string(), doctype, "DOCTYPE not preserved verbatim");
+	assert_eq!(dtd.name(), "note");
+	assert!(dtd.external_id().is_none());
+	assert!(dtd.internal_subset().unwrap().contains(r#"<!ENTITY nbsp "&#xA0;">"#));
+	let out = doc.to_string_with_indent("\t");
+	assert!(out.starts_with(&format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{doctype}\n<note>")), "DOCTYPE not serialized verbatim:\n{out}");
+	assert_eq!(kiss_xml::parse_str(out).unwrap(), doc, "DOCTYPE changed in round trip");
+	// comment containing '>' and quotes inside the internal subset
+	let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE greeting SYSTEM "hello.dtd" [
+  <!-- don't be fooled: a > b -- and "quotes" -->
+  <!ENTITY gt-ish "a > b">
+  %params;
+]>
+<greeting>Hello!</greeting>
+"#;
+	let doc = kiss_xml::parse_str(xml).unwrap();
+	assert_eq!(doc.to_string(), xml, "DOCTYPE with comments did not round-trip byte-for-byte");
+	let dtd = doc.doctype_defs().next().unwrap();
+	assert_eq!(dtd.name(), "greeting");
+	assert_eq!(dtd.external_id(), Some(r#"SYSTEM "hello.dtd""#));
+	assert_eq!(doc.root_element().text(), "Hello!");
+	// modifying the structured fields regenerates the DOCTYPE
+	let mut dtd = dtd.clone();
+	dtd.set_internal_subset(None);
+	assert!(dtd.raw().is_none());
+	assert_eq!(dtd.to_string(), r#"<!DOCTYPE greeting SYSTEM "hello.dtd">"#);
+	assert_eq!(DTD::new("html", None, None).to_string(), "<!DOCTYPE html>");
+	assert_eq!(DTD::from_string("<!DOCTYPE html>").unwrap().name(), "html");
+	assert!(DTD::from_string("<!DOCTYPE>").is_err());
+}
+
+#[test]
+fn test_tagutil() {
+	use kiss_xml::tagutil::*;
+	// single and double quotes, prefixes, escapes
+	let (name, attrs, self_closing) = parse_start_tag(r#"<svg:path d="M 3,58 Z" id='tri&amp;angle'/>"#).unwrap();
+	assert_eq!(name, QName{prefix: Some("svg".to_string()), name: "path".to_string()});
+	assert_eq!(name.to_string(), "svg:path");
+	assert_eq!("svg:path".parse::<QName>().unwrap(), name);
+	assert_eq!(attrs, vec![
+		("d".to_string(), "M 3,58 Z".to_string()),
+		("id".to_string(), "tri&angle".to_string())
+	]);
+	assert!(self_closing);
+	let rendered = render_start_tag(&name.to_string(), &attrs, self_closing);
+	assert_eq!(rendered, r#"<svg:path d="M 3,58 Z" id="tri&amp;angle"/>"#);
+	assert_eq!(parse_start_tag(rendered.as_str()).unwrap(), (name, attrs, true));
+	// quotes inside values
+	let (_, attrs, _) = parse_start_tag(r#"<a title='say "hi"' alt="it's">"#).unwrap();
+	assert_eq!(attrs[0].1, "say \"hi\"");
+	assert_eq!(attrs[1].1, "it's");
+	let rendered = render_start_tag("a", &attrs, false);
+	assert_eq!(parse_start_tag(rendered.as_str()).unwrap().1, attrs);
+	// multi-line attributes
+	let (name, attrs, self_closing) = parse_start_tag("<property\n\tname=\"volume\"\n\tvalue=\"11\"\n>").unwrap();
+	assert_eq!(name.prefix, None);
+	assert_eq!(name.name, "property");
+	assert_eq!(attrs.len(), 2);
+	assert!(!self_closing);
+	// consistent with the parser
+	let tag = r#"<x:item xmlns:x="internal://x" note="a &lt; b" empty="">"#;
+	let doc = kiss_xml::parse_str(format!("{tag}</x:item>")).unwrap();
+	let (_, attrs, _) = parse_start_tag(tag).unwrap();
+	for (k, v) in &attrs {
+		assert_eq!(doc.root_element().get_attr(k), Some(v), "parser and tagutil disagree on attribute {k}");
+	}
+	// malformed tags
+	assert!(parse_start_tag("</a>").is_err());
+	assert!(parse_start_tag("<!-- a -->").is_err());
+	assert!(parse_start_tag("<a b>").is_err());
+	assert!(parse_start_tag("<a b=c>").is_err());
+	assert!(parse_start_tag(r#"<a b="c>"#).is_err());
+	assert!(parse_start_tag("<>").is_err());
+	assert!(parse_start_tag("a").is_err());
+}
+
+#[test]
+fn test_attribute_order() {
+	use std::cmp::Ordering;
+	use kiss_xml::attribute_order;
+	let kv = |k: &str, v: &str| (k.to_string(), v.to_string());
+	let cmp = |a: &(String, String), b: &(String, String)| attribute_order(&(&a.0, &a.1), &(&b.0, &b.1));
+	let xmlns = kv("xmlns", "internal://default");
+	let xmlns_a = kv("xmlns:a", "internal://a");
+	let xmlns_b = kv("xmlns:b", "internal://b");
+	let xmlnsfoo = kv("xmlnsfoo", "1");
+	let alpha = kv("alpha", "2");
+	let zeta = kv("zeta", "3");
+	// default namespace first, then prefixed namespaces by prefix
+	assert_eq!(cmp(&xmlns, &xmlns_a), Ordering::Less);
+	assert_eq!(cmp(&xmlns_b, &xmlns), Ordering::Greater);
+	assert_eq!(cmp(&xmlns_a, &xmlns_b), Ordering::Less);
+	assert_eq!(cmp(&xmlns_b, &alpha), Ordering::Less);
+	// xmlnsfoo is an ordinary attribute
+	assert_eq!(cmp(&xmlns_b, &xmlnsfoo), Ordering::Less);
+	assert_eq!(cmp(&alpha, &xmlnsfoo), Ordering::Less);
+	assert_eq!(cmp(&xmlnsfoo, &zeta), Ordering::Less);
+	// equal keys are ordered by value
+	assert_eq!(cmp(&kv("id", "1"), &kv("id", "2")), Ordering::Less);
+	assert_eq!(cmp(&kv("id", "2"), &kv("id", "1")), Ordering::Greater);
+	assert_eq!(cmp(&kv("id", "1"), &kv("id", "1")), Ordering::Equal);
+	// every arrangement sorts the same way
+	let expected = vec![&xmlns, &xmlns_a, &xmlns_b, &alpha, &xmlnsfoo, &zeta];
+	let mut attrs = expected.clone();
+	for i in 0..attrs.len() {
+		attrs.rotate_left(1);
+		attrs.swap(0, i);
+		let mut sorted: Vec<(&String, &String)> = attrs.iter().map(|t| (&t.0, &t.1)).collect();
+		sorted.sort_by(attribute_order);
+		let sorted: Vec<&String> = sorted.into_iter().map(|(k, _)| k).collect();
+		assert_eq!(sorted, expected.iter().map(|t| &t.0).collect::<Vec<_>>());
+	}
+	// end-to-end serialization
+	let unsorted = r#"<root zeta="3" xmlnsfoo="1" xmlns:b="internal://b" alpha="2" xmlns="internal://default" xmlns:a="internal://a"/>"#;
+	let sorted = r#"<root xmlns="internal://default" xmlns:a="internal://a" xmlns:b="internal://b" alpha="2" xmlnsfoo="1" zeta="3"/>"#;
+	assert_eq!(kiss_xml::parse_str(unsorted).unwrap().root_element().to_string(), sorted);
+}
+
+#[test]
+fn test_opaque_markup() {
+	use kiss_xml::dom::{MarkupPlacement, Node};
+	let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!ENTITY copy "&#xA9;">
+<!DOCTYPE note SYSTEM "note.dtd">
+<!FOO bar "a > b">
+<?xml-stylesheet type="text/xsl" href="style.xsl"?>
+<note>
+  <to>Tove</to>
+</note>
+<?vendor-trailer checksum="1234"?>
+"#;
+	let doc = kiss_xml::parse_str(xml).unwrap();
+	assert_eq!(doc.to_string(), xml, "opaque markup did not round-trip byte-for-byte");
+	let markup: Vec<_> = doc.opaque_markup().collect();
+	assert_eq!(markup.len(), 4);
+	assert_eq!(markup[0].raw(), r#"<!ENTITY copy "&#xA9;">"#);
+	assert_eq!(markup[0].placement(), MarkupPlacement::Prologue(0));
+	assert_eq!(markup[0].source_offset(), xml.find("<!ENTITY").unwrap());
+	assert_eq!(markup[1].raw(), r#"<!FOO bar "a > b">"#);
+	assert_eq!(markup[1].placement(), MarkupPlacement::Prologue(1));
+	assert_eq!(markup[2].placement(), MarkupPlacement::Prologue(1));
+	assert_eq!(markup[3].raw(), r#"<?vendor-trailer checksum="1234"?>"#);
+	assert_eq!(markup[3].placement(), MarkupPlacement::Epilogue);
+	assert_eq!(doc.root_element().first_element_by_name("to").unwrap().text(), "Tove");
+	assert_eq!(kiss_xml::parse_str(doc.to_string()).unwrap(), doc);
+	// opaque markup only outside the root element
+	assert!(kiss_xml::parse_str("<root><!FOO bar></root>").is_err());
+	// self-closing root with epilogue
+	let xml = "<root/>\n<!FOO bar>\n";
+	assert_eq!(kiss_xml::parse_str(xml).unwrap().to_string(), xml);
+	// removing it
+	let mut doc = kiss_xml::parse_str(xml).unwrap();
+	doc.clear_opaque_markup();
+	assert_eq!(doc.to_string(), "<root/>\n");
+}
+
+#[test]
+fn test_root_accessors() {
+	use kiss_xml::dom::*;
+	let mut doc = kiss_xml::parse_str(r#"<config xmlns="internal://config" version="2"><a/></config>"#).unwrap();
+	assert_eq!(doc.root_name(), "config");
+	assert_eq!(doc.root_namespace(), Some("internal://config".to_string()));
+	assert_eq!(doc.root_attr("version"), Some(&"2".to_string()));
+	assert_eq!(doc.root_attr("missing"), None);
+	doc.set_root_attr("version", "3").unwrap();
+	assert_eq!(doc.root_element().get_attr("version"), Some(&"3".to_string()));
+	assert!(doc.set_root_attr("bad name", "x").is_err());
+	// declaring a prefix on the root makes it resolvable for elements appended later
+	doc.set_root_attr("xmlns:x", "internal://x").unwrap();
+	doc.root_element_mut().append(
+		Element::new::<String, String>("item", None, None, None, Some("x".to_string()), None).unwrap()
+	);
+	let item = doc.root_element().first_element_by_name("item").unwrap();
+	assert_eq!(item.namespace(), Some("internal://x".to_string()));
+	assert_eq!(item.namespace_prefix(), Some("x".to_string()));
+	let reparsed = kiss_xml::parse_str(doc.to_string()).unwrap();
+	assert_eq!(
+		reparsed.root_element().first_element_by_name("item").unwrap().namespace(),
+		Some("internal://x".to_string())
+	);
+	let doc = kiss_xml::parse_str("<root/>").unwrap();
+	assert_eq!(doc.root_namespace(), None);
+}
+
+#[test]
+fn test_remove_paths() {
+	let mut doc = kiss_xml::parse_str(sample_xml_2()).unwrap();
+	let count = doc.remove_paths(&[
+		"/root/mydata/properties",
+		"/root/mydata/properties/property[2]",
+		"mydata/other[2]",
+		"/root/mydata/desc",
+	]).unwrap();
+	assert_eq!(count, 3);
+	assert_eq!(doc.to_string_with_indent("\t"), r#"<?xml version="1.0" encoding="UTF-8"?>
+<root author="some dude">
+	<!--comment-->
+	<mydata>
+		<meta>My metadata goes here</meta>
+		<other/>
+	</mydata>
+</root>
+"#);
+	// misses
+	let mut doc = kiss_xml::parse_str(sample_xml_2()).unwrap();
+	let (count, misses) = doc.remove_paths_with_misses(&[
+		"/root/mydata/other[3]", "/other/mydata", "mydata/other", "mydata/other"
+	]).unwrap();
+	assert_eq!(count, 1);
+	assert_eq!(misses, vec!["/root/mydata/other[3]".to_string(), "/other/mydata".to_string()]);
+	assert_eq!(doc.element_at_path("/root/mydata/other[2]").unwrap(), None);
+	assert!(doc.element_at_path("/root/mydata/other").unwrap().is_some());
+	// errors leave the document unmodified
+	let mut doc = kiss_xml::parse_str(sample_xml_2()).unwrap();
+	assert!(doc.remove_paths(&["/root/mydata/meta", "/root"]).is_err());
+	assert!(doc.remove_paths(&["/root/mydata/meta", "mydata//meta"]).is_err());
+	assert_eq!(doc, kiss_xml::parse_str(sample_xml_2()).unwrap());
+}
+
+#[test]
+fn test_extract_subtree() {
+	use kiss_xml::dom::*;
+	let xml = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<!-- Blue triangle SVG graphic -->
+<svg width="100" height="100" viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" xmlns:unused="internal://unused">
+  <g id="layer1" inkscape:label="Layer 1">
+    <path style="fill:#00a6c2;fill-opacity:1;stroke:none"
+       d="M 3,58 57,11 42,64 Z" id="triangle" />
+  </g>
+</svg>"#;
+	let mut doc = kiss_xml::parse_str(xml).unwrap();
+	let layer = doc.extract_subtree("/svg/g").unwrap();
+	assert_eq!(layer.to_string(), r#"<?xml version="1.0" encoding="UTF-8"?>
+<g xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" id="layer1" inkscape:label="Layer 1">
+  <path d="M 3,58 57,11 42,64 Z" id="triangle" style="fill:#00a6c2;fill-opacity:1;stroke:none"/>
+</g>
+"#);
+	// the new document stands on its own
+	let reparsed = kiss_xml::parse_str(layer.to_string()).unwrap();
+	assert_eq!(reparsed, layer);
+	assert_eq!(reparsed.root_namespace(), Some("http://www.w3.org/2000/svg".to_string()));
+	assert_eq!(
+		reparsed.root_element().first_element_by_name("path").unwrap().namespace(),
+		Some("http://www.w3.org/2000/svg".to_string())
+	);
+	// extracting by predicate
+	let predicate = |e: &Element| e.get_attr("id").map(|id| id == "triangle").unwrap_or(false);
+	let triangle = doc.extract_subtree(ElementSelector::Predicate(&predicate)).unwrap();
+	assert_eq!(triangle.root_name(), "path");
+	assert_eq!(triangle.root_attr("xmlns"), Some(&"http://www.w3.org/2000/svg".to_string()));
+	assert!(doc.extract_subtree("/svg/x").is_err());
+	// detaching removes it from the source
+	let detached = doc.detach_subtree("g").unwrap();
+	assert_eq!(detached, layer);
+	assert!(doc.root_element().first_element_by_name("g").is_err());
+	assert_eq!(kiss_xml::parse_str(doc.to_string()).unwrap(), doc);
+	assert!(doc.detach_subtree("/svg").is_err());
+}
+
+#[test]
+fn test_attribute_name_validation() {
+	use kiss_xml::dom::*;
+	use std::collections::HashMap;
+	for bad in ["xmlns:", "a:b:c", "xmlns:a:b", "svg:", "a=b", "a\"b", "a/b", "a>b", "1a", ":a", ""] {
+		assert!(
+			Element::new_with_attributes("e", HashMap::from([(bad, "u")])).is_err(),
+			"attribute name {bad:?} should have been rejected"
+		);
+		let mut e = Element::new_from_name("e").unwrap();
+		assert!(e.set_attr(bad, "u").is_err(), "attribute name {bad:?} should have been rejected by set_attr");
+		assert!(e.attributes().is_empty());
+	}
+	let msg = Element::new_with_attributes("a", HashMap::from([("xmlns:", "u")])).unwrap_err().to_string();
+	assert!(msg.contains("xmlns:prefix"), "unhelpful error message: {msg}");
+	// anything accepted by the constructors must round-trip
+	let good = HashMap::from([
+		("xmlns", "internal://default"),
+		("xmlns:x", "internal://x"),
+		("x:attr", "1"),
+		("xml:lang", "en"),
+		("_under", "2"),
+		("dash-dot.9", "3"),
+		("xmlnsfoo", "4"),
+		("quote", r#"say "hi" & 'bye' <tag>"#),
+	]);
+	for (k, v) in &good {
+		let single = Element::new_with_attributes("e", HashMap::from([(*k, *v)])).unwrap();
+		let reparsed = kiss_xml::parse_str(single.to_string()).unwrap();
+		assert_eq!(reparsed.root_element().attributes(), single.attributes(), "attribute {k:?} did not round-trip");
+	}
+	let e = Element::new_with_attributes("e", good).unwrap();
+	let reparsed = kiss_xml::parse_str(e.to_string()).unwrap();
+	assert_eq!(reparsed.root_element().attributes(), e.attributes());
+}
+
+#[test]
+fn test_namespace_uris() {
+	use std::collections::HashSet;
+	let mut doc = kiss_xml::parse_str(sample_xml_4()).unwrap();
+	let root = doc.root_element();
+	assert_eq!(
+		root.namespaces_in_scope().collect::<Vec<_>>(),
+		vec![("dim", "internal://ns/b"), ("img", "internal://ns/a")]
+	);
+	let depth = root.first_element_by_name("depth").unwrap();
+	assert_eq!(depth.namespaces_in_scope().count(), 2, "inherited namespaces should be in scope");
+	assert_eq!(root.namespaces_used_in_subtree(), HashSet::from(["internal://ns/a".to_string(), "internal://ns/b".to_string()]));
+	assert!(depth.namespaces_used_in_subtree().is_empty());
+	// 1 declaration + 2 img:* elements
+	assert_eq!(doc.root_element_mut().rewrite_namespace_uri("internal://ns/a", "internal://ns/final"), 3);
+	let root = doc.root_element();
+	assert_eq!(
+		root.namespaces_in_scope().collect::<Vec<_>>(),
+		vec![("dim", "internal://ns/b"), ("img", "internal://ns/final")]
+	);
+	assert_eq!(root.namespaces_used_in_subtree(), HashSet::from(["internal://ns/final".to_string(), "internal://ns/b".to_string()]));
+	assert_eq!(root.elements_by_namespace(Some("internal://ns/final")).count(), 2);
+	assert_eq!(root.elements_by_namespace(Some("internal://ns/a")).count(), 0);
+	assert_eq!(doc.to_string_with_indent("\t"), sample_xml_4().replace("internal://ns/a", "internal://ns/final"));
+	assert_eq!(kiss_xml::parse_str(doc.to_string()).unwrap(), doc);
+	// default namespace and prefixed attributes
+	let doc = kiss_xml::parse_str(r#"<root xmlns="internal://d" xmlns:x="internal://x"><a x:id="1"/></root>"#).unwrap();
+	let a = doc.root_element().first_element_by_name("a").unwrap();
+	assert_eq!(a.namespaces_in_scope().next(), Some(("", "internal://d")));
+	assert_eq!(a.namespaces_used_in_subtree(), HashSet::from(["internal://d".to_string(), "internal://x".to_string()]));
+	assert_eq!(
+		doc.root_element().clone().rewrite_namespace_uri("internal://missing", "internal://y"), 0
+	);
+}
+
+#[test]
+fn test_memory_breakdown() {
+	use kiss_xml::dom::*;
+	let doc = kiss_xml::parse_str(sample_xml_2()).unwrap();
+	let stats = doc.memory_breakdown();
+	assert_eq!(stats.elements, 9);
+	assert_eq!(stats.text_nodes, 2);
+	assert_eq!(stats.comments, 1);
+	assert_eq!(stats.cdata, 0);
+	assert_eq!(stats.attributes, 5);
+	assert!(stats.string_bytes > "This is my dataMy metadata goes here".len());
+	assert!(stats.estimated_bytes > stats.string_bytes);
+	assert_eq!(doc.estimated_memory_bytes(), stats.estimated_bytes);
+	assert_eq!(kiss_xml::parse_str(sample_xml_2()).unwrap().memory_breakdown(), stats, "estimate is not deterministic");
+	let root_stats = doc.root_element().memory_breakdown();
+	assert_eq!(root_stats.elements, stats.elements);
+	assert!(root_stats.estimated_bytes < stats.estimated_bytes, "document estimate should include the declaration");
+	let cdata = Element::new_with_children("a", vec![CData::new("<b/>").unwrap().boxed()]).unwrap();
+	assert_eq!(cdata.memory_breakdown().cdata, 1);
+	// grows roughly linearly
+	let mydata = doc.root_element().first_element_by_name("mydata").unwrap().to_string();
+	let estimate_for = |n: usize| {
+		let xml = format!("<root>{}</root>", mydata.repeat(n));
+		kiss_xml::parse_str(xml).unwrap().estimated_memory_bytes()
+	};
+	let (e10, e20, e40) = (estimate_for(10), estimate_for(20), estimate_for(40));
+	let (step1, step2) = ((e20 - e10) as f64, ((e40 - e20) as f64) / 2.0);
+	assert!(e10 < e20 && e20 < e40);
+	assert!((step1 / step2 - 1.0).abs() < 0.25, "estimate does not grow linearly: {e10}, {e20}, {e40}");
+}
+
+#[test]
+fn test_text_cdata_conversion() {
+	use kiss_xml::dom::*;
+	// node conversions
+	let cdata = Text::new("a < b").into_cdata().unwrap();
+	assert_eq!(cdata.text(), "a < b");
+	assert_eq!(cdata.into_text().text(), "a < b");
+	assert!(Text::new("a ]]> b").into_cdata().is_err());
+	// markup-heavy text is smaller as CDATA
+	let code = "<p>if (a < b && b > c) { x = \"<&>\"; }</p>\n".repeat(10);
+	let mut doc = kiss_xml::parse_str("<root><code/><note>]]&gt; stays text</note></root>").unwrap();
+	doc.root_element_mut().first_element_by_name_mut("code").unwrap().append(Text::new(code.as_str()));
+	let text_size = doc.to_string().len();
+	let code_elem = doc.root_element_mut().first_element_by_name_mut("code").unwrap();
+	assert_eq!(code_elem.convert_text_children_to_cdata(), 1);
+	assert!(code_elem.children().next().unwrap().is_cdata());
+	assert_eq!(doc.root_element_mut().first_element_by_name_mut("note").unwrap().convert_text_children_to_cdata(), 0);
+	let cdata_size = doc.to_string().len();
+	assert!(cdata_size < text_size, "CDATA ({cdata_size} bytes) should be smaller than escaped text ({text_size} bytes)");
+	let reparsed = kiss_xml::parse_str(doc.to_string()).unwrap();
+	let reparsed_code = reparsed.root_element().first_element_by_name("code").unwrap();
+	assert!(reparsed_code.children().next().unwrap().is_cdata());
+	assert_eq!(reparsed_code.children().next().unwrap().text(), code);
+	// and back again, preserving order
+	let mut e = kiss_xml::parse_str("<e>a<![CDATA[<b>]]><c/><![CDATA[d&]]></e>").unwrap().root_element().clone();
+	assert_eq!(e.convert_cdata_children_to_text(), 2);
+	assert_eq!(e.children().count(), 3, "adjacent text should be merged");
+	assert!(e.children().all(|n| !n.is_cdata()));
+	assert_eq!(e.to_string(), "<e>a&lt;b&gt;<c/>d&amp;</e>");
+	assert_eq!(e.text(), "a<b>d&");
+}
+
+#[test]
+fn test_dtd_accessors() {
+	let mut doc = kiss_xml::parse_str(sample_xml_1()).unwrap();
+	assert!(doc.has_dtd());
+	assert_eq!(doc.dtd_count(), 1);
+	assert_eq!(doc.doctype_defs().len(), 1);
+	assert_eq!(doc.doctype_defs_mut().len(), 1);
+	assert_eq!(doc.dtd(0).unwrap().name(), "note");
+	assert!(doc.dtd(1).is_none());
+	assert!(doc.dtd_mut(1).is_none());
+	doc.dtd_mut(0).unwrap().set_external_id(Some(r#"SYSTEM "note.dtd""#.to_string()));
+	assert_eq!(doc.dtd(0).unwrap().external_id(), Some(r#"SYSTEM "note.dtd""#));
+	assert_eq!(doc.doctype_defs().next(), doc.dtd(0));
+	// no DTD
+	let mut doc = kiss_xml::parse_str(sample_xml_2()).unwrap();
+	assert!(!doc.has_dtd());
+	assert_eq!(doc.dtd_count(), 0);
+	assert_eq!(doc.doctype_defs().len(), 0);
+	assert!(doc.dtd(0).is_none());
+	assert!(doc.dtd_mut(0).is_none());
+}
+
+#[test]
+fn test_reusable_parser() {
+	use kiss_xml::dom::Node;
+	let fixtures = [sample_xml_1(), sample_xml_2(), sample_xml_3(), sample_xml_4(), sample_xml_5()];
+	let mut parser = kiss_xml::Parser::new();
+	// same results as parse_str, including when reusing the parser
+	for _ in 0..2 {
+		for xml in fixtures {
+			let doc = parser.parse(xml).unwrap();
+			let expected = kiss_xml::parse_str(xml).unwrap();
+			assert_eq!(doc, expected);
+			assert_eq!(doc.to_string(), expected.to_string());
+		}
+	}
+	// no state carried over from a previous document
+	let doc = parser.parse(r#"<a xmlns="internal://a" xmlns:x="internal://x" id="1"><x:b c="2"/></a>"#).unwrap();
+	assert_eq!(doc.root_namespace(), Some("internal://a".to_string()));
+	let doc = parser.parse(r#"<a><b/></a>"#).unwrap();
+	assert_eq!(doc.root_namespace(), None);
+	assert!(doc.root_element().attributes().is_empty());
+	let b = doc.root_element().first_element_by_name("b").unwrap();
+	assert_eq!(b.namespace(), None);
+	assert!(b.attributes().is_empty());
+	assert_eq!(b.namespaces_in_scope().count(), 0);
+	// a failed parse does not affect the next one
+	assert!(parser.parse("<a><b></a>").is_err());
+	assert!(parser.parse("<a><b c=d/></a>").is_err());
+	let doc = parser.parse("<c>text</c>").unwrap();
+	assert_eq!(doc.root_element().text(), "text");
+	assert_eq!(doc.root_element().children().count(), 1);
+	// options
+	let options = kiss_xml::ParseOptions{source_spans: true, ..Default::default()};
+	let mut parser = kiss_xml::Parser::with_options(options);
+	assert!(parser.options().source_spans);
+	assert!(parser.parse(sample_xml_2()).unwrap().source_spans().is_some());
+}
+
+#[test]
+fn test_crlf_normalization() {
+	let fixtures = [sample_xml_1(), sample_xml_2(), sample_xml_3(), sample_xml_4(), sample_xml_5()];
+	for lf in fixtures {
+		let crlf = lf.replace("\n", "\r\n");
+		let lf_doc = kiss_xml::parse_str(lf).unwrap();
+		let crlf_doc = kiss_xml::parse_str(crlf.as_str()).unwrap();
+		assert_eq!(lf_doc, crlf_doc);
+		assert_eq!(lf_doc.to_string(), crlf_doc.to_string());
+		assert!(!crlf_doc.to_string().contains('\r'));
+	}
+	// text, CDATA, comments, and attribute values
+	let xml = "<root note=\"line 1\r\nline 2\rline 3\">\r\n\tline 1\r\n\tline 2\r<![CDATA[a\r\nb]]><!--c\r\nd--></root>";
+	let doc = kiss_xml::parse_str(xml).unwrap();
+	let root = doc.root_element();
+	assert_eq!(root.get_attr("note").unwrap(), "line 1\nline 2\nline 3");
+	assert_eq!(root.children().next().unwrap().text(), "\n\tline 1\n\tline 2\n");
+	assert_eq!(root.children().nth(1).unwrap().text(), "a\nb");
+	assert_eq!(root.children().nth(2).unwrap().text(), "c\nd");
+	// escape hatch
+	let options = kiss_xml::ParseOptions{preserve_carriage_returns: true, ..Default::default()};
+	let doc = kiss_xml::parse_str_with_options(xml, &options).unwrap();
+	let root = doc.root_element();
+	assert_eq!(root.get_attr("note").unwrap(), "line 1\r\nline 2\rline 3");
+	assert_eq!(root.children().next().unwrap().text(), "\r\n\tline 1\r\n\tline 2\r");
+	assert_eq!(root.children().nth(1).unwrap().text(), "a\r\nb");
+}
+
+#[test]
+fn test_self_and_descendants() {
+	use kiss_xml::dom::*;
+	let doc = kiss_xml::parse_str(sample_xml_1()).unwrap();
+	let root = doc.root_element();
+	let names: Vec<String> = root.self_and_descendants().map(|e| e.name()).collect();
+	assert_eq!(names, vec!["note", "to", "from", "heading", "paragraph", "b", "paragraph", "footer", "signed"]);
+	let names: Vec<String> = root.descendant_elements().map(|e| e.name()).collect();
+	assert_eq!(names, vec!["to", "from", "heading", "paragraph", "b", "paragraph", "footer", "signed"]);
+	assert_eq!(root.first_element_by_name("to").unwrap().self_and_descendants().count(), 1);
+	assert_eq!(root.first_element_by_name("to").unwrap().descendant_elements().count(), 0);
+	let doc = kiss_xml::parse_str(sample_xml_2()).unwrap();
+	let names: Vec<String> = doc.root_element().descendant_elements().map(|e| e.name()).collect();
+	assert_eq!(names, vec!["mydata", "desc", "properties", "property", "property", "meta", "other", "other"]);
+	assert_eq!(doc.root_element().search_elements_by_name("property").count(), 2);
+	// deep chain
+	let depth = 3_000;
+	let mut chain = Element::new_from_name("leaf").unwrap();
+	for _ in 0..depth {
+		let mut parent = Element::new_from_name("node").unwrap();
+		parent.append(chain);
+		chain = parent;
+	}
+	assert_eq!(chain.self_and_descendants().count(), depth + 1);
+	assert_eq!(chain.descendant_elements().count(), depth);
+	assert_eq!(chain.descendant_elements().last().unwrap().name(), "leaf");
+	// dropping a deep tree is recursive, so take it apart from the top
+	while chain.children().count() > 0 {
+		let mut child = chain.remove(0).unwrap();
+		chain = std::mem::take(child.as_element_mut().unwrap());
+	}
+}
+
+#[test]
+fn test_lint() {
+	use kiss_xml::lint::{LintKind, Linter};
+	let xml = r#"<root xmlns:unused="internal://unused" xmlns:used="internal://used">
+	<item id="a" label="&lt;b&gt;bold&lt;/b&gt;"/>
+	<item id="b" label="a &lt; b" title=""/>
+	<item id="a"/>
+	<used:data><![CDATA[  ]]></used:data>
+	<text note="two
+lines"><![CDATA[ x ]]></text>
+</root>"#;
+	let doc = kiss_xml::parse_str(xml).unwrap();
+	let warnings = doc.lint();
+	let kinds: Vec<LintKind> = warnings.iter().map(|w| w.kind).collect();
+	assert_eq!(kinds, vec![
+		LintKind::DuplicateChild,
+		LintKind::UnusedNamespacePrefix,
+		LintKind::MarkupInAttributeValue,
+		LintKind::EmptyAttributeValue,
+		LintKind::WhitespaceOnlyCData,
+		LintKind::RawWhitespaceInAttributeValue,
+	]);
+	let paths: Vec<&str> = warnings.iter().map(|w| w.path.as_str()).collect();
+	assert_eq!(paths, vec!["/root[1]", "/root[1]", "/root[1]/item[1]", "/root[1]/item[2]", "/root[1]/data[1]", "/root[1]/text[1]"]);
+	for w in &warnings {
+		assert!(doc.element_at_path(&w.path).unwrap().is_some(), "lint path {} does not resolve", w.path);
+	}
+	assert!(warnings[1].message.contains("'unused'"));
+	assert!(warnings[2].to_string().starts_with("MarkupInAttributeValue at /root[1]/item[1]: "));
+	// each check can be disabled individually
+	for kind in LintKind::ALL {
+		let remaining = Linter::new().disable(kind).lint(&doc);
+		assert_eq!(remaining.len(), 5);
+		assert!(remaining.iter().all(|w| w.kind != kind));
+		let only = Linter::none().enable(kind).lint(&doc);
+		assert_eq!(only.len(), 1);
+		assert_eq!(only[0].kind, kind);
+	}
+	// a clean document produces no warnings
+	let clean = kiss_xml::parse_str(r#"<root xmlns:x="internal://x">
+	<item id="a" label="a &lt; b"/>
+	<item id="b"/>
+	<x:item id="a"/>
+	<text><![CDATA[<b>bold</b>]]></text>
+</root>"#).unwrap();
+	assert_eq!(clean.lint(), vec![]);
+}
+
+#[test]
+fn test_effective_default_namespace() {
+	use kiss_xml::dom::Element;
+	let ns_a = Some("internal://ns/a".to_string());
+	// default namespace inherited from the root
+	let doc = kiss_xml::parse_str(sample_xml_3()).unwrap();
+	assert_eq!(doc.root_element().effective_default_namespace(), ns_a);
+	for child in doc.root_element().child_elements() {
+		assert_eq!(child.effective_default_namespace(), ns_a);
+	}
+	// prefixed root without a default namespace: un-prefixed children have no namespace
+	let doc = kiss_xml::parse_str(sample_xml_5()).unwrap();
+	let root = doc.root_element();
+	assert_eq!(root.namespace(), ns_a);
+	assert_eq!(root.effective_default_namespace(), None);
+	assert_eq!(root.first_element_by_name("width").unwrap().namespace(), None);
+	assert_eq!(root.first_element_by_name("width").unwrap().effective_default_namespace(), None);
+	// prefixed elements pass the inherited default namespace through to their children
+	let mut doc = kiss_xml::parse_str(r#"<root xmlns="internal://ns/a" xmlns:b="internal://ns/b">
+	<b:group>
+		<item><leaf/></item>
+		<b:inner xmlns="internal://ns/c"><item/></b:inner>
+	</b:group>
+</root>"#).unwrap();
+	let group = doc.root_element().first_element_by_name("group").unwrap();
+	assert_eq!(group.namespace(), Some("internal://ns/b".to_string()));
+	assert_eq!(group.default_namespace(), None);
+	assert_eq!(group.effective_default_namespace(), ns_a);
+	let item = group.first_element_by_name("item").unwrap();
+	assert_eq!(item.namespace(), ns_a);
+	assert_eq!(item.first_element_by_name("leaf").unwrap().effective_default_namespace(), ns_a);
+	let inner = group.first_element_by_name("inner").unwrap();
+	assert_eq!(inner.effective_default_namespace(), Some("internal://ns/c".to_string()));
+	assert_eq!(inner.first_element_by_name("item").unwrap().namespace(), Some("internal://ns/c".to_string()));
+	// appended elements get the same namespace as parsed ones
+	let group = doc.root_element_mut().first_element_by_name_mut("group").unwrap();
+	group.append(Element::new_from_name("appended").unwrap());
+	assert_eq!(group.first_element_by_name("appended").unwrap().namespace(), ns_a);
+	group.insert(0, Element::new_from_name("inserted").unwrap()).unwrap();
+	assert_eq!(group.first_element_by_name("inserted").unwrap().namespace(), ns_a);
+	// the inherited default namespace is in scope for prefixed elements
+	let group = doc.root_element().first_element_by_name("group").unwrap();
+	let in_scope: Vec<(&str, &str)> = group.namespaces_in_scope().collect();
+	assert_eq!(in_scope, vec![("", "internal://ns/a"), ("b", "internal://ns/b")]);
+	// extracting a prefixed element keeps the inherited default namespace
+	let sub = doc.extract_subtree("/root/group").unwrap();
+	assert_eq!(sub.root_element().get_attr("xmlns"), ns_a.as_ref());
+	assert_eq!(sub.root_element().first_element_by_name("item").unwrap().namespace(), ns_a);
+	// rewriting the default namespace also rewrites the one that prefixed elements pass through
+	let mut doc = kiss_xml::parse_str(r#"<root xmlns="internal://a" xmlns:b="internal://b"><b:group/></root>"#).unwrap();
+	assert_eq!(doc.root_element_mut().rewrite_namespace_uri("internal://a", "internal://z"), 2);
+	let group = doc.root_element_mut().first_element_by_name_mut("group").unwrap();
+	assert_eq!(group.effective_default_namespace(), Some("internal://z".to_string()));
+	group.append(Element::new_from_name("child").unwrap());
+	assert_eq!(group.first_element_by_name("child").unwrap().namespace(), Some("internal://z".to_string()));
+	assert_eq!(kiss_xml::parse_str(doc.to_string()).unwrap(), doc);
+}
+
+#[test]
+fn test_space_before_self_close() {
+	use kiss_xml::OutputOptions;
+	let compact = "<root>\n  <property name=\"a\" value=\"1\"/>\n  <empty/>\n  <text>x</text>\n</root>\n";
+	let spaced = "<root>\n  <property name=\"a\" value=\"1\" />\n  <empty />\n  <text>x</text>\n</root>\n";
+	let default_options = OutputOptions::default();
+	let spaced_options = OutputOptions{space_before_self_close: true, ..Default::default()};
+	assert!(!default_options.space_before_self_close);
+	for source in [compact, spaced, "<root><property name=\"a\" value=\"1\"\n\t/><empty\n/><text>x</text></root>"] {
+		let doc = kiss_xml::parse_str(source).unwrap();
+		// a source without a declaration or a trailing newline is a fragment, written without one
+		let (compact, spaced) = match doc.is_fragment() {
+			true => (compact.trim_end(), spaced.trim_end()),
+			false => (compact, spaced)
+		};
+		assert_eq!(doc.to_string(), compact, "default output should not depend on the source style");
+		assert_eq!(doc.to_string_with_options(&default_options), compact);
+		assert_eq!(doc.to_string_with_options(&spaced_options), spaced);
+		// both styles parse back to the same DOM
+		assert_eq!(kiss_xml::parse_str(doc.to_string_with_options(&spaced_options)).unwrap(), doc);
+		assert_eq!(kiss_xml::parse_str(doc.to_string()).unwrap(), doc);
+	}
+	let doc = kiss_xml::parse_str(spaced).unwrap();
+	let options = OutputOptions{indent: "\t".to_string(), space_before_self_close: true, ..Default::default()};
+	assert_eq!(doc.root_element().first_element_by_name("empty").unwrap().to_string_with_options(&options), "<empty />");
+	assert_eq!(doc.to_string_with_indent("\t"), compact.replace("  ", "\t"));
+}
+
+#[test]
+fn test_find_duplicate_children() {
+	use kiss_xml;
+	let mut doc = kiss_xml::parse_str(sample_xml_2()).unwrap();
+	let mydata = doc.root_element_mut().first_element_by_name_mut("mydata").unwrap();
+	// <desc>, <properties>, <meta>, <other/>, <other/>
+	assert_eq!(mydata.find_duplicate_children(), vec![(3, 4)]);
+	assert_eq!(mydata.dedup_equal_children(), 1);
+	assert_eq!(mydata.elements_by_name("other").count(), 1);
+	assert!(mydata.find_duplicate_children().is_empty());
+	assert_eq!(mydata.dedup_equal_children(), 0);
+	// near-duplicates differing by one attribute (or one descendant) are not duplicates
+	let mut list = kiss_xml::parse_str(r#"<list>
+	<property name="a" value="1"/>
+	<property name="a" value="2"/>
+	<property name="a" value="1"/>
+	<group><property name="a" value="1"/></group>
+	<group><property name="a" value="2"/></group>
+	<group><property name="a" value="1"/></group>
+	<property value="1" name="a"/>
+	<!--note-->
+	<!--note-->
+</list>"#).unwrap();
+	let root = list.root_element_mut();
+	assert_eq!(root.find_duplicate_children(), vec![(0, 2), (3, 5), (0, 6), (7, 8)]);
+	assert_eq!(root.dedup_equal_children(), 4);
+	assert_eq!(root.child_elements().count(), 4);
+	assert_eq!(root.children().filter(|n| n.is_comment()).count(), 1);
+	assert_eq!(root.child_elements().map(|e| e.get_attr("value").cloned()).collect::<Vec<_>>(),
+		vec![Some("1".to_string()), Some("2".to_string()), None, None]);
+}
+
+#[test]
+fn test_validate_path() {
+	use kiss_xml;
+	for path in [
+		"/root", "/root/mydata/other", "/root/mydata/other[2]", "/root/mydata/properties/property[2]",
+		"/root[1]/mydata[1]/other[2]", "sound/effects/reverb", "sound/property[2]", "/svg/g", "g",
+		"svg:g/xlink:a", "_private/data-1.2", "données/élément"
+	] {
+		assert!(kiss_xml::validate_path(path).is_ok(), "path '{}' should be valid", path);
+	}
+	fn error_at(path: &str, position: usize) {
+		let msg = kiss_xml::validate_path(path).expect_err(&format!("path '{}' should be invalid", path)).to_string();
+		assert!(msg.contains(&format!("position {}", position)), "wrong position for '{}': {}", path, msg);
+	}
+	// empty segments
+	error_at("", 0);
+	error_at("a//b", 2);
+	error_at("/a/b/", 5);
+	error_at("//a", 1);
+	// bad characters
+	error_at("sound/pro perty", 9);
+	error_at("/root/1st", 6);
+	error_at("a/b=c", 3);
+	error_at("a/:b", 2);
+	error_at("a/x:y:z", 5);
+	error_at("a/b:", 2);
+	// malformed predicates
+	error_at("a/b[2", 3);
+	error_at("a/b[0]", 4);
+	error_at("a/b[x]", 4);
+	error_at("a/b[]", 4);
+	error_at("a/[2]", 2);
+}
+
+#[test]
+fn test_validate_attr_name() {
+	use kiss_xml;
+	for name in ["name", "xmlns", "xmlns:svg", "xlink:href", "_id", "data-value"] {
+		assert!(kiss_xml::validate_attr_name(name).is_ok(), "attribute name '{}' should be valid", name);
+	}
+	for name in ["", "1st", "bad name", "a=b", "a:b:c", ":a", "a:"] {
+		assert!(kiss_xml::validate_attr_name(name).is_err(), "attribute name '{}' should be invalid", name);
+	}
+}
+
+#[test]
+fn test_find_fragment() {
+	use kiss_xml;
+	use kiss_xml::dom::*;
+	let doc = kiss_xml::parse_str(sample_xml_2()).unwrap();
+	let root = doc.root_element();
+	let fragment = |xml: &str| kiss_xml::parse_str(xml).unwrap().root_element().clone();
+	// matches
+	for xml in [
+		r#"<root/>"#,
+		r#"<root author="some dude"/>"#,
+		r#"<properties><property name="b"/></properties>"#,
+		r#"<properties><property name="a" value="1"/><property name="b" value="2"/></properties>"#,
+		r#"<mydata><desc>  This is my data </desc><other/></mydata>"#,
+		r#"<mydata><properties><property/></properties><meta/><other/><other/></mydata>"#,
+		r#"<root><mydata><other/></mydata></root>"#,
+		r#"<meta>My metadata goes here</meta>"#,
+	] {
+		assert!(root.contains_fragment(&fragment(xml)), "fragment should match: {}", xml);
+	}
+	assert_eq!(root.find_fragment(&fragment(r#"<property name="b"/>"#)).unwrap().get_attr("value").unwrap(), "2");
+	assert_eq!(root.find_fragment(&fragment(r#"<mydata><other/></mydata>"#)).unwrap().name(), "mydata");
+	// no match
+	for xml in [
+		r#"<root author="someone else"/>"#,
+		r#"<properties><property name="c"/></properties>"#,
+		r#"<properties><property name="a" value="2"/></properties>"#,
+		r#"<properties><property name="b"/><property name="a"/></properties>"#,
+		r#"<root><properties/></root>"#,
+		r#"<mydata><other/><other/><other/></mydata>"#,
+		r#"<mydata><meta/><desc/></mydata>"#,
+		r#"<meta>Other metadata</meta>"#,
+		r#"<other><desc/></other>"#,
+	] {
+		assert!(!root.contains_fragment(&fragment(xml)), "fragment should not match: {}", xml);
+	}
+	assert!(root.find_fragment(&Element::new_from_name("missing").unwrap()).is_none());
+}
+
+#[test]
+fn test_to_string_filtered() {
+	use kiss_xml;
+	let doc = kiss_xml::parse_str(sample_xml_2()).unwrap();
+	let original = doc.to_string();
+	let filter = kiss_xml::OutputFilter{
+		deny_elements: vec!["properties".to_string()],
+		deny_attributes: vec!["author".to_string()],
+		..Default::default()
+	};
+	assert_eq!(doc.to_string_filtered(&filter), r#"<?xml version="1.0" encoding="UTF-8"?>
+<root>
+  <!--comment-->
+  <mydata>
+    <desc>This is my data</desc>
+    <meta>My metadata goes here</meta>
+    <other/>
+    <other/>
+  </mydata>
+</root>
+"#);
+	// the DOM is unchanged
+	assert_eq!(doc.to_string(), original);
+	assert_eq!(doc, kiss_xml::parse_str(sample_xml_2()).unwrap());
+	// filter by path, including all children of an element
+	let filter = kiss_xml::OutputFilter{
+		deny_paths: vec!["/root/mydata/other[2]".to_string(), "mydata/properties/property[1]".to_string(), "/root/mydata/meta".to_string()],
+		deny_attributes: vec!["value".to_string()],
+		output_options: kiss_xml::OutputOptions{indent: "\t".to_string(), ..Default::default()},
+		..Default::default()
+	};
+	assert_eq!(doc.to_string_filtered(&filter), r#"<?xml version="1.0" encoding="UTF-8"?>
+<root author="some dude">
+	<!--comment-->
+	<mydata>
+		<desc>This is my data</desc>
+		<properties>
+			<property name="b"/>
+		</properties>
+		<other/>
+	</mydata>
+</root>
+"#);
+	// an empty filter writes the whole document
+	assert_eq!(doc.to_string_filtered(&kiss_xml::OutputFilter::default()), original);
+	// namespace declarations are kept and the output is still well-formed
+	let doc = kiss_xml::parse_str(r#"<root xmlns:a="internal://ns/a"><a:x a:id="1"/><a:y><a:z/></a:y></root>"#).unwrap();
+	let filter = kiss_xml::OutputFilter{
+		deny_elements: vec!["a:y".to_string()],
+		deny_attributes: vec!["xmlns:a".to_string(), "a:id".to_string()],
+		..Default::default()
+	};
+	let filtered = doc.to_string_filtered(&filter);
+	assert_eq!(filtered, "<root xmlns:a=\"internal://ns/a\">\n  <a:x/>\n</root>");
+	assert!(kiss_xml::parse_str(filtered).is_ok());
+}
+
+#[test]
+fn test_scrub_timestamp_comments() {
+	use kiss_xml;
+	use kiss_xml::dom::*;
+	fn generate(timestamp: &str) -> Document {
+		let mut doc = Document::new(Element::new_with_children("root", vec![
+			Comment::new(format!("This XML document was generated on {}", timestamp)).unwrap().boxed(),
+			Element::new_with_text("motd", "Message of the day is: hello!").unwrap().boxed()
+		]).unwrap());
+		doc.root_element_mut().append_element("settings").unwrap()
+			.append_comment(format!("last modified {}", timestamp)).unwrap();
+		doc.root_element_mut().append_comment("end of document").unwrap();
+		doc
+	}
+	let is_timestamp = |c: &Comment| {
+		regex::Regex::new(r"\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}").unwrap().is_match(c.get_content())
+	};
+	let mut doc1 = generate("2024-01-01T00:00:00+00:00");
+	let mut doc2 = generate("2025-06-30T12:34:56+00:00");
+	assert_ne!(doc1, doc2);
+	// compare without modifying
+	assert!(doc1.eq_ignore_comments(&doc2, is_timestamp));
+	assert!(doc1.eq_ignore_comments(&doc2, |_| true));
+	assert!(!doc1.eq_ignore_comments(&doc2, |c| c.get_content().starts_with("This XML")));
+	// scrub then compare
+	assert_eq!(doc1.scrub_comments(is_timestamp, "<timestamp>").unwrap(), 2);
+	assert_eq!(doc2.scrub_comments(is_timestamp, "<timestamp>").unwrap(), 2);
+	assert_eq!(doc1, doc2);
+	assert_eq!(doc1.to_string(), r#"<?xml version="1.0" encoding="UTF-8"?>
+<root>
+  <!--<timestamp>-->
+  <motd>Message of the day is: hello!</motd>
+  <settings><!--<timestamp>--></settings>
+  <!--end of document-->
+</root>
+"#);
+	// invalid replacement is rejected without modifying anything
+	let mut doc3 = generate("2024-01-01T00:00:00+00:00");
+	assert!(doc3.scrub_comments(|_| true, "-->").is_err());
+	assert_eq!(doc3, generate("2024-01-01T00:00:00+00:00"));
+	// ignoring comments does not ignore other differences
+	let mut doc4 = generate("2024-01-01T00:00:00+00:00");
+	doc4.root_element_mut().first_element_by_name_mut("motd").unwrap().set_text("bye!");
+	assert!(!doc4.eq_ignore_comments(&generate("2024-01-01T00:00:00+00:00"), |_| true));
+}
+
+#[test]
+fn test_framed_documents() {
+	use kiss_xml;
+	use kiss_xml::errors::KissXmlError;
+	let docs = vec![
+		kiss_xml::parse_str(sample_xml_1()).unwrap(),
+		kiss_xml::parse_str(sample_xml_2()).unwrap(),
+		kiss_xml::parse_str("<msg>héllo wörld</msg>").unwrap(),
+	];
+	let mut buffer: Vec<u8> = Vec::new();
+	for doc in &docs {
+		kiss_xml::write_framed(&mut buffer, doc).unwrap();
+	}
+	let first_len = docs[0].to_string().len();
+	assert_eq!(&buffer[..4], &(first_len as u32).to_be_bytes());
+	let mut reader = std::io::Cursor::new(buffer.clone());
+	for doc in &docs {
+		assert_eq!(&kiss_xml::read_framed(&mut reader).unwrap(), doc);
+	}
+	// end of stream
+	match kiss_xml::read_framed(&mut reader) {
+		Err(KissXmlError::IOError(e)) => assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof),
+		other => panic!("expected an IOError, got {:?}", other)
+	}
+	// truncated frame
+	let mut truncated = &buffer[..first_len];
+	assert!(matches!(kiss_xml::read_framed(&mut truncated), Err(KissXmlError::IOError(_))));
+	// frame over the size limit
+	let options = kiss_xml::ParseOptions::new().max_input_bytes(Some(first_len - 1));
+	assert!(matches!(kiss_xml::read_framed_with_options(&mut buffer.as_slice(), &options), Err(KissXmlError::LimitExceeded(_))));
+	// a huge length prefix on a short stream is not allocated up front
+	let mut lying = u32::MAX.to_be_bytes().to_vec();
+	lying.extend_from_slice(b"<a/>");
+	match kiss_xml::read_framed(&mut lying.as_slice()) {
+		Err(KissXmlError::IOError(e)) => assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof),
+		other => panic!("expected an IOError, got {:?}", other)
+	}
+}
+
+#[test]
+fn test_extract_documents() {
+	use kiss_xml;
+	use kiss_xml::dom::Node;
+	let log = r#"2024-01-01 12:00:00 INFO connecting to server <not a tag
+2024-01-01 12:00:01 DEBUG received <?xml version="1.0" encoding="UTF-8"?>
+<response status="ok"><value>42</value></response> (took 3ms)
+2024-01-01 12:00:02 WARN retrying because 3 < 5 and <unclosed>
+2024-01-01 12:00:03 DEBUG received <!-- compact --> <ack id="7"/>, done <
+"#;
+	let docs = kiss_xml::extract_documents(log);
+	assert_eq!(docs.len(), 2, "found {:?}", docs.iter().map(|(r, _)| &log[r.clone()]).collect::<Vec<_>>());
+	let (span, result) = &docs[0];
+	assert!(log[span.clone()].starts_with("<?xml"));
+	assert!(log[span.clone()].ends_with("</response>"));
+	let doc = result.as_ref().unwrap();
+	assert_eq!(doc.root_element().name(), "response");
+	assert_eq!(doc.root_element().first_element_by_name("value").unwrap().text(), "42");
+	let (span, result) = &docs[1];
+	assert_eq!(&log[span.clone()], r#"<ack id="7"/>"#);
+	assert_eq!(result.as_ref().unwrap().root_element().get_attr("id").unwrap(), "7");
+	// complete but invalid documents are reported with their error
+	let docs = kiss_xml::extract_documents("start <a><b></a></b> end");
+	assert_eq!(docs.len(), 1);
+	assert!(docs[0].1.is_err());
+	assert!(kiss_xml::extract_documents("no xml here").is_empty());
+	// a closed element after an unclosed one
+	let text = "<x> and <y>hi</y> then <z/>";
+	let spans: Vec<&str> = kiss_xml::extract_documents(text).into_iter().map(|(r, _)| &text[r]).collect();
+	assert_eq!(spans, vec!["<y>hi</y>", "<z/>"]);
+	// parse_first_document
+	let (doc, len) = kiss_xml::parse_first_document("  <a><b/></a><c/>").unwrap();
+	assert_eq!(doc.root_element().name(), "a");
+	assert_eq!(len, 13);
+	assert!(kiss_xml::parse_first_document("text <a/>").is_err());
+	assert!(kiss_xml::parse_first_document("<a><b/>").is_err());
+}
+
+#[test]
+fn test_extract_documents_from_large_text() {
+	use kiss_xml;
+	use std::time::{Duration, Instant};
+	// lots of unclosed tags, which must not each be scanned to the end of the text
+	let mut log = String::new();
+	while log.len() < 1024 * 1024 {
+		log.push_str("log <x> entry ");
+	}
+	log.push_str("<done/>");
+	let start = Instant::now();
+	let docs = kiss_xml::extract_documents(log.as_str());
+	assert!(start.elapsed() < Duration::from_secs(10), "extraction took {:?}", start.elapsed());
+	assert_eq!(docs.len(), 1);
+	assert_eq!(&log[docs[0].0.clone()], "<done/>");
+	// with a closing tag at the end, only the last <x> is closed
+	log.push_str("</x>");
+	let start = Instant::now();
+	let docs = kiss_xml::extract_documents(log.as_str());
+	assert!(start.elapsed() < Duration::from_secs(10), "extraction took {:?}", start.elapsed());
+	assert_eq!(docs.len(), 1);
+	assert_eq!(&log[docs[0].0.clone()], "<x> entry <done/></x>");
+	// the same with an XML declaration before every unclosed tag
+	let mut log = String::new();
+	while log.len() < 1024 * 1024 {
+		log.push_str("<?xml version=\"1.0\"?><a> ");
+	}
+	log.push_str("<done/>");
+	let start = Instant::now();
+	let docs = kiss_xml::extract_documents(log.as_str());
+	assert!(start.elapsed() < Duration::from_secs(10), "extraction took {:?}", start.elapsed());
+	assert_eq!(docs.len(), 1);
+	assert_eq!(&log[docs[0].0.clone()], "<done/>");
+	log.push_str("</a>");
+	let start = Instant::now();
+	let docs = kiss_xml::extract_documents(log.as_str());
+	assert!(start.elapsed() < Duration::from_secs(10), "extraction took {:?}", start.elapsed());
+	assert_eq!(docs.len(), 1);
+	assert_eq!(&log[docs[0].0.clone()], "<?xml version=\"1.0\"?><a> <done/></a>");
+}
+
+#[test]
+fn test_formatted_attributes_and_text() {
+	use kiss_xml::dom::*;
+	let value = "a\"b'c<d&e\nf";
+	let mut e = Element::new_from_name("e").unwrap();
+	e.set_attr_fmt("v", format_args!("[{}] &amp;", value)).unwrap();
+	// stored raw: interpolated and literal parts are kept as-is
+	assert_eq!(e.get_attr("v").unwrap(), "[a\"b'c<d&e\nf] &amp;");
+	e.append_text_fmt(format_args!("<{}>", value));
+	assert_eq!(e.text(), "<a\"b'c<d&e\nf>");
+	// serialized: everything escaped exactly once
+	assert_eq!(
+		e.to_string(),
+		"<e v=\"[a&quot;b&apos;c&lt;d&amp;e\nf] &amp;amp;\">&lt;a\"b'c&lt;d&amp;e\nf&gt;</e>"
+	);
+	assert!(e.set_attr_fmt("bad name", format_args!("{}", 1)).is_err());
+	// round trip
+	let doc = kiss_xml::parse_str(e.to_string()).unwrap();
+	assert_eq!(doc.root_element().get_attr("v").unwrap(), "[a\"b'c<d&e\nf] &amp;");
+	assert_eq!(doc.root_element().text(), "<a\"b'c<d&e\nf>");
+}
+
+#[test]
+fn test_new_mixed() {
+	use kiss_xml::dom::*;
+	let doc = kiss_xml::parse_str(sample_xml_1()).unwrap();
+	let parsed = doc.root_element().first_element_by_name("paragraph").unwrap();
+	let built = Element::new_mixed("paragraph", vec![
+		MixedPart::Text("Don't forget ".into()),
+		MixedPart::Child(Element::new_with_text("b", "me").unwrap().boxed()),
+		MixedPart::Text(" this weekend!".into()),
+	]).unwrap();
+	assert_eq!(&built, parsed);
+	assert_eq!(built.children().count(), 3);
+	// adjacent text parts merge, separated ones do not
+	let e = Element::new_mixed("e", vec![
+		MixedPart::Text("a".into()),
+		MixedPart::Text("b".into()),
+		MixedPart::Comment("c".into()),
+		MixedPart::Text("d".into()),
+		MixedPart::CData("<e>".into()),
+		MixedPart::Text("f".into()),
+	]).unwrap();
+	assert_eq!(e.to_string(), "<e>ab<!--c-->d<![CDATA[<e>]]>f</e>");
+	assert_eq!(e.children().count(), 5);
+	// new(...) puts the text before the children
+	let e = Element::new("x", Some("a".to_string()), Option::<std::collections::HashMap<String, String>>::None, None, None,
+		Some(vec![Element::new_from_name("b").unwrap().boxed()])).unwrap();
+	assert_eq!(e.to_string(), "<x>a<b/></x>");
+	assert!(Element::new_mixed("e", vec![MixedPart::Comment("-->".into())]).is_err());
+}
+
+#[test]
+fn test_omit_redundant_namespace_declarations() {
+	use kiss_xml::dom::*;
+	use kiss_xml::OutputOptions;
+	// assemble from fragments that each declare their own namespaces
+	let mut root = Element::new_with_attributes("root", std::collections::HashMap::from([
+		("xmlns", "urn:x"), ("xmlns:p", "urn:p")
+	])).unwrap();
+	for _ in 0..3 {
+		let mut a = kiss_xml::parse_str(r#"<a xmlns="urn:x" xmlns:p="urn:p"><p:b xmlns:p="urn:p"/></a>"#)
+			.unwrap().root_element().clone();
+		a.append(kiss_xml::parse_str(r#"<c xmlns="urn:y"><d xmlns="urn:x"/></c>"#).unwrap().root_element().clone());
+		root.append(a);
+	}
+	let doc = Document::new(root);
+	let full = doc.to_string_with_options(&OutputOptions::default());
+	let slim = doc.to_string_with_options(&OutputOptions::new().omit_redundant_namespace_declarations(true));
+	assert!(slim.len() < full.len());
+	assert_eq!(slim.matches("xmlns=\"urn:x\"").count(), 4, "root and the three <d> elements inside urn:y");
+	assert_eq!(slim.matches("xmlns:p=").count(), 1);
+	assert_eq!(slim.matches("xmlns=\"urn:y\"").count(), 3);
+	// namespaces are unchanged after parsing the slimmed output
+	let reparsed = kiss_xml::parse_str(&slim).unwrap();
+	let expected: Vec<(String, Option<String>, Option<String>)> = kiss_xml::parse_str(&full).unwrap().root_element()
+		.self_and_descendants().map(|e| (e.name(), e.namespace(), e.namespace_prefix())).collect();
+	let actual: Vec<(String, Option<String>, Option<String>)> = reparsed.root_element()
+		.self_and_descendants().map(|e| (e.name(), e.namespace(), e.namespace_prefix())).collect();
+	assert_eq!(actual, expected);
+	assert_eq!(expected.len(), 13);
+	assert!(expected.iter().any(|(n, ns, _)| n == "b" && ns.as_deref() == Some("urn:p")));
+	// off by default
+	assert_eq!(full, doc.to_string());
+}
+
+#[test]
+fn test_parse_expecting_root() {
+	use kiss_xml::errors::KissXmlError;
+	use std::io::Write;
+	// matching
+	let doc = kiss_xml::parse_str_expecting(sample_xml_5(), "root", Some("internal://ns/a")).unwrap();
+	assert_eq!(doc.root_element().namespace_prefix().as_deref(), Some("img"));
+	assert!(kiss_xml::parse_str_expecting(sample_xml_1(), "note", None).is_ok());
+	assert!(kiss_xml::parse_stream_expecting(sample_xml_1().as_bytes(), "note", None).is_ok());
+	let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+	write!(tmpfile, "{}", sample_xml_5()).unwrap();
+	assert!(kiss_xml::parse_filepath_expecting(tmpfile.path(), "root", Some("internal://ns/a")).is_ok());
+	// name mismatch
+	match kiss_xml::parse_str_expecting(sample_xml_1(), "config", None) {
+		Err(KissXmlError::UnexpectedRoot(e)) => {
+			assert_eq!(e.actual_name, "note");
+			assert_eq!(e.actual_namespace, None);
+			assert_eq!(e.to_string(), "UnexpectedRoot: expected root <config> in no namespace, found <note> in no namespace");
+		},
+		other => panic!("expected UnexpectedRoot error, got {:?}", other)
+	}
+	// namespace mismatch, including a prefixed root (prefixes are not compared)
+	match kiss_xml::parse_str_expecting(sample_xml_5(), "root", Some("internal://ns/b")) {
+		Err(KissXmlError::UnexpectedRoot(e)) => {
+			assert_eq!(e.actual_name, "root");
+			assert_eq!(e.actual_namespace.as_deref(), Some("internal://ns/a"));
+			assert_eq!(e.expected_namespace.as_deref(), Some("internal://ns/b"));
+			assert!(e.msg.contains("found <root> in namespace internal://ns/a"), "{}", e.msg);
+		},
+		other => panic!("expected UnexpectedRoot error, got {:?}", other)
+	}
+	assert!(matches!(kiss_xml::parse_str_expecting(sample_xml_5(), "root", None), Err(KissXmlError::UnexpectedRoot(_))));
+	assert!(matches!(kiss_xml::parse_str_expecting(sample_xml_1(), "note", Some("urn:x")), Err(KissXmlError::UnexpectedRoot(_))));
+	// parse errors are passed through
+	assert!(matches!(kiss_xml::parse_str_expecting("<note>", "note", None), Err(KissXmlError::ParsingError(_))));
+}
+
+#[test]
+fn test_attr_diff() {
+	use kiss_xml::dom::*;
+	let a = kiss_xml::parse_str(
+		r#"<p:property xmlns:p="urn:props/1" xmlns:old="urn:old" name="width" value="10" unit="px"/>"#
+	).unwrap().root_element().clone();
+	let b = kiss_xml::parse_str(
+		r#"<p:property xmlns:p="urn:props/2" name="width" value="12" min="0" max="100"/>"#
+	).unwrap().root_element().clone();
+	assert_eq!(a.namespace().as_deref(), Some("urn:props/1"));
+	let diff = a.attr_diff(&b);
+	assert_eq!(diff.added, vec![("max".to_string(), "100".to_string()), ("min".to_string(), "0".to_string())]);
+	assert_eq!(diff.removed, vec!["unit".to_string(), "xmlns:old".to_string()]);
+	assert_eq!(diff.changed, vec![
+		("value".to_string(), "10".to_string(), "12".to_string()),
+		("xmlns:p".to_string(), "urn:props/1".to_string(), "urn:props/2".to_string()),
+	]);
+	assert_eq!(diff.len(), 6);
+	assert_eq!(diff.changes().collect::<Vec<_>>(), vec![
+		AttrChange::Added("max", "100"),
+		AttrChange::Added("min", "0"),
+		AttrChange::Removed("unit"),
+		AttrChange::Changed("value", "10", "12"),
+		AttrChange::Removed("xmlns:old"),
+		AttrChange::Changed("xmlns:p", "urn:props/1", "urn:props/2"),
+	]);
+	let mut patched = a.clone();
+	patched.apply_attr_diff(&diff).unwrap();
+	assert_eq!(patched.attributes(), b.attributes());
+	assert_eq!(patched.namespace().as_deref(), Some("urn:props/2"), "xmlns change not applied to namespace");
+	assert_eq!(patched, b);
+	assert!(patched.attr_diff(&b).is_empty());
+	// invalid diffs leave the element unmodified
+	let mut unchanged = a.clone();
+	let bad_removal = AttrDiff{removed: vec!["nope".to_string()], added: vec![("x".to_string(), "1".to_string())], ..Default::default()};
+	assert!(unchanged.apply_attr_diff(&bad_removal).is_err());
+	let bad_name = AttrDiff{added: vec![("bad name".to_string(), "1".to_string())], removed: vec!["unit".to_string()], ..Default::default()};
+	assert!(unchanged.apply_attr_diff(&bad_name).is_err());
+	assert_eq!(unchanged, a);
+}
+
+#[test]
+fn test_names_with_path_reserved_characters() {
+	use kiss_xml::dom::*;
+	use kiss_xml::errors::KissXmlError;
+	let reserved = ["a/b", "/a", "a[1]", "a]", "@a", "a@b", "a b", "a:b", "a:b:c", "1a", "-a", "a*", "a=b", "a\"", "a>", ""];
+	for name in reserved {
+		assert!(
+			matches!(Element::new_from_name(name), Err(KissXmlError::InvalidElementName(_))),
+			"element name '{}' should be rejected", name
+		);
+		assert!(Element::new_with_text(name, "x").is_err(), "element name '{}' should be rejected", name);
+	}
+	for name in ["a/b", "a[1]", "@a", "a b", ":a", "a:", "p:1a", "a:b:c", ""] {
+		assert!(kiss_xml::validate_attr_name(name).is_err(), "attribute name '{}' should be rejected", name);
+	}
+	assert!(kiss_xml::validate_attr_name("xlink:href").is_ok());
+	assert!(kiss_xml::parse_str("<a/b/>").is_err());
+	assert!(kiss_xml::parse_str("<a x/y=\"1\"/>").is_err());
+	// every constructible name can be looked up by path without being confused with the path syntax
+	let names = ["a", "_a", "a.b", "a-b", "a_1", "\u{e9}t\u{e9}", "a\u{b7}b", "e\u{301}", "\u{4e2d}\u{6587}"];
+	let mut root = Element::new_from_name("root").unwrap();
+	for (i, name) in names.iter().enumerate() {
+		root.append(Element::new_with_text(name, i.to_string()).unwrap());
+		root.append(Element::new_with_text(name, format!("{i}-2")).unwrap());
+	}
+	let doc = kiss_xml::parse_str(Document::new(root).to_string()).unwrap();
+	for (i, name) in names.iter().enumerate() {
+		let path = format!("/root/{name}[2]");
+		assert!(kiss_xml::validate_path(&path).is_ok(), "path {} should be valid", path);
+		assert_eq!(doc.element_at_path(&path).unwrap().unwrap().text(), format!("{i}-2"));
+		assert_eq!(doc.element_at_path(name).unwrap().unwrap().text(), i.to_string());
+	}
+}
+
+#[test]
+fn test_insert_path_values() {
+	use kiss_xml::dom::*;
+	use std::collections::BTreeMap;
+	let entries: BTreeMap<String, String> = [
+		("display/brightness", "80"),
+		("display/mode", "dark"),
+		("name", "My Settings"),
+		("network/proxy/host", "proxy.local"),
+		("network/proxy/port", "8080"),
+		("network/timeout", "30"),
+		("sound/effects/reverb", "on"),
+		("sound/mixer", "standard"),
+		("sound/volume", "11"),
+		("user", "bob & alice"),
+	].iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+	let mut config = Element::new_from_name("config").unwrap();
+	assert_eq!(config.insert_path_values(entries, DuplicatePathPolicy::Overwrite).unwrap(), 10);
+	let expected = kiss_xml::parse_str(r#"<config>
+		<display><brightness>80</brightness><mode>dark</mode></display>
+		<name>My Settings</name>
+		<network>
+			<proxy><host>proxy.local</host><port>8080</port></proxy>
+			<timeout>30</timeout>
+		</network>
+		<sound>
+			<effects><reverb>on</reverb></effects>
+			<mixer>standard</mixer>
+			<volume>11</volume>
+		</sound>
+		<user>bob &amp; alice</user>
+	</config>"#).unwrap();
+	assert_eq!(&config, expected.root_element());
+	// duplicate paths under each policy
+	let duplicates = || vec![
+		("sound/volume".to_string(), "11".to_string()),
+		("sound/volume".to_string(), "5".to_string()),
+	];
+	let mut overwritten = Element::new_from_name("config").unwrap();
+	assert_eq!(overwritten.insert_path_values(duplicates(), DuplicatePathPolicy::Overwrite).unwrap(), 2);
+	assert_eq!(&overwritten, kiss_xml::parse_str("<config><sound><volume>5</volume></sound></config>").unwrap().root_element());
+	let mut appended = Element::new_from_name("config").unwrap();
+	assert_eq!(appended.insert_path_values(duplicates(), DuplicatePathPolicy::AppendSibling).unwrap(), 2);
+	assert_eq!(&appended, kiss_xml::parse_str("<config><sound><volume>11</volume><volume>5</volume></sound></config>").unwrap().root_element());
+	// overwriting keeps child elements
+	let mut mixed = Element::new_from_name("config").unwrap();
+	mixed.insert_path_values(vec![
+		("sound/volume".to_string(), "11".to_string()),
+		("sound".to_string(), "loud".to_string()),
+	], DuplicatePathPolicy::Overwrite).unwrap();
+	assert_eq!(&mixed, kiss_xml::parse_str("<config><sound><volume>11</volume>loud</sound></config>").unwrap().root_element());
+	// invalid paths are named in the error
+	for bad in ["sound//volume", "/sound/volume", "sound/vol ume", "sound/a@b", ""] {
+		let mut e = Element::new_from_name("config").unwrap();
+		let err = e.insert_path_values(vec![(bad.to_string(), "1".to_string())], DuplicatePathPolicy::Overwrite)
+			.expect_err(bad);
+		assert!(err.to_string().contains(&format!("'{bad}'")), "error should name the path: {}", err);
+	}
+}
+
+#[test]
+fn test_raw_whitespace_in_attribute_warnings() {
+	use kiss_xml::lint::LintKind;
+	use kiss_xml::OutputOptions;
+	let xml = "<config>\n\t<item label=\"first line\nsecond line\" sep=\"\t\" plain=\"ok\"/>\n\t<item/>\n\t<item title='a\r\n\tb\n' escaped=\"x&#10;y\"/>\n</config>";
+	let doc = kiss_xml::parse_str(xml).unwrap();
+	let warnings: Vec<(&str, &str)> = doc.parse_warnings().iter()
+		.map(|w| (w.path.as_str(), &w.message[..w.message.find("' ").unwrap() + 1])).collect();
+	// once per attribute, regardless of how many whitespace characters it contains
+	assert_eq!(warnings, vec![
+		("/config[1]/item[1]", "value of attribute 'label'"),
+		("/config[1]/item[1]", "value of attribute 'sep'"),
+		("/config[1]/item[3]", "value of attribute 'title'"),
+	]);
+	assert!(doc.parse_warnings().iter().all(|w| w.kind == LintKind::RawWhitespaceInAttributeValue));
+	assert_eq!(doc.lint().len(), 3);
+	let item = doc.element_at_path("/config/item[3]").unwrap().unwrap();
+	assert_eq!(item.get_attr("escaped").unwrap(), "x\ny", "character reference not decoded");
+	// re-serialize with character references, which parses to the same DOM without warnings
+	let fixed = doc.to_string_with_options(&OutputOptions::new().escape_attribute_whitespace(true));
+	assert!(fixed.contains(r#"label="first line&#10;second line""#), "{}", fixed);
+	assert!(fixed.contains(r#"sep="&#9;""#), "{}", fixed);
+	let reparsed = kiss_xml::parse_str(&fixed).unwrap();
+	assert!(reparsed.parse_warnings().is_empty(), "{:?}", reparsed.parse_warnings());
+	assert_eq!(reparsed, doc);
+	// the default output writes whitespace as-is, including the decoded character reference
+	assert_eq!(kiss_xml::parse_str(doc.to_string()).unwrap().parse_warnings().len(), 4);
+}
+
+#[test]
+fn test_empty_attributes() {
+	use kiss_xml::dom::*;
+	use kiss_xml::lint::LintKind;
+	let xml = r#"<root xmlns:x="urn:x" id="">
+	<item id="" name="a" title=""/>
+	<item name="b" xmlns=""/>
+	<x:item name="" xmlns:y=""/>
+</root>"#;
+	let mut doc = kiss_xml::parse_str(xml).unwrap();
+	// detection
+	let root = doc.root_element();
+	assert_eq!(root.attrs_with_empty_values().collect::<Vec<_>>(), vec!["id"]);
+	let items: Vec<&Element> = root.child_elements().collect();
+	assert_eq!(items[0].attrs_with_empty_values().collect::<Vec<_>>(), vec!["id", "title"]);
+	assert_eq!(items[1].attrs_with_empty_values().count(), 0);
+	assert_eq!(items[2].attrs_with_empty_values().collect::<Vec<_>>(), vec!["name"]);
+	let warnings: Vec<(String, String)> = doc.lint().into_iter()
+		.filter(|w| w.kind == LintKind::EmptyAttributeValue)
+		.map(|w| (w.path, w.message)).collect();
+	assert_eq!(warnings, vec![
+		("/root[1]".to_string(), "attribute 'id' has an empty value".to_string()),
+		("/root[1]/item[1]".to_string(), "attribute 'id' has an empty value".to_string()),
+		("/root[1]/item[1]".to_string(), "attribute 'title' has an empty value".to_string()),
+		("/root[1]/item[3]".to_string(), "attribute 'name' has an empty value".to_string()),
+	]);
+	// removal
+	let mut first = items[0].clone();
+	assert_eq!(first.remove_empty_attrs(), 2);
+	assert_eq!(first.remove_empty_attrs(), 0);
+	assert_eq!(doc.remove_empty_attrs(), 4);
+	assert_eq!(doc.remove_empty_attrs(), 0);
+	assert!(doc.lint().iter().all(|w| w.kind != LintKind::EmptyAttributeValue));
+	// namespace undeclarations are kept by default
+	let items: Vec<&Element> = doc.root_element().child_elements().collect();
+	assert_eq!(items[1].get_attr("xmlns").map(String::as_str), Some(""));
+	assert_eq!(items[2].get_attr("xmlns:y").map(String::as_str), Some(""));
+	assert!(items[2].namespace_prefixes().unwrap_or_default().contains_key("y"));
+	// ...but can be removed explicitly, which updates the namespace context
+	let mut x_item = items[2].clone();
+	assert_eq!(x_item.retain_attrs(|_, v| !v.is_empty()), 1);
+	assert!(!x_item.namespace_prefixes().unwrap_or_default().contains_key("y"));
+	assert_eq!(x_item.namespace().as_deref(), Some("urn:x"));
+}
+
+#[test]
+fn test_children_windows() {
+	use kiss_xml::dom::*;
+	use kiss_xml::errors::IndexOutOfBounds;
+	// 10k children: elements, with a comment after every 100th element
+	let mut wide = Element::new_from_name("wide").unwrap();
+	for i in 0..10_000 {
+		wide.append(Element::new_with_text("i", i.to_string()).unwrap());
+		if i % 100 == 99 {
+			wide.append(Comment::new(format!("after {i}")).unwrap());
+		}
+	}
+	assert_eq!(wide.children().count(), 10_100);
+	// interior windows of nodes
+	let window: Vec<String> = wide.children_range(1000..1005).unwrap().map(|n| n.to_string()).collect();
+	assert_eq!(window, vec!["<i>991</i>", "<i>992</i>", "<i>993</i>", "<i>994</i>", "<i>995</i>"]);
+	let window: Vec<String> = wide.children_range(99..102).unwrap().map(|n| n.to_string()).collect();
+	assert_eq!(window, vec!["<i>99</i>", "<!--after 99-->", "<i>100</i>"]);
+	// interior windows of elements skip the comments
+	let window: Vec<String> = wide.child_elements_range(1000..1050).unwrap().map(|e| e.text()).collect();
+	assert_eq!(window, (1000..1050).map(|i| i.to_string()).collect::<Vec<String>>());
+	// end of range is clamped, start of range is checked
+	assert_eq!(wide.children_range(10_090..10_200).unwrap().count(), 10);
+	assert_eq!(wide.children_range(10_100..10_200).unwrap().count(), 0);
+	assert_eq!(wide.children_range(500..400).unwrap().count(), 0);
+	assert_eq!(wide.child_elements_range(9_998..20_000).unwrap().last().unwrap().text(), "9999");
+	assert_eq!(wide.child_elements_range(10_000..10_001).unwrap().count(), 0);
+	match wide.children_range(10_101..10_200) {
+		Err(IndexOutOfBounds{index, bounds}) => {
+			assert_eq!(index, 10_101);
+			assert_eq!(bounds, Some((0, 10_100)));
+		},
+		Ok(_) => panic!("expected an IndexOutOfBounds error")
+	}
+	match wide.child_elements_range(10_001..10_002) {
+		Err(e) => assert_eq!(e.bounds, Some((0, 10_000))),
+		Ok(_) => panic!("expected an IndexOutOfBounds error")
+	}
+	// chunks, including the last partial chunk
+	let chunks: Vec<&[Box<dyn Node>]> = wide.children_chunks(1_000).collect();
+	assert_eq!(chunks.len(), 11);
+	assert!(chunks[..10].iter().all(|c| c.len() == 1_000));
+	assert_eq!(chunks[10].len(), 100);
+	assert_eq!(chunks[3][0].to_string(), "<i>2971</i>");
+	assert_eq!(chunks[10].last().unwrap().to_string(), "<!--after 9999-->");
+	assert_eq!(chunks.iter().map(|c| c.len()).sum::<usize>(), 10_100);
+	assert_eq!(Element::new_from_name("empty").unwrap().children_chunks(10).count(), 0);
+}
+
+#[test]
+fn test_fragment_documents() {
+	use kiss_xml::dom::*;
+	use kiss_xml::OutputOptions;
+	// load-edit-save of an include file keeps it declaration-less and newline-less
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("include.xml");
+	let original = std::fs::read_to_string("tests/corpus/include.xml").unwrap();
+	assert!(!original.ends_with('\n'));
+	std::fs::write(&path, &original).unwrap();
+	let mut doc = kiss_xml::parse_filepath(&path).unwrap();
+	assert!(doc.is_fragment());
+	assert!(doc.declaration().is_none());
+	doc.write_to_filepath(&path).unwrap();
+	assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+	doc.root_element_mut().first_element_by_name_mut("mute").unwrap().set_text("true");
+	doc.write_to_filepath(&path).unwrap();
+	assert_eq!(std::fs::read_to_string(&path).unwrap(), original.replace("false", "true"));
+	// constructed fragments
+	let fragment = Document::new_fragment(Element::new_with_children("sound", vec![
+		Element::new_from_name("mute").unwrap().boxed()
+	]).unwrap());
+	assert!(fragment.is_fragment());
+	assert_eq!(fragment.to_string(), "<sound>\n  <mute/>\n</sound>");
+	// an explicit trailing_newline option applies to fragments too
+	assert_eq!(fragment.to_string_with_options(&OutputOptions::new().trailing_newline(Some(true))), "<sound>\n  <mute/>\n</sound>\n");
+	assert_eq!(fragment.to_string_with_options(&OutputOptions::new().trailing_newline(Some(false))), "<sound>\n  <mute/>\n</sound>");
+	// documents with a declaration or a trailing newline are not fragments
+	for xml in ["<sound/>\n", "<?xml version=\"1.0\"?><sound/>", "<?xml version=\"1.0\"?>\n<sound/>\n"] {
+		let doc = kiss_xml::parse_str(xml).unwrap();
+		assert!(!doc.is_fragment(), "{xml:?} is not a fragment");
+		assert!(doc.to_string().ends_with("<sound/>\n"));
+		assert!(doc.to_string_with_options(&OutputOptions::new().trailing_newline(Some(false))).ends_with("<sound/>"));
+	}
+	// an explicit trailing newline restores the output of kiss-xml 1.0 for parsed fragments
+	let doc = kiss_xml::parse_str(original.as_str()).unwrap();
+	assert!(doc.is_fragment());
+	assert_eq!(doc.to_string_with_options(&OutputOptions::new().trailing_newline(Some(true))), format!("{original}\n"));
+	assert!(!Document::new(Element::new_from_name("sound").unwrap()).is_fragment());
+	// switching between fragments and documents
+	let mut doc = kiss_xml::parse_str("<?xml version=\"1.0\"?>\n<sound/>\n").unwrap();
+	doc.set_fragment(true);
+	assert!(doc.declaration().is_none());
+	assert_eq!(doc.to_string(), "<sound/>");
+	doc.set_declaration(Declaration::default());
+	assert!(!doc.is_fragment());
+	assert_eq!(doc.to_string(), "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<sound/>\n");
+	// being a fragment is a formatting property, so it does not affect equality
+	let mut a = kiss_xml::parse_str("<sound/>").unwrap();
+	let b = kiss_xml::parse_str("<sound/>\n").unwrap();
+	assert!(a == b);
+	assert!(!a.identical_to(&b));
+	a.set_fragment(false);
+	assert!(a.identical_to(&b));
+}
+
+#[test]
+fn test_attribute_values_are_unescaped_when_parsed() {
+	// attribute values used to be stored as written in the source, so escaped characters were
+	// escaped a second time when written (eg `x &amp; y` was written as `x &amp;amp; y`)
+	let xml = r#"<a note="x &amp; y" quote='say &quot;hi&quot;' lt="a &lt; b"/>"#;
+	let doc = kiss_xml::parse_str(xml).unwrap();
+	let root = doc.root_element();
+	assert_eq!(root.get_attr("note").unwrap(), "x & y");
+	assert_eq!(root.get_attr("quote").unwrap(), "say \"hi\"");
+	assert_eq!(root.get_attr("lt").unwrap(), "a < b");
+	assert_eq!(root.to_string(), r#"<a lt="a &lt; b" note="x &amp; y" quote="say &quot;hi&quot;"/>"#);
+	let reparsed = kiss_xml::parse_str(root.to_string()).unwrap();
+	assert_eq!(reparsed.root_element().get_attr("note").unwrap(), "x & y");
+	assert_eq!(reparsed, doc);
+	// malformed attributes are still rejected
+	for bad in ["<a b=c/>", "<a =\"x\"/>", "<a b=/>", "<a b/>"] {
+		assert!(kiss_xml::parse_str(bad).is_err(), "{bad} should not parse");
+	}
+}
+
+#[test]
+fn test_only_text_child_is_escaped() {
+	use kiss_xml::dom::*;
+	// an element whose only child is a text node used to write that text without escaping it, eg
+	// `<formula>x < y & y > z</formula>`, which is not well-formed XML
+	let e = Element::new_with_text("formula", "x < y & y > z").unwrap();
+	assert_eq!(e.to_string(), "<formula>x &lt; y &amp; y &gt; z</formula>");
+	let doc = Document::new(e);
+	let reparsed = kiss_xml::parse_str(doc.to_string()).unwrap();
+	assert_eq!(reparsed.root_element().text(), "x < y & y > z");
+	// the same text next to other children was always escaped
+	let mut e = Element::new_with_text("formula", "x < y").unwrap();
+	e.append(Element::new_from_name("br").unwrap());
+	assert_eq!(e.to_string(), "<formula>x &lt; y<br/></formula>");
+}
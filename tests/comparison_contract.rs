@@ -0,0 +1,72 @@
+//! Tests for the equality contract of Document (see the PartialEq impl for Document)
+
+use kiss_xml::dom::*;
+
+fn doc(xml: &str) -> Document {
+	kiss_xml::parse_str(xml).unwrap()
+}
+
+#[test]
+fn test_root_element() {
+	// attribute order is ignored
+	assert_eq!(doc(r#"<a x="1" y="2"/>"#), doc(r#"<a y="2" x="1"/>"#));
+	// child order is not
+	assert_ne!(doc("<a><b/><c/></a>"), doc("<a><c/><b/></a>"));
+	assert_ne!(doc(r#"<a x="1"/>"#), doc(r#"<a x="2"/>"#));
+}
+
+#[test]
+fn test_declaration() {
+	let explicit = doc("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<a/>");
+	let defaulted = Document::new(Element::new_from_name("a").unwrap());
+	assert_eq!(explicit, defaulted);
+	assert!(explicit.identical_to(&defaulted));
+	// presence is significant
+	let absent = doc("<a/>");
+	assert!(absent.declaration().is_none());
+	assert_ne!(absent, defaulted);
+	// declarations are not interpreted, only compared as text
+	assert_ne!(doc("<?xml version=\"1.0\"?>\n<a/>"), explicit);
+	assert_ne!(doc("<?xml version='1.0' encoding='UTF-8'?>\n<a/>"), explicit);
+}
+
+#[test]
+fn test_dtd_order() {
+	let ab = doc("<!DOCTYPE a>\n<!DOCTYPE b>\n<a/>");
+	let ba = doc("<!DOCTYPE b>\n<!DOCTYPE a>\n<a/>");
+	assert_eq!(ab.doctype_defs().count(), 2);
+	assert_ne!(ab, ba);
+	assert_eq!(ab, doc("<!DOCTYPE a>\n<!DOCTYPE b>\n<a/>"));
+	assert_ne!(ab, doc("<!DOCTYPE a>\n<a/>"));
+}
+
+#[test]
+fn test_dtd_formatting() {
+	let parsed = doc("<!DOCTYPE  a  SYSTEM \"a.dtd\" >\n<a/>");
+	let built = Document::new_with_decl_dtd(
+		Element::new_from_name("a").unwrap(), None,
+		Some(&[DTD::new("a", Some("SYSTEM \"a.dtd\"".to_string()), None)])
+	);
+	assert_eq!(parsed, built);
+	assert!(!parsed.identical_to(&built));
+	assert_ne!(parsed, doc("<!DOCTYPE a SYSTEM \"b.dtd\">\n<a/>"));
+	assert_ne!(parsed, doc("<!DOCTYPE a SYSTEM \"a.dtd\" [<!ENTITY x \"y\">]>\n<a/>"));
+}
+
+#[test]
+fn test_prologue_markup() {
+	let pi = doc("<?style x?>\n<a/>");
+	assert_eq!(pi, doc("<?style x?>\n\n\n<a/>"), "source offsets are not compared");
+	assert_ne!(pi, doc("<?style y?>\n<a/>"));
+	assert_ne!(pi, doc("<a/>\n<?style x?>"), "prologue and epilogue placement differs");
+	assert_ne!(pi, doc("<a/>"));
+}
+
+#[test]
+fn test_source_spans_ignored() {
+	let xml = "<a><b/></a>";
+	let with_spans = kiss_xml::parse_str_with_options(xml, &kiss_xml::ParseOptions::new().source_spans(true)).unwrap();
+	assert!(with_spans.source_spans().is_some());
+	assert_eq!(with_spans, doc(xml));
+	assert!(with_spans.identical_to(&doc(xml)));
+}
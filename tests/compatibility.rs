@@ -0,0 +1,97 @@
+//! Tests that older and newer forms of the public API agree with each other, so that breaking changes for downstream users are caught here first
+
+fn sample_config() -> kiss_xml::dom::Document {
+	kiss_xml::parse_str(r#"<?xml version="1.0" encoding="UTF-8"?>
+<config>
+	<name>My Settings</name>
+	<sound>
+		<property name="volume" value="11" />
+		<property name="mixer" value="standard" />
+	</sound>
+</config>
+"#).unwrap()
+}
+
+#[test]
+fn test_element_lookup_option_and_result() {
+	use kiss_xml::dom::Node;
+	use kiss_xml::errors::DoesNotExistError;
+	let mut doc = sample_config();
+	let root = doc.root_element();
+	// Result form
+	let sound: Result<&kiss_xml::dom::Element, DoesNotExistError> = root.first_element_by_name("sound");
+	assert_eq!(sound.unwrap().name(), "sound");
+	assert!(root.first_element_by_name("video").is_err());
+	// Option form
+	assert_eq!(root.find_element_by_name("sound").map(|e| e.name()), Some("sound".to_string()));
+	assert!(root.find_element_by_name("video").is_none());
+	// both forms find the same element
+	assert_eq!(root.find_element_by_name("name"), root.first_element_by_name("name").ok());
+	// mutable variants
+	doc.root_element_mut().first_element_by_name_mut("name").unwrap().set_text("A");
+	assert_eq!(doc.root_element().first_element_by_name("name").unwrap().text(), "A");
+	doc.root_element_mut().find_element_by_name_mut("name").unwrap().set_text("B");
+	assert_eq!(doc.root_element().find_element_by_name("name").unwrap().text(), "B");
+	assert!(doc.root_element_mut().first_element_by_name_mut("video").is_err());
+	assert!(doc.root_element_mut().find_element_by_name_mut("video").is_none());
+}
+
+#[test]
+fn test_attribute_lookup_option_and_result() {
+	use kiss_xml::errors::{DoesNotExistError, KissXmlError};
+	let doc = sample_config();
+	let prop = doc.root_element().first_element_by_name("sound").unwrap()
+		.first_element_by_name("property").unwrap();
+	// Option form
+	assert_eq!(prop.get_attr("value"), Some(&"11".to_string()));
+	assert_eq!(prop.get_attr("units"), None);
+	// Result form
+	assert_eq!(prop.require_attr("value").unwrap(), "11");
+	let err: DoesNotExistError = prop.require_attr("units").unwrap_err();
+	assert!(err.msg.contains("units"), "error message should name the missing attribute: {}", err);
+	// the older documented pattern still works and agrees with the new one
+	assert_eq!(prop.get_attr("name").ok_or(DoesNotExistError::default()).unwrap(), prop.require_attr("name").unwrap());
+	// both forms work with ? in a function returning KissXmlError
+	fn volume(doc: &kiss_xml::dom::Document) -> Result<String, KissXmlError> {
+		Ok(doc.root_element().first_element_by_name("sound")?
+			.first_element_by_name("property")?
+			.require_attr("value")?.clone())
+	}
+	assert_eq!(volume(&doc).unwrap(), "11");
+}
+
+#[test]
+fn test_text_returns_string() {
+	// older docs showed `.text().unwrap()`; text() returns a String for all node types
+	use kiss_xml::dom::Node;
+	let doc = sample_config();
+	let name: String = doc.root_element().first_element_by_name("name").unwrap().text();
+	assert_eq!(name, "My Settings");
+	for c in doc.root_element().children() {
+		let _: String = c.text();
+	}
+}
+
+#[test]
+fn test_conveniences_from_older_examples() {
+	use kiss_xml::dom::*;
+	let mut doc = sample_config();
+	let root = doc.root_element();
+	// `children().next()`
+	let first = root.first_child_node().unwrap();
+	assert!(first.is_element());
+	assert_eq!(first.as_element().unwrap().name(), "name");
+	assert_eq!(root.first_child_node().map(|n| n.text()), root.children().next().map(|n| n.text()));
+	assert!(root.first_element_by_name("sound").unwrap().first_element_by_name("property").unwrap().first_child_node().is_none());
+	// `text().unwrap()` on an element
+	assert_eq!(root.first_element_by_name("name").unwrap().direct_text().unwrap(), "My Settings");
+	assert_eq!(root.direct_text(), None);
+	// `append(Comment::new(...))` without unwrapping
+	doc.root_element_mut().append(Comment::from_str_lossy("ok"));
+	doc.root_element_mut().append(Comment::from_str_lossy("a-->b--->c"));
+	let comments: Vec<String> = doc.root_element().children().filter(|n| n.is_comment()).map(|n| n.text()).collect();
+	assert_eq!(comments, vec!["ok".to_string(), "a-- >b--- >c".to_string()]);
+	assert_eq!(Comment::from_str_lossy("ok"), Comment::new("ok").unwrap());
+	let reparsed = kiss_xml::parse_str(doc.to_string()).unwrap();
+	assert_eq!(reparsed, doc);
+}
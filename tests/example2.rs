@@ -1,3 +1,4 @@
+//! Example usage test
 
 #[test]
 fn example1() {
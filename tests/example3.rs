@@ -1,3 +1,4 @@
+//! Example usage test
 
 #[test]
 fn example3() -> Result<(), kiss_xml::errors::KissXmlError>{
@@ -10,6 +11,7 @@ fn example3() -> Result<(), kiss_xml::errors::KissXmlError>{
 	// print to terminal
 	println!("{}", doc.root_element());
 	// write to file
-	doc.write_to_filepath("politics.xml")?;
+	let dir = tempfile::tempdir()?;
+	doc.write_to_filepath(dir.path().join("politics.xml"))?;
 	Ok(())
 }
@@ -1,4 +1,4 @@
-
+//! Tests of parser failure handling
 #[test]
 fn test_unclosed_root() {
 	use kiss_xml;
@@ -15,4 +15,251 @@ fn test_unclosed_root() {
 	).is_err(),
 	"Should have errored due to unclosed root element"
 	)
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_max_input_bytes() {
+	use kiss_xml;
+	use kiss_xml::errors::KissXmlError;
+	use std::io::{Read, Write};
+	use tempfile::tempdir;
+	let limit: usize = 1000;
+	let mut xml = String::from("<root>");
+	while xml.len() < limit - 6 {xml.push_str("<a/>");}
+	xml.push_str("</root>");
+	assert!(xml.len() > limit && xml.len() < limit + 10, "bad test setup");
+	// file just over the limit
+	let dir = tempdir().unwrap();
+	let file_path = dir.path().join("big.xml");
+	let mut tmpfile = std::fs::File::create(&file_path).unwrap();
+	write!(tmpfile, "{}", xml).unwrap();
+	drop(tmpfile);
+	assert!(kiss_xml::parse_filepath(&file_path).is_ok(), "file should parse without a limit");
+	assert!(
+		matches!(kiss_xml::parse_filepath_limited(&file_path, limit), Err(KissXmlError::LimitExceeded(_))),
+		"file over the size limit should produce a LimitExceeded error"
+	);
+	assert!(kiss_xml::parse_filepath_limited(&file_path, xml.len()).is_ok(), "file at the size limit should parse");
+	// a stream that never ends
+	struct EndlessStream {consumed: usize}
+	impl Read for EndlessStream {
+		fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+			let n = buf.len().min(64);
+			buf[..n].fill(b' ');
+			self.consumed += n;
+			Ok(n)
+		}
+	}
+	let mut stream = EndlessStream{consumed: 0};
+	let options = kiss_xml::ParseOptions{max_input_bytes: Some(limit), ..Default::default()};
+	let result = kiss_xml::parse_stream_with_options(&mut stream, &options);
+	assert!(matches!(result, Err(KissXmlError::LimitExceeded(_))), "endless stream should produce a LimitExceeded error");
+	assert!(stream.consumed <= limit + 64, "read {} bytes from stream, more than the limit plus one chunk", stream.consumed);
+	// in-memory strings are checked too
+	assert!(matches!(kiss_xml::parse_str_with_options(xml.as_str(), &options), Err(KissXmlError::LimitExceeded(_))));
+}
+
+#[test]
+fn test_unterminated_doctype() {
+	use kiss_xml;
+	use kiss_xml::errors::KissXmlError;
+	// internal subset is never closed, so the brackets never balance
+	let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE config [
+	<!ENTITY name "My Settings" >
+	<!ELEMENT config (name)
+<config>
+	<name>&name;</name>
+</config>
+"#;
+	let result = kiss_xml::parse_str(xml);
+	match result {
+		Err(KissXmlError::ParsingError(e)) => {
+			let msg = format!("{}", e);
+			assert!(msg.contains("unterminated DOCTYPE starting at line 2, column 1"), "wrong error message: {}", msg);
+		},
+		other => panic!("expected a ParsingError, got {:?}", other)
+	}
+	// a length cap stops the scan early
+	let options = kiss_xml::ParseOptions{max_dtd_bytes: 16, ..Default::default()};
+	assert!(matches!(
+		kiss_xml::parse_str_with_options(r#"<!DOCTYPE config SYSTEM "config.dtd"><config/>"#, &options),
+		Err(KissXmlError::ParsingError(_))
+	), "DOCTYPE longer than max_dtd_bytes should be rejected");
+	assert!(kiss_xml::parse_str(r#"<!DOCTYPE config SYSTEM "config.dtd"><config/>"#).is_ok());
+}
+
+#[test]
+fn test_deeply_nested_doctype() {
+	use kiss_xml;
+	use kiss_xml::errors::KissXmlError;
+	let depth = 100_000;
+	let mut xml = String::from("<!DOCTYPE a [");
+	for _ in 0..depth {xml.push_str("<!ENTITY ");}
+	for _ in 0..depth {xml.push('>');}
+	xml.push_str("]>\n<a/>");
+	match kiss_xml::parse_str(xml.as_str()) {
+		Err(KissXmlError::ParsingError(e)) => {
+			let msg = format!("{}", e);
+			assert!(msg.contains("unterminated DOCTYPE starting at line 1, column 1"), "wrong error message: {}", msg);
+		},
+		other => panic!("expected a ParsingError, got {:?}", other)
+	}
+	// within the depth limit, the same construct is fine
+	let options = kiss_xml::ParseOptions{max_dtd_depth: depth, ..Default::default()};
+	assert!(kiss_xml::parse_str_with_options(xml.as_str(), &options).is_ok());
+}
+
+#[test]
+fn test_error_positions() {
+	use kiss_xml;
+	fn error_message(xml: &str) -> String {
+		kiss_xml::parse_str(xml).expect_err("should not parse").to_string()
+	}
+	let msg = error_message("<root>\n\t<a>\n\t<1b/>\n</root>");
+	assert!(msg.contains("line 3, column 2"), "wrong position: {}", msg);
+	let msg = error_message("<root><a/>\n</root>\n</b>");
+	assert!(msg.contains("line 3, column 1"), "wrong position: {}", msg);
+	let msg = error_message("<röot>ünïcödé<1b/></röot>");
+	assert!(msg.contains("line 1, column 14"), "wrong position: {}", msg);
+}
+
+#[test]
+fn test_long_line_errors() {
+	use kiss_xml;
+	use std::time::{Duration, Instant};
+	// minified (single line) 5 MB document with an error at the very end
+	let mut xml = String::from("<root>");
+	while xml.len() < 5 * 1024 * 1024 {
+		xml.push_str("<item id=\"12345\">some text</item>");
+	}
+	let error_pos = xml.chars().count() + 1;
+	xml.push_str("<1bad/></root>");
+	let start = Instant::now();
+	let msg = kiss_xml::parse_str(xml.as_str()).expect_err("should not parse").to_string();
+	assert!(msg.contains(&format!("line 1, column {error_pos}")), "wrong position: {}", msg);
+	assert!(start.elapsed() < Duration::from_secs(60), "parsing took {:?}", start.elapsed());
+	// many failed parses of long lines with the same parser
+	let mut parser = kiss_xml::Parser::new();
+	let mut line = String::from("<root>");
+	while line.len() < 5 * 1024 {
+		line.push_str("<item id=\"12345\">some text</item>");
+	}
+	let start = Instant::now();
+	for i in 0..1000 {
+		let doc = format!("{line}<{i}/></root>");
+		let msg = parser.parse(doc.as_str()).expect_err("should not parse").to_string();
+		assert!(msg.contains(&format!("line 1, column {}", line.len() + 1)), "wrong position: {}", msg);
+	}
+	assert!(start.elapsed() < Duration::from_secs(60), "parsing took {:?}", start.elapsed());
+}
+
+#[test]
+fn test_max_depth() {
+	use kiss_xml;
+	use kiss_xml::dom::Node;
+	use kiss_xml::errors::KissXmlError;
+	let limit: usize = 20;
+	// nested document twice as deep as the limit
+	let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+	for i in 0..(2 * limit) {xml.push_str(&format!("<e{i}>"));}
+	xml.push_str("text");
+	for i in (0..(2 * limit)).rev() {xml.push_str(&format!("</e{i}>"));}
+	assert!(kiss_xml::parse_str(xml.as_str()).is_ok());
+	let options = kiss_xml::ParseOptions{max_depth: Some(limit), ..Default::default()};
+	assert!(kiss_xml::parse_str_with_options(xml.as_str(), &kiss_xml::ParseOptions{max_depth: Some(2 * limit), ..Default::default()}).is_ok());
+	let error_pos = xml.find(&format!("<e{limit}>")).unwrap();
+	match kiss_xml::parse_str_with_options(xml.as_str(), &options) {
+		Err(KissXmlError::LimitExceeded(e)) => {
+			assert_eq!(e.open_elements.len(), limit);
+			assert_eq!(e.open_elements.first().unwrap(), "e0");
+			assert_eq!(e.open_elements.last().unwrap(), &format!("e{}", limit - 1));
+			assert_eq!(e.position, Some(error_pos));
+			assert!(e.msg.contains("line 2"), "message should give the position: {}", e.msg);
+			assert!(e.partial_document.is_none());
+		},
+		other => panic!("expected a LimitExceeded error, got {:?}", other)
+	}
+	// with partial document
+	let options = kiss_xml::ParseOptions{max_depth: Some(limit), return_partial_on_limit: true, ..Default::default()};
+	match kiss_xml::parse_str_with_options(xml.as_str(), &options) {
+		Err(KissXmlError::LimitExceeded(e)) => {
+			assert_eq!(e.open_elements.len(), limit);
+			let partial = e.partial_document.expect("should have a partial document");
+			assert_eq!(partial.root_element().name(), "e0");
+			assert_eq!(partial.root_element().self_and_descendants().count(), limit);
+			assert!(partial.declaration().is_some());
+			assert_eq!(partial.root_element().text(), "");
+		},
+		other => panic!("expected a LimitExceeded error, got {:?}", other)
+	}
+	// self-closing elements count too, and siblings that were already closed are kept
+	let xml = "<a><b><c/></b><b><c><d/></c></b></a>";
+	let options = kiss_xml::ParseOptions{max_depth: Some(3), return_partial_on_limit: true, ..Default::default()};
+	match kiss_xml::parse_str_with_options(xml, &options) {
+		Err(KissXmlError::LimitExceeded(e)) => {
+			assert_eq!(e.open_elements, vec!["a", "b", "c"]);
+			assert_eq!(e.position, xml.find("<d/>"));
+			assert_eq!(e.partial_document.unwrap().root_element().to_string(), "<a>\n  <b>\n    <c/>\n  </b>\n  <b>\n    <c/>\n  </b>\n</a>");
+		},
+		other => panic!("expected a LimitExceeded error, got {:?}", other)
+	}
+	assert!(kiss_xml::parse_str_with_options(xml, &kiss_xml::ParseOptions{max_depth: Some(4), ..Default::default()}).is_ok());
+}
+
+#[test]
+fn test_error_report() {
+	use kiss_xml::errors::{ErrorReport, KissXmlError};
+	let xml = "<?xml version=\"1.0\"?>\n<config>\n\t<sound>\n\t\t<property name=\"volume\"></prop>\n\t</sound>\n</config>";
+	let report = kiss_xml::parse_str_report(xml).unwrap_err();
+	assert_eq!(report, ErrorReport{
+		kind: "ParsingError".into(),
+		message: "closing tag </prop> does not match <property> (syntax error on line 4, column 27)".into(),
+		line: Some(4),
+		column: Some(27),
+		offset: xml.find("</prop>"),
+		path: Some("/config/sound/property".into()),
+	});
+	assert_eq!(report.to_string(), format!("ParsingError: {}", report.message));
+	// the same fields are available on the error itself
+	match kiss_xml::parse_str(xml) {
+		Err(KissXmlError::ParsingError(e)) => {
+			assert_eq!(e.position(), report.offset);
+			assert_eq!((e.line(), e.column()), (Some(4), Some(27)));
+			assert_eq!(e.open_elements(), ["config", "sound", "property"]);
+		},
+		other => panic!("expected a ParsingError, got {:?}", other)
+	}
+	// errors outside of the elements have no path, and unclosed elements are reported at the end
+	let report = kiss_xml::parse_str_report("</a>").unwrap_err();
+	assert_eq!((report.line, report.column, report.offset, report.path), (Some(1), Some(1), Some(0), None));
+	let report = kiss_xml::parse_str_report("<a>\n<b>").unwrap_err();
+	assert_eq!((report.line, report.column, report.offset), (Some(2), Some(4), Some(7)));
+	assert_eq!(report.path.as_deref(), Some("/a/b"));
+	// a parser that is re-used after an error does not report stale open elements
+	let mut parser = kiss_xml::Parser::new();
+	assert!(parser.parse("<a><b>").is_err());
+	let report = parser.parse("text").unwrap_err().report();
+	assert_eq!(report.path, None);
+	// errors that are not about a position in the XML only have a kind and message
+	let report = KissXmlError::from(kiss_xml::errors::DoesNotExistError::new("no such thing")).report();
+	assert_eq!(report, ErrorReport{
+		kind: "DoesNotExistError".into(), message: "no such thing".into(),
+		line: None, column: None, offset: None, path: None
+	});
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_error_report_json() {
+	use kiss_xml::errors::ErrorReport;
+	let err = kiss_xml::parse_str("<a>\n  <b></c>\n</a>").unwrap_err();
+	let json = err.to_json();
+	assert_eq!(json, r#"{"kind":"ParsingError","message":"closing tag </c> does not match <b> (syntax error on line 2, column 6)","line":2,"column":6,"offset":9,"path":"/a/b"}"#);
+	let parsed: ErrorReport = serde_json::from_str(&json).unwrap();
+	assert_eq!(parsed, err.report());
+	let err = kiss_xml::parse_str("").unwrap_err();
+	let parsed: ErrorReport = serde_json::from_str(&err.to_json()).unwrap();
+	assert_eq!(parsed, err.report());
+	assert_eq!(parsed.line, None);
+}
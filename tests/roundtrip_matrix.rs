@@ -0,0 +1,120 @@
+//! Round-trip tests of the corpus in `tests/corpus/`, parsing and serializing each fixture under a
+//! matrix of parse and output options and checking the outcome of every combination against a
+//! table of expectations
+
+use kiss_xml::{OutputOptions, ParseOptions};
+
+/// What happens to a fixture when it is parsed and then serialized again
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Outcome {
+	/// the output is exactly the same as the input
+	ByteIdentical,
+	/// the output is different from the input, but it has the same content: it parses to the same
+	/// DOM and none of the input markup was dropped
+	Equivalent,
+	/// some content is lost, for the given reason
+	KnownLossy(&'static str),
+}
+
+use Outcome::*;
+
+/// The option combinations (columns of the matrix)
+fn option_matrix() -> Vec<(&'static str, ParseOptions, OutputOptions)> {
+	vec![
+		("default", ParseOptions::new(), OutputOptions::new()),
+		("tab indent", ParseOptions::new(), OutputOptions::new().indent("\t")),
+		("space before />", ParseOptions::new(), OutputOptions::new().space_before_self_close(true)),
+		("preserve CR", ParseOptions::new().preserve_carriage_returns(true), OutputOptions::new()),
+		("source spans", ParseOptions::new().source_spans(true), OutputOptions::new()),
+		("preserve CR, tab indent, space before />",
+			ParseOptions::new().preserve_carriage_returns(true),
+			OutputOptions::new().indent("\t").space_before_self_close(true)),
+	]
+}
+
+/// counts the occurrences of markup that the DOM comparison cannot see (because the parser
+/// drops it), so that content lost while parsing is not reported as equivalent
+fn markup_counts(xml: &str) -> [usize; 4] {
+	[xml.matches("<!--").count(), xml.matches("<![CDATA[").count(), xml.matches("<!DOCTYPE").count(), xml.matches("<?").count()]
+}
+
+/// parses and serializes the given XML, and classifies the result (the reason for lossy results
+/// is left blank, to be filled in from the table)
+fn round_trip(xml: &str, parse_options: &ParseOptions, output_options: &OutputOptions) -> Outcome {
+	let doc = kiss_xml::parse_str_with_options(xml, parse_options).expect("fixture should parse");
+	let output = doc.to_string_with_options(output_options);
+	if output == xml {
+		return ByteIdentical;
+	}
+	let doc2 = kiss_xml::parse_str_with_options(output.as_str(), parse_options).expect("output should parse");
+	if doc2 == doc && markup_counts(xml) == markup_counts(output.as_str()) {
+		Equivalent
+	} else {
+		KnownLossy("")
+	}
+}
+
+/// Comments before or after the root element are dropped by the parser with a warning.
+/// Tracking: "What's NOT included" in the crate documentation; remove this entry once
+/// comments outside the root element are kept as opaque markup like other prologue markup.
+const PROLOGUE_COMMENT_DROPPED: Outcome = KnownLossy("comments outside the root element are not supported");
+
+/// Expected outcome of each fixture (rows) under each option combination from `option_matrix()` (columns)
+fn expectations() -> Vec<(&'static str, [Outcome; 6])> {
+	vec![
+		// canonical two-space indented output, only changes when the output options do
+		("config.xml", [ByteIdentical, Equivalent, Equivalent, ByteIdentical, ByteIdentical, Equivalent]),
+		// written with tabs and a space before '/>'
+		("tabbed.xml", [Equivalent, Equivalent, Equivalent, Equivalent, Equivalent, ByteIdentical]),
+		// \r\n line endings are normalized in text (or kept in text, but not between elements)
+		("windows.xml", [Equivalent, Equivalent, Equivalent, Equivalent, Equivalent, Equivalent]),
+		// mixed content is written inline, as in the source
+		("xhtml.xml", [ByteIdentical, Equivalent, Equivalent, ByteIdentical, ByteIdentical, Equivalent]),
+		// attribute order, quote style, and character references are normalized
+		("feed.xml", [Equivalent, Equivalent, Equivalent, Equivalent, Equivalent, Equivalent]),
+		// a fragment without a declaration or a trailing newline, which is kept that way
+		("include.xml", [ByteIdentical, Equivalent, Equivalent, ByteIdentical, ByteIdentical, Equivalent]),
+		// output is always pretty-printed
+		("minified.xml", [Equivalent, Equivalent, Equivalent, Equivalent, Equivalent, Equivalent]),
+		("prologue-comment.xml", [
+			PROLOGUE_COMMENT_DROPPED, PROLOGUE_COMMENT_DROPPED, PROLOGUE_COMMENT_DROPPED,
+			PROLOGUE_COMMENT_DROPPED, PROLOGUE_COMMENT_DROPPED, PROLOGUE_COMMENT_DROPPED
+		]),
+	]
+}
+
+#[test]
+fn test_roundtrip_matrix() {
+	let matrix = option_matrix();
+	let mut failures: Vec<String> = Vec::new();
+	for (fixture, expected) in expectations() {
+		let xml = std::fs::read_to_string(format!("tests/corpus/{fixture}")).unwrap();
+		assert_eq!(expected.len(), matrix.len(), "wrong number of expectations for {fixture}");
+		for ((column, parse_options, output_options), expected) in matrix.iter().zip(expected) {
+			let actual = round_trip(xml.as_str(), parse_options, output_options);
+			let agrees = match (&expected, &actual) {
+				(KnownLossy(reason), KnownLossy(_)) => {
+					assert!(!reason.is_empty(), "{fixture} ({column}): lossy expectations need a reason");
+					true
+				},
+				_ => expected == actual
+			};
+			if !agrees {
+				failures.push(format!("{fixture} ({column}): expected {expected:?}, but was {actual:?} with {parse_options:?} and {output_options:?}"));
+			}
+		}
+	}
+	assert!(failures.is_empty(), "round trip outcomes do not match the table:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn test_corpus_is_covered() {
+	let mut fixtures: Vec<String> = std::fs::read_dir("tests/corpus").unwrap()
+		.map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+		.filter(|name| name.ends_with(".xml"))
+		.collect();
+	fixtures.sort();
+	let mut covered: Vec<String> = expectations().into_iter().map(|(name, _)| name.to_string()).collect();
+	covered.sort();
+	assert_eq!(fixtures, covered, "every corpus fixture needs a row in the round trip table");
+}
@@ -1,3 +1,4 @@
+//! Simple tests of DOM construction and serialization
 use kiss_xml;
 use kiss_xml::dom::*;
 use std::collections::HashMap;
@@ -201,6 +202,30 @@ fn test_clone_node() {
 	}
 }
 
+#[test]
+fn test_node_type_all_kinds() {
+	use kiss_xml;
+	use kiss_xml::dom::*;
+	let nodes: Vec<Box<dyn Node>> = vec![
+		Element::new_from_name("e").unwrap().boxed(),
+		Text::new("text").boxed(),
+		Comment::new("comment").unwrap().boxed(),
+		CData::new("<cdata>").unwrap().boxed(),
+	];
+	let expected = [DomNodeType::ElementNode, DomNodeType::TextNode, DomNodeType::CommentNode, DomNodeType::CDataNode];
+	for (n, t) in nodes.iter().zip(expected) {
+		assert_eq!(n.node_type(), t);
+		let copy = clone_node(n);
+		assert_eq!(copy.node_type(), t);
+		assert!(node_eq(n, &copy), "cloned {} node not equal to original", t);
+	}
+	for (i, a) in nodes.iter().enumerate() {
+		for (j, b) in nodes.iter().enumerate() {
+			assert_eq!(node_eq(a, b), i == j, "{} vs {}", a.node_type(), b.node_type());
+		}
+	}
+}
+
 #[test]
 fn test_leading_trailing_text() {
 	use kiss_xml;
@@ -0,0 +1,122 @@
+//! Snapshot tests of the serialized output of the corpus in `tests/corpus/`. Every fixture is
+//! parsed and written with each of the option sets from `snapshot_options()`, and the output must
+//! match the checked-in snapshot in `tests/snapshots/` byte for byte.
+//!
+//! Downstream users compare the XML written by kiss-xml against stored baselines, so a change to
+//! any snapshot is a change to the output format that must be called out in the release notes (and
+//! must not happen in a patch release). After making an intentional change to the output format,
+//! rewrite the snapshots by running the tests with `UPDATE_SNAPSHOTS=1`, eg
+//! `UPDATE_SNAPSHOTS=1 cargo test --test snapshots`, and review the diff of `tests/snapshots/`.
+
+use kiss_xml::OutputOptions;
+use std::path::{Path, PathBuf};
+
+const CORPUS_DIR: &str = "tests/corpus";
+const SNAPSHOT_DIR: &str = "tests/snapshots";
+
+/// The output options to take snapshots with (the name is part of the snapshot file name)
+fn snapshot_options() -> Vec<(&'static str, OutputOptions)> {
+	vec![
+		("default", OutputOptions::default()),
+		("custom", OutputOptions::new()
+			.indent("\t")
+			.space_before_self_close(true)
+			.omit_redundant_namespace_declarations(true)
+			.escape_attribute_whitespace(true)
+			.trailing_newline(Some(false))),
+	]
+}
+
+/// the corpus fixtures, sorted by name
+fn fixtures() -> Vec<String> {
+	let mut fixtures: Vec<String> = std::fs::read_dir(CORPUS_DIR).unwrap()
+		.map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+		.filter(|name| name.ends_with(".xml"))
+		.collect();
+	fixtures.sort();
+	fixtures
+}
+
+/// path of the snapshot of the given fixture with the given option set
+fn snapshot_path(fixture: &str, options_name: &str) -> PathBuf {
+	let stem = fixture.strip_suffix(".xml").unwrap_or(fixture);
+	Path::new(SNAPSHOT_DIR).join(format!("{stem}.{options_name}.xml"))
+}
+
+/// parses the fixture and writes it with the given options
+fn serialize(fixture: &str, options: &OutputOptions) -> String {
+	let xml = std::fs::read_to_string(Path::new(CORPUS_DIR).join(fixture)).unwrap();
+	kiss_xml::parse_str(xml).expect("fixture should parse").to_string_with_options(options)
+}
+
+#[test]
+fn test_snapshots() {
+	let update = std::env::var("UPDATE_SNAPSHOTS").is_ok_and(|v| v == "1");
+	let mut failures: Vec<String> = Vec::new();
+	for fixture in fixtures() {
+		for (options_name, options) in snapshot_options() {
+			let output = serialize(fixture.as_str(), &options);
+			let path = snapshot_path(fixture.as_str(), options_name);
+			if update {
+				std::fs::create_dir_all(SNAPSHOT_DIR).unwrap();
+				std::fs::write(&path, &output).unwrap();
+				continue;
+			}
+			match std::fs::read_to_string(&path) {
+				Ok(expected) if expected == output => {},
+				Ok(expected) => failures.push(format!(
+					"{} does not match the output for {fixture}:\n--- expected ---\n{expected}\n--- actual ---\n{output}",
+					path.display()
+				)),
+				Err(_) => failures.push(format!("missing snapshot {}", path.display())),
+			}
+		}
+	}
+	assert!(
+		failures.is_empty(),
+		"the serialized output changed (if this is intentional, re-run with UPDATE_SNAPSHOTS=1):\n{}",
+		failures.join("\n")
+	);
+}
+
+#[test]
+fn test_no_stale_snapshots() {
+	let mut expected: Vec<PathBuf> = fixtures().iter()
+		.flat_map(|fixture| snapshot_options().into_iter().map(|(name, _)| snapshot_path(fixture, name)))
+		.collect();
+	expected.sort();
+	let mut actual: Vec<PathBuf> = std::fs::read_dir(SNAPSHOT_DIR).unwrap()
+		.map(|entry| Path::new(SNAPSHOT_DIR).join(entry.unwrap().file_name()))
+		.collect();
+	actual.sort();
+	assert_eq!(actual, expected, "every snapshot needs a corpus fixture and option set (delete the others)");
+}
+
+#[test]
+fn test_output_is_deterministic() {
+	// every parse creates new hash maps with their own iteration order, so repeated parses and
+	// copies of the DOM would expose any output that depends on hash map iteration order
+	for fixture in fixtures() {
+		for (options_name, options) in snapshot_options() {
+			let first = serialize(fixture.as_str(), &options);
+			for _ in 0..20 {
+				assert_eq!(serialize(fixture.as_str(), &options), first, "{fixture} ({options_name})");
+			}
+			let xml = std::fs::read_to_string(Path::new(CORPUS_DIR).join(&fixture)).unwrap();
+			let doc = kiss_xml::parse_str(xml).unwrap();
+			let mut rebuilt = doc.clone();
+			// re-insert the attributes of every element in reverse order
+			let mut stack = vec![rebuilt.root_element_mut()];
+			while let Some(elem) = stack.pop() {
+				let attrs: Vec<(String, String)> = elem.sorted_attributes().into_iter().rev()
+					.map(|(k, v)| (k.clone(), v.clone())).collect();
+				elem.retain_attrs(|_, _| false);
+				for (k, v) in attrs {
+					elem.set_attr(k, v).unwrap();
+				}
+				stack.extend(elem.child_elements_mut());
+			}
+			assert_eq!(rebuilt.to_string_with_options(&options), first, "{fixture} ({options_name}, rebuilt)");
+		}
+	}
+}